@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+use log::{info, warn};
+use sensor_core::{SensorType, SensorValue};
+use serde_json::json;
+
+use crate::config::{AlertAction, AlertCondition, AlertRuleConfig};
+use crate::sensor;
+use crate::sensor_sampler::{SensorDemand, SensorSampler};
+use crate::utils::LockResultExt;
+
+/// Consumer id of the alert evaluation at the sensor sampler
+const CONSUMER_ID: &str = "alerts";
+
+/// Timeout after which the evaluation thread checks for a new sample again
+const SAMPLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Timeout of a webhook request
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+lazy_static! {
+    /// The state of each alert rule as sensor value, read by the alert sensor provider
+    static ref ALERT_SENSOR_VALUES: Mutex<Vec<SensorValue>> = Mutex::new(vec![]);
+}
+
+/// Exposes the state of each alert rule as sensor, 1 while the alert is active and 0 otherwise.
+/// A conditional image on the display can use these sensors to show an overlay.
+pub struct AlertSensors {}
+
+impl sensor::SensorProvider for AlertSensors {
+    fn get_name(&self) -> String {
+        "Alerts".to_string()
+    }
+
+    fn get_preferred_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn read(&self) -> Vec<SensorValue> {
+        ALERT_SENSOR_VALUES.lock().ignore_poison().clone()
+    }
+}
+
+/// State of a single alert rule
+#[derive(Default)]
+struct AlertState {
+    /// Since when the value is beyond the threshold, while the alert is not active
+    condition_since: Option<Instant>,
+    active: bool,
+    last_triggered_at: Option<Instant>,
+}
+
+/// Evaluates the alert rules against the sampled sensor values.
+#[derive(Default)]
+pub struct AlertEvaluator {
+    states: HashMap<String, AlertState>,
+}
+
+impl AlertEvaluator {
+    /// Updates the state of all enabled rules with the sensor values.
+    /// A rule becomes active once its condition held for the rule duration,
+    /// and is cleared once the value recovered by the hysteresis.
+    /// Returns the rules, whose actions must be executed, with the value of their sensor.
+    /// Rules that become active again within their cooldown are not returned.
+    pub fn evaluate<'a>(
+        &mut self,
+        alert_rules: &'a [AlertRuleConfig],
+        sensor_values: &[SensorValue],
+        now: Instant,
+    ) -> Vec<(&'a AlertRuleConfig, SensorValue)> {
+        let mut triggered_rules = vec![];
+
+        for alert_rule in alert_rules.iter().filter(|alert_rule| alert_rule.enabled) {
+            // A missing sensor or a value that is not a number keeps the state of the rule
+            let sensor_value = match sensor_values
+                .iter()
+                .find(|sensor_value| sensor_value.id == alert_rule.sensor_id)
+            {
+                Some(sensor_value) => sensor_value,
+                None => continue,
+            };
+            let value: f64 = match sensor_value.value.trim().parse() {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+
+            let (is_beyond_threshold, is_recovered) = match alert_rule.condition {
+                AlertCondition::Above => (
+                    value > alert_rule.threshold,
+                    value < alert_rule.threshold - alert_rule.hysteresis,
+                ),
+                AlertCondition::Below => (
+                    value < alert_rule.threshold,
+                    value > alert_rule.threshold + alert_rule.hysteresis,
+                ),
+            };
+
+            let alert_state = self.states.entry(alert_rule.id.clone()).or_default();
+            if alert_state.active {
+                if is_recovered {
+                    alert_state.active = false;
+                    alert_state.condition_since = None;
+                }
+                continue;
+            }
+
+            if !is_beyond_threshold {
+                alert_state.condition_since = None;
+                continue;
+            }
+
+            let condition_since = *alert_state.condition_since.get_or_insert(now);
+            if now.duration_since(condition_since) < Duration::from_millis(alert_rule.duration_ms) {
+                continue;
+            }
+
+            alert_state.active = true;
+            let cooldown = Duration::from_millis(alert_rule.cooldown_ms);
+            let is_in_cooldown = alert_state
+                .last_triggered_at
+                .is_some_and(|last_triggered_at| now.duration_since(last_triggered_at) < cooldown);
+            if !is_in_cooldown {
+                alert_state.last_triggered_at = Some(now);
+                triggered_rules.push((alert_rule, sensor_value.clone()));
+            }
+        }
+
+        triggered_rules
+    }
+
+    /// Returns whether the alert of the rule is active
+    pub fn is_active(&self, alert_rule_id: &str) -> bool {
+        self.states
+            .get(alert_rule_id)
+            .is_some_and(|alert_state| alert_state.active)
+    }
+}
+
+/// Evaluates the enabled alert rules against each new sample in a new thread.
+/// The rules are read once, changes take effect on the next start.
+pub fn start(sensor_sampler: &Arc<SensorSampler>, alert_rules: &[AlertRuleConfig]) {
+    let alert_rules: Vec<AlertRuleConfig> = alert_rules
+        .iter()
+        .filter(|alert_rule| alert_rule.enabled)
+        .cloned()
+        .collect();
+    if alert_rules.is_empty() {
+        return;
+    }
+
+    let sensor_ids = alert_rules
+        .iter()
+        .map(|alert_rule| alert_rule.sensor_id.clone())
+        .collect();
+    sensor_sampler.register_consumer(CONSUMER_ID, SensorDemand::Only(sensor_ids));
+
+    let mut alert_evaluator = AlertEvaluator::default();
+    update_alert_sensor_values(&alert_evaluator, &alert_rules);
+    info!("Evaluating {} alert rule(s)", alert_rules.len());
+
+    let sensor_sampler = sensor_sampler.clone();
+    thread::spawn(move || loop {
+        let sensor_values = match sensor_sampler.wait_for_next_sample(SAMPLE_TIMEOUT) {
+            Some(sensor_values) => sensor_values,
+            None => continue,
+        };
+
+        let triggered_rules =
+            alert_evaluator.evaluate(&alert_rules, &sensor_values, Instant::now());
+        update_alert_sensor_values(&alert_evaluator, &alert_rules);
+
+        for (alert_rule, sensor_value) in triggered_rules {
+            info!(
+                "Alert '{}' triggered, {} is {} {}",
+                alert_rule.name, sensor_value.label, sensor_value.value, sensor_value.unit
+            );
+            let alert_rule = alert_rule.clone();
+            thread::spawn(move || execute_actions(&alert_rule, &sensor_value));
+        }
+    });
+}
+
+/// Publishes the state of all rules to the alert sensor provider
+fn update_alert_sensor_values(alert_evaluator: &AlertEvaluator, alert_rules: &[AlertRuleConfig]) {
+    let sensor_values = alert_rules
+        .iter()
+        .map(|alert_rule| SensorValue {
+            id: format!("alert_{}", alert_rule.id),
+            value: if alert_evaluator.is_active(&alert_rule.id) {
+                "1".to_string()
+            } else {
+                "0".to_string()
+            },
+            unit: "".to_string(),
+            label: format!("Alert {}", alert_rule.name),
+            sensor_type: SensorType::Number,
+        })
+        .collect();
+
+    *ALERT_SENSOR_VALUES.lock().ignore_poison() = sensor_values;
+}
+
+/// Executes all actions of the triggered rule one after another, failures are logged
+fn execute_actions(alert_rule: &AlertRuleConfig, sensor_value: &SensorValue) {
+    let message = format!(
+        "{} is {} {}",
+        sensor_value.label, sensor_value.value, sensor_value.unit
+    );
+
+    for alert_action in &alert_rule.actions {
+        let result = match alert_action {
+            AlertAction::Notification => Command::new("notify-send")
+                .arg("--app-name=Sensor Bridge")
+                .arg(&alert_rule.name)
+                .arg(&message)
+                .status()
+                .map(|_| ())
+                .map_err(|err| err.to_string()),
+            AlertAction::Command { command } => Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .env("SENSOR_BRIDGE_ALERT_ID", &alert_rule.id)
+                .env("SENSOR_BRIDGE_ALERT_NAME", &alert_rule.name)
+                .env("SENSOR_BRIDGE_SENSOR_ID", &sensor_value.id)
+                .env("SENSOR_BRIDGE_SENSOR_VALUE", &sensor_value.value)
+                .env("SENSOR_BRIDGE_SENSOR_UNIT", &sensor_value.unit)
+                .status()
+                .map(|_| ())
+                .map_err(|err| err.to_string()),
+            AlertAction::Webhook { url } => ureq::post(url)
+                .timeout(WEBHOOK_TIMEOUT)
+                .send_json(json!({
+                    "alert_id": alert_rule.id,
+                    "alert_name": alert_rule.name,
+                    "sensor_id": sensor_value.id,
+                    "sensor_label": sensor_value.label,
+                    "value": sensor_value.value,
+                    "unit": sensor_value.unit,
+                    "threshold": alert_rule.threshold,
+                    "message": message,
+                }))
+                .map(|_| ())
+                .map_err(|err| err.to_string()),
+        };
+
+        if let Err(err) = result {
+            warn!(
+                "Could not execute {alert_action:?} of alert '{}': {err}",
+                alert_rule.name
+            );
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::time::{Duration, Instant};
+
+use assertor::{assert_that, EqualityAssertion};
+use sensor_core::{SensorType, SensorValue};
+
+use crate::alerts::AlertEvaluator;
+use crate::config::{AlertCondition, AlertRuleConfig};
+
+fn create_alert_rule(condition: AlertCondition) -> AlertRuleConfig {
+    AlertRuleConfig {
+        id: "gpu_hot".to_string(),
+        name: "GPU hot".to_string(),
+        enabled: true,
+        sensor_id: "gpu_temp".to_string(),
+        condition,
+        threshold: 90.0,
+        duration_ms: 10_000,
+        hysteresis: 5.0,
+        cooldown_ms: 60_000,
+        actions: vec![],
+    }
+}
+
+fn create_sensor_values(value: f64) -> Vec<SensorValue> {
+    vec![SensorValue {
+        id: "gpu_temp".to_string(),
+        value: value.to_string(),
+        unit: "°C".to_string(),
+        label: "GPU temperature".to_string(),
+        sensor_type: SensorType::Number,
+    }]
+}
+
+/// Evaluates the value at the specified second and returns the number of triggered rules
+fn evaluate_at(
+    alert_evaluator: &mut AlertEvaluator,
+    alert_rules: &[AlertRuleConfig],
+    start: Instant,
+    second: u64,
+    value: f64,
+) -> usize {
+    let now = start + Duration::from_secs(second);
+    alert_evaluator
+        .evaluate(alert_rules, &create_sensor_values(value), now)
+        .len()
+}
+
+#[test]
+fn test_alert_requires_duration() {
+    // GIVEN is a rule, that requires 10 seconds above 90
+    let alert_rules = vec![create_alert_rule(AlertCondition::Above)];
+    let mut alert_evaluator = AlertEvaluator::default();
+    let start = Instant::now();
+
+    // WHEN the value is above the threshold for 5 seconds, drops and rises for 10 seconds
+    let short_peak = evaluate_at(&mut alert_evaluator, &alert_rules, start, 0, 95.0)
+        + evaluate_at(&mut alert_evaluator, &alert_rules, start, 5, 95.0)
+        + evaluate_at(&mut alert_evaluator, &alert_rules, start, 6, 80.0);
+    let before_duration = evaluate_at(&mut alert_evaluator, &alert_rules, start, 10, 95.0)
+        + evaluate_at(&mut alert_evaluator, &alert_rules, start, 19, 95.0);
+    let after_duration = evaluate_at(&mut alert_evaluator, &alert_rules, start, 20, 95.0);
+
+    // THEN the alert should only trigger once the condition held for the duration
+    assert_that!(short_peak).is_equal_to(0);
+    assert_that!(before_duration).is_equal_to(0);
+    assert_that!(after_duration).is_equal_to(1);
+    assert_that!(alert_evaluator.is_active("gpu_hot")).is_equal_to(true);
+}
+
+#[test]
+fn test_alert_hysteresis() {
+    // GIVEN is an active alert with a hysteresis of 5
+    let mut alert_rule = create_alert_rule(AlertCondition::Above);
+    alert_rule.duration_ms = 0;
+    let alert_rules = vec![alert_rule];
+    let mut alert_evaluator = AlertEvaluator::default();
+    let start = Instant::now();
+    evaluate_at(&mut alert_evaluator, &alert_rules, start, 0, 95.0);
+
+    // WHEN the value drops below the threshold, but not by the hysteresis
+    evaluate_at(&mut alert_evaluator, &alert_rules, start, 1, 87.0);
+    let active_within_hysteresis = alert_evaluator.is_active("gpu_hot");
+    evaluate_at(&mut alert_evaluator, &alert_rules, start, 2, 84.0);
+    let active_below_hysteresis = alert_evaluator.is_active("gpu_hot");
+
+    // THEN the alert should only be cleared below the hysteresis
+    assert_that!(active_within_hysteresis).is_equal_to(true);
+    assert_that!(active_below_hysteresis).is_equal_to(false);
+}
+
+#[test]
+fn test_alert_cooldown() {
+    // GIVEN is a below rule with a cooldown of 60 seconds
+    let mut alert_rule = create_alert_rule(AlertCondition::Below);
+    alert_rule.threshold = 5.0;
+    alert_rule.hysteresis = 0.0;
+    alert_rule.duration_ms = 0;
+    let alert_rules = vec![alert_rule];
+    let mut alert_evaluator = AlertEvaluator::default();
+    let start = Instant::now();
+
+    // WHEN the value drops below the threshold three times
+    let first = evaluate_at(&mut alert_evaluator, &alert_rules, start, 0, 4.0);
+    evaluate_at(&mut alert_evaluator, &alert_rules, start, 1, 6.0);
+    let within_cooldown = evaluate_at(&mut alert_evaluator, &alert_rules, start, 30, 4.0);
+    evaluate_at(&mut alert_evaluator, &alert_rules, start, 31, 6.0);
+    let after_cooldown = evaluate_at(&mut alert_evaluator, &alert_rules, start, 61, 4.0);
+
+    // THEN the actions should not be triggered again within the cooldown
+    assert_that!(first).is_equal_to(1);
+    assert_that!(within_cooldown).is_equal_to(0);
+    assert_that!(after_cooldown).is_equal_to(1);
+}
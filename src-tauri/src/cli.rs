@@ -0,0 +1,467 @@
+use std::fs;
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use image::DynamicImage;
+use sensor_core::SensorValue;
+use signal_hook::consts::{SIGINT, SIGTERM};
+
+use crate::config::NetworkDeviceConfig;
+use crate::history_store::HistoryStore;
+use crate::recording::{Recorder, RecordingConfig, RecordingFormat};
+use crate::sensor::SensorProviderRegistry;
+use crate::sensor_sampler::{SensorSampler, SAMPLE_RATE};
+use crate::sensor_transform::SensorTransforms;
+use crate::{
+    config, derived_sensor, export_import, headless, history_store, lcd_preview, sensor_history,
+    text, utils,
+};
+
+const USAGE: &str = "Usage: sensor-bridge [COMMAND]
+
+Starts the user interface if no command is specified.
+
+Commands:
+  sensors [--format json|table]   List all sensors with their current value
+  sensor <SENSOR_ID>              Print a single sensor as json
+  devices [--format json|table]   List all configured devices
+  render <DEVICE> <FILE>          Render the layout of a device to a png file
+  enable <DEVICE>                 Enable the sync of a device, a running instance must be restarted
+  disable <DEVICE>                Disable the sync of a device, a running instance must be restarted
+  export <FILE>                   Export the configuration without credentials to a json file
+  import <FILE>                   Import the configuration from a json file, a running instance must be restarted
+  export-history <FILE> [--seconds N]
+                                  Export the stored sensor history, or its last N seconds, to a csv file
+  record <FILE> [--format csv|jsonl] [--interval MS] [--sensors ID,...] [--seconds N]
+                                  Record sensor values until Ctrl+C or for N seconds, then print a summary
+  help                            Print this help
+
+Options:
+  --headless                      Sync all enabled devices without the user interface
+
+A device is specified by its id or name.
+The commands enable, disable and import only change the config file. An already running instance,
+with or without user interface, keeps its configuration until it is restarted.";
+
+/// Printed after changing the config file, as a running instance does not reload it
+const RESTART_NOTE: &str = "Restart a running sensor-bridge to apply the change.";
+
+/// A command of the command line interface
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Sensors {
+        output_format: OutputFormat,
+    },
+    Sensor {
+        sensor_id: String,
+    },
+    Devices {
+        output_format: OutputFormat,
+    },
+    Render {
+        device: String,
+        file_path: String,
+    },
+    Enable {
+        device: String,
+    },
+    Disable {
+        device: String,
+    },
+    Export {
+        file_path: String,
+    },
+    Import {
+        file_path: String,
+    },
+    ExportHistory {
+        file_path: String,
+        window: Option<Duration>,
+    },
+    Record {
+        recording_config: RecordingConfig,
+        duration: Option<Duration>,
+    },
+    Help,
+}
+
+/// Format in which lists are printed
+#[derive(Debug, PartialEq)]
+pub enum OutputFormat {
+    Json,
+    Table,
+}
+
+/// Parses the command line arguments, without the program name.
+/// Returns None if no command is specified, thus the user interface or the headless mode should start.
+pub fn parse(args: &[String]) -> Result<Option<Command>, String> {
+    let args: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|arg| *arg != headless::HEADLESS_ARG)
+        .collect();
+
+    let command = match args.as_slice() {
+        [] => return Ok(None),
+        ["sensors", options @ ..] => Command::Sensors {
+            output_format: parse_output_format(options)?,
+        },
+        ["sensor", sensor_id] => Command::Sensor {
+            sensor_id: sensor_id.to_string(),
+        },
+        ["devices", options @ ..] => Command::Devices {
+            output_format: parse_output_format(options)?,
+        },
+        ["render", device, file_path] => Command::Render {
+            device: device.to_string(),
+            file_path: file_path.to_string(),
+        },
+        ["enable", device] => Command::Enable {
+            device: device.to_string(),
+        },
+        ["disable", device] => Command::Disable {
+            device: device.to_string(),
+        },
+        ["export", file_path] => Command::Export {
+            file_path: file_path.to_string(),
+        },
+        ["import", file_path] => Command::Import {
+            file_path: file_path.to_string(),
+        },
+        ["export-history", file_path, options @ ..] => Command::ExportHistory {
+            file_path: file_path.to_string(),
+            window: parse_window(options)?,
+        },
+        ["record", file_path, options @ ..] => parse_record(file_path, options)?,
+        ["help"] | ["--help"] | ["-h"] => Command::Help,
+        _ => return Err(format!("Invalid arguments: {}", args.join(" "))),
+    };
+
+    Ok(Some(command))
+}
+
+/// Parses the output format option, defaults to a table
+fn parse_output_format(options: &[&str]) -> Result<OutputFormat, String> {
+    match options {
+        [] | ["--format", "table"] => Ok(OutputFormat::Table),
+        ["--format", "json"] => Ok(OutputFormat::Json),
+        _ => Err(format!("Invalid options: {}", options.join(" "))),
+    }
+}
+
+/// Parses the time window option, the complete history is used if it is not specified
+fn parse_window(options: &[&str]) -> Result<Option<Duration>, String> {
+    match options {
+        [] => Ok(None),
+        ["--seconds", seconds] => match seconds.parse() {
+            Ok(seconds) => Ok(Some(Duration::from_secs(seconds))),
+            Err(_) => Err(format!("Invalid number of seconds: {seconds}")),
+        },
+        _ => Err(format!("Invalid options: {}", options.join(" "))),
+    }
+}
+
+/// Parses the options of a recording, unspecified options use the defaults of a recording config
+fn parse_record(file_path: &str, options: &[&str]) -> Result<Command, String> {
+    let mut recording_config = RecordingConfig::new(file_path);
+    let mut duration = None;
+
+    for option in options.chunks(2) {
+        match option {
+            ["--format", "csv"] => recording_config.format = RecordingFormat::Csv,
+            ["--format", "jsonl"] => recording_config.format = RecordingFormat::JsonLines,
+            ["--interval", interval_ms] => {
+                recording_config.interval_ms = interval_ms
+                    .parse()
+                    .map_err(|_| format!("Invalid interval: {interval_ms}"))?
+            }
+            ["--sensors", sensor_ids] => {
+                recording_config.sensor_ids = sensor_ids
+                    .split(',')
+                    .map(|id| id.trim().to_string())
+                    .collect()
+            }
+            ["--seconds", _] => duration = parse_window(option)?,
+            _ => return Err(format!("Invalid options: {}", options.join(" "))),
+        }
+    }
+
+    Ok(Command::Record {
+        recording_config,
+        duration,
+    })
+}
+
+/// Runs the specified command, prints its result to stdout and errors to stderr.
+/// Returns the exit code of the process.
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = match parse(args) {
+        Ok(Some(command)) => command,
+        Ok(None) => return None,
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            return Some(2);
+        }
+    };
+
+    let result = match command {
+        Command::Sensors { output_format } => print_sensors(output_format),
+        Command::Sensor { sensor_id } => print_sensor(&sensor_id),
+        Command::Devices { output_format } => print_devices(output_format),
+        Command::Render { device, file_path } => render(&device, &file_path),
+        Command::Enable { device } => set_device_active(&device, true),
+        Command::Disable { device } => set_device_active(&device, false),
+        Command::Export { file_path } => export_import::export_configuration(file_path),
+        Command::Import { file_path } => import(file_path),
+        Command::ExportHistory { file_path, window } => export_history(&file_path, window),
+        Command::Record {
+            recording_config,
+            duration,
+        } => record(recording_config, duration),
+        Command::Help => {
+            println!("{USAGE}");
+            Ok(())
+        }
+    };
+
+    match result {
+        Ok(()) => Some(0),
+        Err(err) => {
+            eprintln!("{err}");
+            Some(1)
+        }
+    }
+}
+
+/// Reads all available sensor values.
+/// Load and rate sensors are measured over the time between two reads, thus the sensors are read twice.
+/// Sensors that require a root shell are not read.
+fn read_sensor_values() -> Vec<SensorValue> {
+    let app_config = config::read_from_app_config();
+    let sensor_provider_registry = SensorProviderRegistry::new(
+        &Arc::new(Mutex::new(None)),
+        &app_config.disabled_sensor_providers,
+    );
+    let sensor_sampler = SensorSampler::new(sensor_provider_registry);
+    sensor_sampler.set_derived_sensors(derived_sensor::create_all(&app_config.derived_sensors));
+    sensor_sampler.set_sensor_transforms(SensorTransforms::new(&app_config.sensor_transforms));
+    sensor_sampler.set_sensor_overrides(app_config.sensor_overrides.clone());
+
+    sensor_sampler.discover();
+    thread::sleep(SAMPLE_RATE);
+    sensor_sampler.discover()
+}
+
+/// Prints all available sensors with their current value
+fn print_sensors(output_format: OutputFormat) -> Result<(), String> {
+    let sensor_values = read_sensor_values();
+
+    match output_format {
+        OutputFormat::Json => print_json(&sensor_values),
+        OutputFormat::Table => {
+            let rows = sensor_values
+                .iter()
+                .map(|sensor_value| {
+                    vec![
+                        sensor_value.id.clone(),
+                        sensor_value.label.clone(),
+                        format!("{} {}", sensor_value.value, sensor_value.unit),
+                    ]
+                })
+                .collect();
+            print_table(&["ID", "LABEL", "VALUE"], rows);
+            Ok(())
+        }
+    }
+}
+
+/// Prints a single sensor as json
+fn print_sensor(sensor_id: &str) -> Result<(), String> {
+    let sensor_value = read_sensor_values()
+        .into_iter()
+        .find(|sensor_value| sensor_value.id == sensor_id)
+        .ok_or_else(|| format!("Sensor '{sensor_id}' not found"))?;
+
+    print_json(&sensor_value)
+}
+
+/// Prints all configured devices
+fn print_devices(output_format: OutputFormat) -> Result<(), String> {
+    let mut network_devices: Vec<NetworkDeviceConfig> = config::read_from_app_config()
+        .network_devices
+        .into_values()
+        .collect();
+    network_devices.sort_by(|a, b| a.name.cmp(&b.name));
+
+    match output_format {
+        OutputFormat::Json => print_json(&network_devices),
+        OutputFormat::Table => {
+            let rows = network_devices
+                .iter()
+                .map(|network_device| {
+                    vec![
+                        network_device.id.clone(),
+                        network_device.name.clone(),
+                        format!("{}:{}", network_device.address, network_device.port),
+                        network_device.active.to_string(),
+                    ]
+                })
+                .collect();
+            print_table(&["ID", "NAME", "ADDRESS", "ACTIVE"], rows);
+            Ok(())
+        }
+    }
+}
+
+/// Renders the layout of the device with the current sensor values to a png file
+fn render(device: &str, file_path: &str) -> Result<(), String> {
+    let network_device = find_device(device)?;
+    let mut display_config = network_device.display_config;
+
+    lcd_preview::prepare_assets(display_config.elements.clone());
+    let mut sensor_value_history = vec![read_sensor_values()];
+    if network_device.auto_scale_units {
+        text::auto_scale_units(&mut display_config, &mut sensor_value_history);
+    }
+    let image = lcd_preview::render_image(display_config, &sensor_value_history);
+
+    let png_data = utils::rgba_to_png_bytes(DynamicImage::ImageRgba8(image));
+    fs::write(file_path, png_data).map_err(|err| format!("Could not write '{file_path}': {err}"))
+}
+
+/// Enables or disables the sync of the device.
+/// Only the config file is changed, a running instance applies it after a restart.
+fn set_device_active(device: &str, active: bool) -> Result<(), String> {
+    let mut network_device = find_device(device)?;
+    network_device.active = active;
+    config::write(&network_device);
+    eprintln!("{RESTART_NOTE}");
+    Ok(())
+}
+
+/// Imports the configuration and saves it as the new configuration.
+/// Only the config file is changed, a running instance applies it after a restart.
+fn import(file_path: String) -> Result<(), String> {
+    export_import::import_configuration(file_path)?;
+    eprintln!("{RESTART_NOTE}");
+    Ok(())
+}
+
+/// Exports the snapshots of the history store within the time window to a csv file
+fn export_history(file_path: &str, window: Option<Duration>) -> Result<(), String> {
+    let history_store_config = config::read_from_app_config().history_store;
+    let history_store = HistoryStore::open(
+        &history_store::get_history_dir(),
+        history_store_config.max_size(),
+    )
+    .map_err(|err| format!("Could not open the history store: {err}"))?;
+
+    let now = sensor_history::to_timestamp(SystemTime::now());
+    let from = window.map_or(0, |window| now.saturating_sub(window.as_millis() as u64));
+    let snapshots = history_store.read(from, now);
+    if snapshots.is_empty() {
+        return Err(
+            "No stored sensor history found, the history store may be disabled".to_string(),
+        );
+    }
+
+    let file =
+        File::create(file_path).map_err(|err| format!("Could not write '{file_path}': {err}"))?;
+    history_store::write_csv(&mut BufWriter::new(file), &snapshots)
+        .map_err(|err| format!("Could not write '{file_path}': {err}"))
+}
+
+/// Records the sensor values until the process is interrupted or the duration elapsed.
+/// Prints the summary of the recorded numeric sensors.
+fn record(recording_config: RecordingConfig, duration: Option<Duration>) -> Result<(), String> {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    for signal in [SIGINT, SIGTERM] {
+        signal_hook::flag::register(signal, interrupted.clone())
+            .map_err(|err| format!("Could not register signal handler: {err}"))?;
+    }
+
+    let app_config = config::read_from_app_config();
+    let sensor_provider_registry = SensorProviderRegistry::new(
+        &Arc::new(Mutex::new(None)),
+        &app_config.disabled_sensor_providers,
+    );
+    let sensor_sampler = Arc::new(SensorSampler::new(sensor_provider_registry));
+    sensor_sampler.set_derived_sensors(derived_sensor::create_all(&app_config.derived_sensors));
+    sensor_sampler.set_sensor_transforms(SensorTransforms::new(&app_config.sensor_transforms));
+    sensor_sampler.set_sensor_overrides(app_config.sensor_overrides.clone());
+    sensor_sampler.start();
+
+    let recorder = Recorder::default();
+    recorder.start(&sensor_sampler, recording_config)?;
+    eprintln!("Recording, press Ctrl+C to stop");
+
+    let deadline = duration.map(|duration| Instant::now() + duration);
+    while !interrupted.load(Ordering::Relaxed)
+        && deadline.is_none_or(|deadline| Instant::now() < deadline)
+    {
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    let summary = recorder.stop()?;
+    let rows = summary
+        .sensors
+        .iter()
+        .map(|sensor_summary| {
+            vec![
+                sensor_summary.sensor_id.clone(),
+                utils::format_number(sensor_summary.min),
+                utils::format_number(sensor_summary.avg),
+                utils::format_number(sensor_summary.max),
+                utils::format_number(sensor_summary.p95),
+                sensor_summary.unit.clone(),
+            ]
+        })
+        .collect();
+    print_table(&["ID", "MIN", "AVG", "MAX", "P95", "UNIT"], rows);
+    eprintln!(
+        "Recorded {} snapshots to {}",
+        summary.snapshot_count, summary.file_path
+    );
+
+    Ok(())
+}
+
+/// Returns the config of the device with the specified id or name
+fn find_device(device: &str) -> Result<NetworkDeviceConfig, String> {
+    config::read_from_app_config()
+        .network_devices
+        .into_values()
+        .find(|network_device| network_device.id == device || network_device.name == device)
+        .ok_or_else(|| format!("Device '{device}' not found"))
+}
+
+/// Prints the value as pretty json
+fn print_json<T: serde::Serialize + ?Sized>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|err| err.to_string())?;
+    println!("{json}");
+    Ok(())
+}
+
+/// Prints the rows as table, each column is as wide as its widest cell
+fn print_table(header: &[&str], rows: Vec<Vec<String>>) {
+    let mut column_widths: Vec<usize> = header.iter().map(|title| title.chars().count()).collect();
+    for row in &rows {
+        for (column_width, cell) in column_widths.iter_mut().zip(row) {
+            *column_width = (*column_width).max(cell.chars().count());
+        }
+    }
+
+    let header = header.iter().map(|title| title.to_string()).collect();
+    for row in std::iter::once(header).chain(rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&column_widths)
+            .map(|(cell, &column_width)| format!("{cell:<column_width$}"))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use assertor::{assert_that, EqualityAssertion};
+
+use crate::cli;
+use crate::cli::{Command, OutputFormat};
+use crate::recording::{RecordingConfig, RecordingFormat};
+
+/// Converts the arguments to owned strings, as returned by std::env::args
+fn to_args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn test_parse_without_command() {
+    // GIVEN are arguments without a command
+    let no_args = to_args(&[]);
+    let headless_args = to_args(&["--headless"]);
+
+    // WHEN parsing the arguments
+    let no_args = cli::parse(&no_args);
+    let headless_args = cli::parse(&headless_args);
+
+    // THEN no command should be returned, to start the user interface or the headless mode
+    assert_that!(no_args).is_equal_to(Ok(None));
+    assert_that!(headless_args).is_equal_to(Ok(None));
+}
+
+#[test]
+fn test_parse_sensors_output_format() {
+    // GIVEN are sensors commands with and without an output format
+    let default_format = to_args(&["sensors"]);
+    let json_format = to_args(&["sensors", "--format", "json"]);
+    let invalid_format = to_args(&["sensors", "--format", "xml"]);
+
+    // WHEN parsing the arguments
+    let default_format = cli::parse(&default_format);
+    let json_format = cli::parse(&json_format);
+    let invalid_format = cli::parse(&invalid_format);
+
+    // THEN the table should be the default and invalid formats should be rejected
+    assert_that!(default_format).is_equal_to(Ok(Some(Command::Sensors {
+        output_format: OutputFormat::Table,
+    })));
+    assert_that!(json_format).is_equal_to(Ok(Some(Command::Sensors {
+        output_format: OutputFormat::Json,
+    })));
+    assert_that!(invalid_format.is_err()).is_equal_to(true);
+}
+
+#[test]
+fn test_parse_device_commands() {
+    // GIVEN are commands that refer to a device
+    let render = to_args(&["render", "Living room", "/tmp/display.png"]);
+    let enable = to_args(&["enable", "Living room"]);
+    let disable_without_device = to_args(&["disable"]);
+
+    // WHEN parsing the arguments
+    let render = cli::parse(&render);
+    let enable = cli::parse(&enable);
+    let disable_without_device = cli::parse(&disable_without_device);
+
+    // THEN the device and file path should be taken from the arguments
+    assert_that!(render).is_equal_to(Ok(Some(Command::Render {
+        device: "Living room".to_string(),
+        file_path: "/tmp/display.png".to_string(),
+    })));
+    assert_that!(enable).is_equal_to(Ok(Some(Command::Enable {
+        device: "Living room".to_string(),
+    })));
+    assert_that!(disable_without_device.is_err()).is_equal_to(true);
+}
+
+#[test]
+fn test_parse_export_history() {
+    // GIVEN are export history commands with and without a time window
+    let complete_history = to_args(&["export-history", "history.csv"]);
+    let last_hour = to_args(&["export-history", "history.csv", "--seconds", "3600"]);
+    let invalid_window = to_args(&["export-history", "history.csv", "--seconds", "one"]);
+
+    // WHEN parsing the arguments
+    let complete_history = cli::parse(&complete_history);
+    let last_hour = cli::parse(&last_hour);
+    let invalid_window = cli::parse(&invalid_window);
+
+    // THEN the time window should be taken from the options
+    assert_that!(complete_history).is_equal_to(Ok(Some(Command::ExportHistory {
+        file_path: "history.csv".to_string(),
+        window: None,
+    })));
+    assert_that!(last_hour).is_equal_to(Ok(Some(Command::ExportHistory {
+        file_path: "history.csv".to_string(),
+        window: Some(Duration::from_secs(3600)),
+    })));
+    assert_that!(invalid_window.is_err()).is_equal_to(true);
+}
+
+#[test]
+fn test_parse_record() {
+    // GIVEN are record commands with default and with custom options
+    let defaults = to_args(&["record", "session.csv"]);
+    let custom = to_args(&[
+        "record",
+        "session.jsonl",
+        "--format",
+        "jsonl",
+        "--interval",
+        "500",
+        "--sensors",
+        "cpu_load_total,gpu_temp",
+        "--seconds",
+        "60",
+    ]);
+    let missing_value = to_args(&["record", "session.csv", "--interval"]);
+
+    // WHEN parsing the arguments
+    let defaults = cli::parse(&defaults);
+    let custom = cli::parse(&custom);
+    let missing_value = cli::parse(&missing_value);
+
+    // THEN the options should be applied to the recording config
+    assert_that!(defaults).is_equal_to(Ok(Some(Command::Record {
+        recording_config: RecordingConfig::new("session.csv"),
+        duration: None,
+    })));
+    assert_that!(custom).is_equal_to(Ok(Some(Command::Record {
+        recording_config: RecordingConfig {
+            file_path: "session.jsonl".to_string(),
+            format: RecordingFormat::JsonLines,
+            interval_ms: 500,
+            sensor_ids: vec!["cpu_load_total".to_string(), "gpu_temp".to_string()],
+        },
+        duration: Some(Duration::from_secs(60)),
+    })));
+    assert_that!(missing_value.is_err()).is_equal_to(true);
+}
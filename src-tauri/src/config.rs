@@ -1,16 +1,381 @@
 use std::collections::HashMap;
 use std::fs;
 use std::fs::File;
+use std::time::Duration;
 
+use regex::Regex;
 use sensor_core::DisplayConfig;
 use serde::Deserialize;
 use serde::Serialize;
 use uuid::Uuid;
 
+use crate::discovery::DiscoveredDevice;
+use crate::mqtt::get_object_id;
+
+/// Default interval in which the render data is pushed to a network device
+pub const DEFAULT_PUSH_RATE_MS: u64 = 1000;
+/// Lower bound of the push interval, to not flood the network and the display
+pub const MIN_PUSH_RATE_MS: u64 = 100;
+/// Upper bound of the push interval
+pub const MAX_PUSH_RATE_MS: u64 = 60000;
+/// Default interval in which the layout is sent again to a device that receives delta frames
+pub const DEFAULT_RESYNC_INTERVAL_MS: u64 = 60000;
+/// Default tcp port the sensor-display listens on
+pub const DEFAULT_NETWORK_PORT: u16 = 10489;
+/// Default address of the local http api, only reachable from this computer
+pub const DEFAULT_HTTP_SERVER_ADDRESS: &str = "127.0.0.1";
+/// Default tcp port of the local http api
+pub const DEFAULT_HTTP_SERVER_PORT: u16 = 10491;
+/// Default tcp port of a mqtt broker
+pub const DEFAULT_MQTT_PORT: u16 = 1883;
+/// Default interval in which the sensor values are published to the mqtt broker
+pub const DEFAULT_MQTT_PUBLISH_RATE_MS: u64 = 10000;
+/// Lower bound of the mqtt publish interval, sensors are not sampled more often
+pub const MIN_MQTT_PUBLISH_RATE_MS: u64 = 1000;
+/// Default time in milliseconds after a triggered alert, in which the alert is not triggered again
+pub const DEFAULT_ALERT_COOLDOWN_MS: u64 = 5 * 60 * 1000;
+/// Default upper bound of the size of the on-disk sensor history
+pub const DEFAULT_HISTORY_STORE_MAX_SIZE_MB: u64 = 100;
+
 /// The app config
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct AppConfig {
     pub network_devices: HashMap<String, NetworkDeviceConfig>,
+    /// Names of the sensor providers, that are not read
+    #[serde(default)]
+    pub disabled_sensor_providers: Vec<String>,
+    #[serde(default)]
+    pub http_server: HttpServerConfig,
+    #[serde(default)]
+    pub mqtt: MqttConfig,
+    #[serde(default)]
+    pub history_store: HistoryStoreConfig,
+    #[serde(default)]
+    pub alert_rules: Vec<AlertRuleConfig>,
+    #[serde(default)]
+    pub derived_sensors: Vec<DerivedSensorConfig>,
+    #[serde(default)]
+    pub sensor_transforms: Vec<SensorTransformConfig>,
+    /// Overrides of the label, unit and value of sensors, by sensor id
+    #[serde(default)]
+    pub sensor_overrides: HashMap<String, SensorOverrideConfig>,
+}
+
+impl AppConfig {
+    /// Enables or disables the specified sensor provider
+    pub fn set_sensor_provider_enabled(&mut self, provider_name: &str, enabled: bool) {
+        self.disabled_sensor_providers
+            .retain(|name| name != provider_name);
+        if !enabled {
+            self.disabled_sensor_providers
+                .push(provider_name.to_string());
+        }
+    }
+
+    /// Replaces the prefixes of sensor ids in all references to sensors, e.g. `gpu_card0_` by `gpu_0000_03_00_0_`.
+    /// This covers layouts, alert rules, mqtt sensors, overrides, transforms and derived sensor expressions.
+    /// Returns true if any sensor id was replaced.
+    pub fn replace_sensor_id_prefixes(&mut self, prefixes: &[(String, String)]) -> bool {
+        let mut replaced = false;
+        let mut replace = |sensor_id: &mut String| {
+            if let Some(replaced_sensor_id) = replace_sensor_id_prefix(sensor_id, prefixes) {
+                *sensor_id = replaced_sensor_id;
+                replaced = true;
+            }
+        };
+
+        for network_device in self.network_devices.values_mut() {
+            for element in network_device.display_config.elements.iter_mut() {
+                if let Some(text_config) = element.text_config.as_mut() {
+                    replace(&mut text_config.sensor_id);
+                }
+                if let Some(graph_config) = element.graph_config.as_mut() {
+                    replace(&mut graph_config.sensor_id);
+                }
+                if let Some(conditional_image_config) = element.conditional_image_config.as_mut() {
+                    replace(&mut conditional_image_config.sensor_id);
+                }
+            }
+        }
+        for alert_rule in self.alert_rules.iter_mut() {
+            replace(&mut alert_rule.sensor_id);
+        }
+        for sensor_id in self.mqtt.sensor_ids.iter_mut() {
+            replace(sensor_id);
+        }
+        for sensor_transform in self.sensor_transforms.iter_mut() {
+            replace(&mut sensor_transform.sensor_id);
+        }
+        self.sensor_overrides = std::mem::take(&mut self.sensor_overrides)
+            .into_iter()
+            .map(|(mut sensor_id, sensor_override)| {
+                replace(&mut sensor_id);
+                (sensor_id, sensor_override)
+            })
+            .collect();
+
+        for derived_sensor in self.derived_sensors.iter_mut() {
+            if let Some(expression) =
+                replace_expression_sensor_id_prefixes(&derived_sensor.expression, prefixes)
+            {
+                derived_sensor.expression = expression;
+                replaced = true;
+            }
+        }
+
+        replaced
+    }
+}
+
+/// Returns the sensor id with the first matching prefix replaced, or None if no prefix matches
+fn replace_sensor_id_prefix(sensor_id: &str, prefixes: &[(String, String)]) -> Option<String> {
+    prefixes.iter().find_map(|(prefix, replacement)| {
+        sensor_id
+            .strip_prefix(prefix.as_str())
+            .map(|suffix| format!("{replacement}{suffix}"))
+    })
+}
+
+/// Returns the expression with the prefixes of the sensor ids in braces replaced, e.g. `{gpu_card0_load}`,
+/// or None if the expression does not reference such a sensor id
+fn replace_expression_sensor_id_prefixes(
+    expression: &str,
+    prefixes: &[(String, String)],
+) -> Option<String> {
+    let mut replaced_expression = expression.to_string();
+    for (prefix, replacement) in prefixes {
+        let pattern = Regex::new(&format!(r"\{{{}", regex::escape(prefix))).unwrap();
+        replaced_expression = pattern
+            .replace_all(
+                &replaced_expression,
+                regex::NoExpand(&format!("{{{replacement}")),
+            )
+            .to_string();
+    }
+
+    (replaced_expression != expression).then_some(replaced_expression)
+}
+
+/// Config of the local http api, that exposes the sensor values to other applications
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HttpServerConfig {
+    pub enabled: bool,
+    /// Address the http server binds to
+    #[serde(default = "default_http_server_address")]
+    pub address: String,
+    #[serde(default = "default_http_server_port")]
+    pub port: u16,
+    /// Origins of web pages that may read the api, e.g. `http://localhost:8080`, or `*` for any origin.
+    /// Web pages of other origins are not allowed to read the api.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// Host names under which the api is reachable, besides localhost and the bind address,
+    /// e.g. the host name of the computer if the api is exposed to the network
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+}
+
+impl Default for HttpServerConfig {
+    fn default() -> HttpServerConfig {
+        HttpServerConfig {
+            enabled: false,
+            address: DEFAULT_HTTP_SERVER_ADDRESS.to_string(),
+            port: DEFAULT_HTTP_SERVER_PORT,
+            allowed_origins: vec![],
+            allowed_hosts: vec![],
+        }
+    }
+}
+
+/// Config of the publishing of sensor values to a mqtt broker
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct MqttConfig {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    /// Credentials are only used if the username is not empty
+    pub username: String,
+    pub password: String,
+    pub client_id: String,
+    /// Prefix of all published topics
+    pub topic_prefix: String,
+    pub payload_format: MqttPayloadFormat,
+    /// Interval in milliseconds in which the sensor values are published
+    pub publish_rate_ms: u64,
+    /// Ids of the published sensors, all sensors are published if empty
+    pub sensor_ids: Vec<String>,
+    /// Publishes Home Assistant discovery configs, so that the sensors show up automatically
+    pub home_assistant_discovery: bool,
+    pub home_assistant_discovery_prefix: String,
+}
+
+/// Format in which the sensor values are published
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MqttPayloadFormat {
+    /// Each sensor value is published to its own topic
+    TopicPerSensor,
+    /// All sensor values are published as json object to a single state topic
+    JsonState,
+}
+
+impl Default for MqttConfig {
+    fn default() -> MqttConfig {
+        MqttConfig {
+            enabled: false,
+            host: "localhost".to_string(),
+            port: DEFAULT_MQTT_PORT,
+            username: "".to_string(),
+            password: "".to_string(),
+            client_id: get_default_mqtt_id(),
+            topic_prefix: get_default_mqtt_id(),
+            payload_format: MqttPayloadFormat::JsonState,
+            publish_rate_ms: DEFAULT_MQTT_PUBLISH_RATE_MS,
+            sensor_ids: vec![],
+            home_assistant_discovery: false,
+            home_assistant_discovery_prefix: "homeassistant".to_string(),
+        }
+    }
+}
+
+/// Returns the default client id and topic prefix, which contain the hostname,
+/// so that multiple computers can publish to the same broker
+fn get_default_mqtt_id() -> String {
+    match dns_lookup::get_hostname() {
+        Ok(hostname) => format!("sensor-bridge-{}", get_object_id(&hostname)),
+        Err(_) => "sensor-bridge".to_string(),
+    }
+}
+
+impl MqttConfig {
+    /// Returns the publish interval, bounded to the minimum publish interval
+    pub fn publish_rate(&self) -> Duration {
+        Duration::from_millis(self.publish_rate_ms.max(MIN_MQTT_PUBLISH_RATE_MS))
+    }
+}
+
+/// Config of the on-disk sensor history, that is kept across restarts
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct HistoryStoreConfig {
+    pub enabled: bool,
+    /// Upper bound of the size of all stored files in megabytes, the oldest data is removed first
+    pub max_size_mb: u64,
+}
+
+impl Default for HistoryStoreConfig {
+    fn default() -> HistoryStoreConfig {
+        HistoryStoreConfig {
+            enabled: false,
+            max_size_mb: DEFAULT_HISTORY_STORE_MAX_SIZE_MB,
+        }
+    }
+}
+
+impl HistoryStoreConfig {
+    /// Returns the upper bound of the size of all stored files in bytes
+    pub fn max_size(&self) -> u64 {
+        self.max_size_mb.saturating_mul(1024 * 1024)
+    }
+}
+
+/// A rule, that triggers actions while a sensor value is beyond a threshold for some time
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AlertRuleConfig {
+    /// Unique id of the rule, the state of the rule is exposed as sensor `alert_<id>`
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub sensor_id: String,
+    pub condition: AlertCondition,
+    pub threshold: f64,
+    /// Milliseconds the condition must hold, before the alert is triggered
+    #[serde(default)]
+    pub duration_ms: u64,
+    /// Margin by which the value must recover beyond the threshold, before the alert is cleared
+    #[serde(default)]
+    pub hysteresis: f64,
+    /// Milliseconds after a triggered alert, in which the alert is not triggered again
+    #[serde(default = "default_alert_cooldown_ms")]
+    pub cooldown_ms: u64,
+    #[serde(default)]
+    pub actions: Vec<AlertAction>,
+}
+
+/// Comparison of a sensor value with the threshold of an alert rule
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCondition {
+    Above,
+    Below,
+}
+
+/// Action executed when an alert is triggered
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertAction {
+    /// Shows a desktop notification
+    Notification,
+    /// Runs the shell command, with the alert details as environment variables
+    Command { command: String },
+    /// Posts the alert details as json to the url
+    Webhook { url: String },
+}
+
+/// A sensor computed from other sensors, available as `derived_<id>`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DerivedSensorConfig {
+    pub id: String,
+    pub label: String,
+    #[serde(default)]
+    pub unit: String,
+    /// Arithmetic expression over sensors, e.g. `{cpu_temp} - {ambient_temp}`
+    pub expression: String,
+}
+
+/// User overrides of a single sensor, applied before any consumer reads the sensor
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct SensorOverrideConfig {
+    /// Replaces the label reported by the provider
+    pub label: Option<String>,
+    /// Hidden sensors are not available to any consumer, but can still be used by derived sensors
+    pub hidden: bool,
+    /// Unit the value is converted to, e.g. `°F`, `Mbit/s` or `GHz`.
+    /// If the value can not be converted, only the unit is replaced.
+    pub unit: Option<String>,
+    /// Factor the value is multiplied with, after the unit conversion
+    pub scale: Option<f64>,
+    /// Added to the value, after the scale
+    pub offset: Option<f64>,
+    /// Number of decimal places of the value
+    pub precision: Option<usize>,
+}
+
+/// An additional sensor computed from the values of a sensor
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SensorTransformConfig {
+    pub sensor_id: String,
+    #[serde(flatten)]
+    pub transform: SensorTransform,
+}
+
+/// Transformation of the values of a sensor
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SensorTransform {
+    /// Exponential moving average, follows a step by 63% after the time constant
+    Ema { time_constant_ms: u64 },
+    /// Average of the sampled values within the window
+    Average { window_ms: u64 },
+    /// Holds the highest value, which then decays by the specified amount per second
+    PeakHold { decay_per_second: f64 },
+    /// Lowest value since the start or the last reset
+    SessionMin,
+    /// Highest value since the start or the last reset
+    SessionMax,
 }
 
 /// Config for a single network device
@@ -19,7 +384,26 @@ pub struct NetworkDeviceConfig {
     pub id: String,
     pub name: String,
     pub address: String,
+    /// Tcp port of the device, used if the address does not contain a port
+    #[serde(default = "default_network_port")]
+    pub port: u16,
     pub active: bool,
+    /// Interval in milliseconds in which the render data is pushed to the device
+    #[serde(default = "default_push_rate_ms")]
+    pub push_rate_ms: u64,
+    /// Sends the layout once and then only the changed sensor values, requires a display that supports it
+    #[serde(default)]
+    pub delta_frames: bool,
+    /// Interval in milliseconds in which the layout is sent again, if delta frames are enabled
+    #[serde(default = "default_resync_interval_ms")]
+    pub resync_interval_ms: u64,
+    /// Shows the sensors of text elements in readable units, e.g. 3.4 GHz instead of 3400000000 Hz
+    #[serde(default = "default_auto_scale_units")]
+    pub auto_scale_units: bool,
+    /// Time window in seconds shown by graph elements, by element id.
+    /// Graphs without time window show the complete history.
+    #[serde(default)]
+    pub graph_window_seconds: HashMap<String, u64>,
     pub display_config: DisplayConfig,
 }
 
@@ -29,10 +413,69 @@ impl NetworkDeviceConfig {
             id: Uuid::new_v4().to_string(),
             name: "A new device".to_string(),
             address: "".to_string(),
+            port: DEFAULT_NETWORK_PORT,
             active: false,
+            push_rate_ms: DEFAULT_PUSH_RATE_MS,
+            delta_frames: false,
+            resync_interval_ms: DEFAULT_RESYNC_INTERVAL_MS,
+            auto_scale_units: true,
+            graph_window_seconds: HashMap::new(),
             display_config: Default::default(),
         }
     }
+
+    /// Returns the time window shown by the graph element, or None for the complete history
+    pub fn get_graph_window(&self, element_id: &str) -> Option<Duration> {
+        self.graph_window_seconds
+            .get(element_id)
+            .map(|seconds| Duration::from_secs(*seconds))
+    }
+
+    /// Returns the push interval of the device, bounded to the allowed range
+    pub fn push_rate(&self) -> Duration {
+        Duration::from_millis(self.push_rate_ms.clamp(MIN_PUSH_RATE_MS, MAX_PUSH_RATE_MS))
+    }
+
+    /// Returns the interval in which the layout is sent again, at least the push interval
+    pub fn resync_interval(&self) -> Duration {
+        Duration::from_millis(self.resync_interval_ms).max(self.push_rate())
+    }
+}
+
+/// Returns the default push rate, used for configs that were created without a push rate
+fn default_push_rate_ms() -> u64 {
+    DEFAULT_PUSH_RATE_MS
+}
+
+fn default_resync_interval_ms() -> u64 {
+    DEFAULT_RESYNC_INTERVAL_MS
+}
+
+fn default_auto_scale_units() -> bool {
+    true
+}
+
+/// Returns the default port, used for configs that were created without a port
+fn default_true() -> bool {
+    true
+}
+
+fn default_alert_cooldown_ms() -> u64 {
+    DEFAULT_ALERT_COOLDOWN_MS
+}
+
+fn default_network_port() -> u16 {
+    DEFAULT_NETWORK_PORT
+}
+
+/// Returns the default http server address, used for configs that were created without an address
+fn default_http_server_address() -> String {
+    DEFAULT_HTTP_SERVER_ADDRESS.to_string()
+}
+
+/// Returns the default http server port, used for configs that were created without a port
+fn default_http_server_port() -> u16 {
+    DEFAULT_HTTP_SERVER_PORT
 }
 
 pub fn create_network_device_config() -> NetworkDeviceConfig {
@@ -41,6 +484,21 @@ pub fn create_network_device_config() -> NetworkDeviceConfig {
     new_config
 }
 
+/// Creates a new network device config from a discovered device and saves it to disk.
+/// Name, address, port and resolution are taken from the discovered device.
+pub fn create_network_device_config_from(
+    discovered_device: &DiscoveredDevice,
+) -> NetworkDeviceConfig {
+    let mut new_config = NetworkDeviceConfig::default();
+    new_config.name = discovered_device.name.clone();
+    new_config.address = discovered_device.address.clone();
+    new_config.port = discovered_device.port;
+    new_config.display_config.resolution_width = discovered_device.resolution_width;
+    new_config.display_config.resolution_height = discovered_device.resolution_height;
+    write(&new_config);
+    new_config
+}
+
 /// Loads the config file from disk.
 /// If the file does not exist, it will be created.
 /// Returns the config for the specified network device.
@@ -63,7 +521,7 @@ pub fn write(net_port_config: &NetworkDeviceConfig) {
 
 /// Writes the specified config to disk.
 /// If the config file does not exist, it will be created.
-fn write_to_app_config(config: &AppConfig) {
+pub fn write_to_app_config(config: &AppConfig) {
     let config_path = get_config_path();
     let config_file = File::create(config_path).expect("Failed to create config file");
     serde_json::to_writer_pretty(config_file, &config).expect("Failed to write config file");
@@ -112,6 +570,13 @@ fn get_config_path() -> String {
         .to_string()
 }
 
+/// Enables or disables the specified sensor provider and saves it to disk.
+pub fn set_sensor_provider_enabled(provider_name: &str, enabled: bool) {
+    let mut config: AppConfig = read_from_app_config();
+    config.set_sensor_provider_enabled(provider_name, enabled);
+    write_to_app_config(&config);
+}
+
 /// Removes the specified network device from the config file.
 pub fn remove(network_device_id: &str) {
     let mut config: AppConfig = read_from_app_config();
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use assertor::{assert_that, EqualityAssertion};
+
+use crate::config;
+use crate::config::{AppConfig, DerivedSensorConfig, NetworkDeviceConfig, SensorOverrideConfig};
+
+#[test]
+fn test_push_rate_defaults_for_legacy_config() {
+    // GIVEN is a device config that was written before the push rate existed
+    let device_config: NetworkDeviceConfig = Default::default();
+    let mut legacy_config = serde_json::to_value(device_config).unwrap();
+    legacy_config
+        .as_object_mut()
+        .unwrap()
+        .remove("push_rate_ms");
+
+    // WHEN deserializing the config
+    let device_config: NetworkDeviceConfig = serde_json::from_value(legacy_config).unwrap();
+
+    // THEN the default push rate should be used
+    assert_that!(device_config.push_rate_ms).is_equal_to(config::DEFAULT_PUSH_RATE_MS);
+}
+
+#[test]
+fn test_push_rate_survives_serialization() {
+    // GIVEN is a device config with a custom push rate
+    let device_config = NetworkDeviceConfig {
+        push_rate_ms: 250,
+        ..Default::default()
+    };
+
+    // WHEN serializing and deserializing the config, like export and import does
+    let json = serde_json::to_string(&device_config).unwrap();
+    let device_config: NetworkDeviceConfig = serde_json::from_str(&json).unwrap();
+
+    // THEN the push rate should be preserved
+    assert_that!(device_config.push_rate_ms).is_equal_to(250);
+}
+
+#[test]
+fn test_push_rate_is_bounded() {
+    // GIVEN are device configs with out of bounds push rates
+    let too_fast = NetworkDeviceConfig {
+        push_rate_ms: 1,
+        ..Default::default()
+    };
+    let too_slow = NetworkDeviceConfig {
+        push_rate_ms: u64::MAX,
+        ..Default::default()
+    };
+
+    // WHEN getting the push rate
+    let too_fast = too_fast.push_rate();
+    let too_slow = too_slow.push_rate();
+
+    // THEN the push rates should be clamped to the bounds
+    assert_that!(too_fast).is_equal_to(Duration::from_millis(config::MIN_PUSH_RATE_MS));
+    assert_that!(too_slow).is_equal_to(Duration::from_millis(config::MAX_PUSH_RATE_MS));
+}
+
+#[test]
+fn test_replace_sensor_id_prefixes() {
+    // GIVEN is a config that references legacy sensor ids in an override, a mqtt sensor and a derived sensor expression,
+    // and has a label that contains the legacy sensor id
+    let mut app_config = AppConfig::default();
+    app_config.sensor_overrides.insert(
+        "gpu_card1_gpu_busy_percent".to_string(),
+        SensorOverrideConfig {
+            label: Some("GPU".to_string()),
+            ..Default::default()
+        },
+    );
+    app_config.derived_sensors.push(DerivedSensorConfig {
+        id: "gpu_load_sum".to_string(),
+        label: "gpu_card1_gpu_busy_percent sum".to_string(),
+        unit: "%".to_string(),
+        expression: "{gpu_card1_gpu_busy_percent} + {xgpu_card1_load}".to_string(),
+    });
+    app_config
+        .mqtt
+        .sensor_ids
+        .push("gpu_card1_gpu_busy_percent".to_string());
+    let prefixes = vec![("gpu_card1_".to_string(), "gpu_0000_03_00_0_".to_string())];
+
+    // WHEN replacing the prefixes twice
+    let replaced = app_config.replace_sensor_id_prefixes(&prefixes);
+    let replaced_again = app_config.replace_sensor_id_prefixes(&prefixes);
+
+    // THEN the sensor ids should be replaced once, but not ids that only contain the prefix and not labels
+    assert_that!(replaced).is_equal_to(true);
+    assert_that!(replaced_again).is_equal_to(false);
+    assert_that!(app_config
+        .sensor_overrides
+        .contains_key("gpu_0000_03_00_0_gpu_busy_percent"))
+    .is_equal_to(true);
+    assert_that!(app_config.derived_sensors[0].expression.clone())
+        .is_equal_to("{gpu_0000_03_00_0_gpu_busy_percent} + {xgpu_card1_load}".to_string());
+    assert_that!(app_config.mqtt.sensor_ids.clone())
+        .is_equal_to(vec!["gpu_0000_03_00_0_gpu_busy_percent".to_string()]);
+    assert_that!(app_config.derived_sensors[0].label.clone())
+        .is_equal_to("gpu_card1_gpu_busy_percent sum".to_string());
+}
@@ -0,0 +1,406 @@
+use std::collections::{HashMap, HashSet};
+
+use log::warn;
+use regex::Regex;
+use sensor_core::{SensorType, SensorValue};
+
+use crate::config::DerivedSensorConfig;
+use crate::utils;
+
+/// Prefix of the ids of all derived sensors, to not collide with the ids of provided sensors
+const DERIVED_SENSOR_ID_PREFIX: &str = "derived_";
+
+/// A parsed expression of a derived sensor.
+///
+/// Grammar:
+/// - Sensors are referenced by their id in braces, e.g. `{cpu_load_total}`
+/// - Numbers, `+`, `-`, `*`, `/` and parentheses
+/// - `min(...)`, `max(...)`, `avg(...)` and `sum(...)` over expressions and regex selections,
+///   e.g. `max(/^lm_coretemp-.*-Core \d+$/)`, which select all sensors whose id matches
+#[derive(Debug)]
+pub enum Expression {
+    Number(f64),
+    Sensor(String),
+    Negate(Box<Expression>),
+    Binary(Operator, Box<Expression>, Box<Expression>),
+    Aggregate(Aggregation, Vec<Argument>),
+}
+
+/// Arithmetic operator of a binary expression
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+/// Function over multiple values
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aggregation {
+    Min,
+    Max,
+    Avg,
+    Sum,
+}
+
+/// Argument of an aggregation, either a single expression or all sensors matching a regex
+#[derive(Debug)]
+pub enum Argument {
+    Expression(Expression),
+    Selection(Regex),
+}
+
+/// A derived sensor with its parsed expression
+pub struct DerivedSensor {
+    pub config: DerivedSensorConfig,
+    expression: Expression,
+}
+
+impl DerivedSensor {
+    /// Parses the expression of the derived sensor config
+    pub fn new(config: DerivedSensorConfig) -> Result<DerivedSensor, String> {
+        let expression = parse(&config.expression)
+            .map_err(|err| format!("Invalid expression of sensor '{}': {err}", config.id))?;
+
+        Ok(DerivedSensor { config, expression })
+    }
+
+    /// Returns the id of the sensor value
+    pub fn get_sensor_id(&self) -> String {
+        format!("{DERIVED_SENSOR_ID_PREFIX}{}", self.config.id)
+    }
+
+    /// Returns the ids of all sensors the expression depends on.
+    /// Regex selections are resolved against the known sensor ids.
+    pub fn get_dependencies(&self, known_sensor_ids: &[String]) -> HashSet<String> {
+        let mut sensor_ids = HashSet::new();
+        self.expression
+            .collect_sensor_ids(known_sensor_ids, &mut sensor_ids);
+        sensor_ids
+    }
+}
+
+/// Parses the expressions of all derived sensor configs.
+/// Invalid expressions are logged and their sensors are skipped.
+pub fn create_all(derived_sensor_configs: &[DerivedSensorConfig]) -> Vec<DerivedSensor> {
+    derived_sensor_configs
+        .iter()
+        .filter_map(|config| {
+            DerivedSensor::new(config.clone())
+                .inspect_err(|err| warn!("{err}"))
+                .ok()
+        })
+        .collect()
+}
+
+/// Evaluates the derived sensors against the sensor values.
+/// Derived sensors, whose expression can not be evaluated, e.g. because a sensor is missing, are skipped.
+pub fn evaluate(
+    derived_sensors: &[DerivedSensor],
+    sensor_values: &[SensorValue],
+) -> Vec<SensorValue> {
+    let values: HashMap<&str, f64> = sensor_values
+        .iter()
+        .filter(|sensor_value| matches!(sensor_value.sensor_type, SensorType::Number))
+        .filter_map(|sensor_value| {
+            let value = sensor_value.value.trim().parse().ok()?;
+            Some((sensor_value.id.as_str(), value))
+        })
+        .collect();
+
+    derived_sensors
+        .iter()
+        .filter_map(|derived_sensor| {
+            let value = derived_sensor.expression.evaluate(&values)?;
+            Some(SensorValue {
+                id: derived_sensor.get_sensor_id(),
+                value: utils::format_number(value),
+                unit: derived_sensor.config.unit.clone(),
+                label: derived_sensor.config.label.clone(),
+                sensor_type: SensorType::Number,
+            })
+        })
+        .collect()
+}
+
+impl Expression {
+    /// Evaluates the expression, returns None if a sensor is missing or the result is not finite
+    pub fn evaluate(&self, values: &HashMap<&str, f64>) -> Option<f64> {
+        let result = match self {
+            Expression::Number(number) => *number,
+            Expression::Sensor(sensor_id) => *values.get(sensor_id.as_str())?,
+            Expression::Negate(expression) => -expression.evaluate(values)?,
+            Expression::Binary(operator, left, right) => {
+                let left = left.evaluate(values)?;
+                let right = right.evaluate(values)?;
+                match operator {
+                    Operator::Add => left + right,
+                    Operator::Subtract => left - right,
+                    Operator::Multiply => left * right,
+                    Operator::Divide => left / right,
+                }
+            }
+            Expression::Aggregate(aggregation, arguments) => {
+                let mut numbers = vec![];
+                for argument in arguments {
+                    match argument {
+                        Argument::Expression(expression) => {
+                            numbers.push(expression.evaluate(values)?)
+                        }
+                        Argument::Selection(regex) => numbers.extend(
+                            values
+                                .iter()
+                                .filter(|(sensor_id, _)| regex.is_match(sensor_id))
+                                .map(|(_, value)| *value),
+                        ),
+                    }
+                }
+                if numbers.is_empty() {
+                    return None;
+                }
+                match aggregation {
+                    Aggregation::Min => numbers.iter().copied().fold(f64::INFINITY, f64::min),
+                    Aggregation::Max => numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                    Aggregation::Avg => numbers.iter().sum::<f64>() / numbers.len() as f64,
+                    Aggregation::Sum => numbers.iter().sum(),
+                }
+            }
+        };
+
+        result.is_finite().then_some(result)
+    }
+
+    /// Adds the ids of all referenced and selected sensors
+    fn collect_sensor_ids(&self, known_sensor_ids: &[String], sensor_ids: &mut HashSet<String>) {
+        match self {
+            Expression::Number(_) => {}
+            Expression::Sensor(sensor_id) => {
+                sensor_ids.insert(sensor_id.clone());
+            }
+            Expression::Negate(expression) => {
+                expression.collect_sensor_ids(known_sensor_ids, sensor_ids)
+            }
+            Expression::Binary(_, left, right) => {
+                left.collect_sensor_ids(known_sensor_ids, sensor_ids);
+                right.collect_sensor_ids(known_sensor_ids, sensor_ids);
+            }
+            Expression::Aggregate(_, arguments) => {
+                for argument in arguments {
+                    match argument {
+                        Argument::Expression(expression) => {
+                            expression.collect_sensor_ids(known_sensor_ids, sensor_ids)
+                        }
+                        Argument::Selection(regex) => sensor_ids.extend(
+                            known_sensor_ids
+                                .iter()
+                                .filter(|sensor_id| regex.is_match(sensor_id))
+                                .cloned(),
+                        ),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Parses the expression, returns a description of the first syntax error
+pub fn parse(expression: &str) -> Result<Expression, String> {
+    let mut parser = Parser {
+        chars: expression.chars().collect(),
+        position: 0,
+    };
+
+    let parsed = parser.parse_sum()?;
+    parser.skip_whitespace();
+    match parser.peek() {
+        None => Ok(parsed),
+        Some(char) => Err(parser.error(&format!("Unexpected '{char}'"))),
+    }
+}
+
+/// Recursive descent parser of expressions
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    /// sum := product (('+' | '-') product)*
+    fn parse_sum(&mut self) -> Result<Expression, String> {
+        let mut expression = self.parse_product()?;
+        loop {
+            let operator = match self.peek_token() {
+                Some('+') => Operator::Add,
+                Some('-') => Operator::Subtract,
+                _ => return Ok(expression),
+            };
+            self.position += 1;
+            let right = self.parse_product()?;
+            expression = Expression::Binary(operator, Box::new(expression), Box::new(right));
+        }
+    }
+
+    /// product := unary (('*' | '/') unary)*
+    fn parse_product(&mut self) -> Result<Expression, String> {
+        let mut expression = self.parse_unary()?;
+        loop {
+            let operator = match self.peek_token() {
+                Some('*') => Operator::Multiply,
+                Some('/') => Operator::Divide,
+                _ => return Ok(expression),
+            };
+            self.position += 1;
+            let right = self.parse_unary()?;
+            expression = Expression::Binary(operator, Box::new(expression), Box::new(right));
+        }
+    }
+
+    /// unary := '-' unary | primary
+    fn parse_unary(&mut self) -> Result<Expression, String> {
+        if self.peek_token() == Some('-') {
+            self.position += 1;
+            return Ok(Expression::Negate(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    /// primary := number | '{' sensor id '}' | function '(' arguments ')' | '(' sum ')'
+    fn parse_primary(&mut self) -> Result<Expression, String> {
+        match self.peek_token() {
+            Some('(') => {
+                self.position += 1;
+                let expression = self.parse_sum()?;
+                self.expect(')')?;
+                Ok(expression)
+            }
+            Some('{') => {
+                self.position += 1;
+                let sensor_id = self.take_until('}')?;
+                Ok(Expression::Sensor(sensor_id.trim().to_string()))
+            }
+            Some(char) if char.is_ascii_digit() || char == '.' => self.parse_number(),
+            Some(char) if char.is_ascii_alphabetic() => self.parse_aggregate(),
+            Some(char) => Err(self.error(&format!("Unexpected '{char}'"))),
+            None => Err(self.error("Unexpected end of expression")),
+        }
+    }
+
+    /// aggregate := ('min' | 'max' | 'avg' | 'sum') '(' argument (',' argument)* ')'
+    fn parse_aggregate(&mut self) -> Result<Expression, String> {
+        let start = self.position;
+        while self.peek().is_some_and(|char| char.is_ascii_alphabetic()) {
+            self.position += 1;
+        }
+        let name: String = self.chars[start..self.position].iter().collect();
+        let aggregation = match name.as_str() {
+            "min" => Aggregation::Min,
+            "max" => Aggregation::Max,
+            "avg" => Aggregation::Avg,
+            "sum" => Aggregation::Sum,
+            _ => return Err(self.error(&format!("Unknown function '{name}'"))),
+        };
+
+        self.expect('(')?;
+        let mut arguments = vec![self.parse_argument()?];
+        while self.peek_token() == Some(',') {
+            self.position += 1;
+            arguments.push(self.parse_argument()?);
+        }
+        self.expect(')')?;
+
+        Ok(Expression::Aggregate(aggregation, arguments))
+    }
+
+    /// argument := '/' regex '/' | sum
+    fn parse_argument(&mut self) -> Result<Argument, String> {
+        if self.peek_token() != Some('/') {
+            return Ok(Argument::Expression(self.parse_sum()?));
+        }
+
+        self.position += 1;
+        let mut pattern = String::new();
+        loop {
+            match self.peek() {
+                Some('\\') if self.chars.get(self.position + 1) == Some(&'/') => {
+                    pattern.push('/');
+                    self.position += 2;
+                }
+                Some('/') => {
+                    self.position += 1;
+                    break;
+                }
+                Some(char) => {
+                    pattern.push(char);
+                    self.position += 1;
+                }
+                None => return Err(self.error("Unterminated regex")),
+            }
+        }
+
+        Regex::new(&pattern)
+            .map(Argument::Selection)
+            .map_err(|err| self.error(&format!("Invalid regex: {err}")))
+    }
+
+    /// number := digits ['.' digits]
+    fn parse_number(&mut self) -> Result<Expression, String> {
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|char| char.is_ascii_digit() || char == '.')
+        {
+            self.position += 1;
+        }
+        let number: String = self.chars[start..self.position].iter().collect();
+
+        number
+            .parse()
+            .map(Expression::Number)
+            .map_err(|_| self.error(&format!("Invalid number '{number}'")))
+    }
+
+    /// Consumes all characters until the delimiter, which is consumed as well
+    fn take_until(&mut self, delimiter: char) -> Result<String, String> {
+        let start = self.position;
+        while self.peek().is_some_and(|char| char != delimiter) {
+            self.position += 1;
+        }
+        if self.peek().is_none() {
+            return Err(self.error(&format!("Expected '{delimiter}'")));
+        }
+        let taken = self.chars[start..self.position].iter().collect();
+        self.position += 1;
+        Ok(taken)
+    }
+
+    /// Consumes the expected character, after optional whitespace
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        if self.peek_token() != Some(expected) {
+            return Err(self.error(&format!("Expected '{expected}'")));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    /// Returns the next character after optional whitespace, without consuming it
+    fn peek_token(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.peek()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    /// Returns the error message with the position in the expression
+    fn error(&self, message: &str) -> String {
+        format!("{message} at position {}", self.position + 1)
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+
+use assertor::{assert_that, EqualityAssertion};
+use sensor_core::SensorValue;
+
+use crate::config::DerivedSensorConfig;
+use crate::derived_sensor;
+use crate::derived_sensor::DerivedSensor;
+use crate::test_utils::create_sensor_value;
+
+fn create_derived_sensor(expression: &str) -> DerivedSensor {
+    DerivedSensor::new(DerivedSensorConfig {
+        id: "test".to_string(),
+        label: "Test".to_string(),
+        unit: "°C".to_string(),
+        expression: expression.to_string(),
+    })
+    .unwrap()
+}
+
+/// Evaluates the expression against the sensor values and returns the derived value
+fn evaluate(expression: &str, sensor_values: &[SensorValue]) -> Option<String> {
+    derived_sensor::evaluate(&[create_derived_sensor(expression)], sensor_values)
+        .first()
+        .map(|sensor_value| sensor_value.value.clone())
+}
+
+#[test]
+fn test_evaluate_arithmetic() {
+    // GIVEN are two temperature sensors
+    let sensor_values = vec![
+        create_sensor_value("cpu_temp", "60", "°C"),
+        create_sensor_value("ambient_temp", "25", "°C"),
+    ];
+
+    // WHEN evaluating arithmetic expressions
+    let delta = evaluate("{cpu_temp} - {ambient_temp}", &sensor_values);
+    let precedence = evaluate("2 + 3 * {ambient_temp} / 5", &sensor_values);
+    let parentheses = evaluate("-(2 + 3) * 2.5", &sensor_values);
+
+    // THEN multiplication and division should bind stronger than addition and subtraction
+    assert_that!(delta).is_equal_to(Some("35".to_string()));
+    assert_that!(precedence).is_equal_to(Some("17".to_string()));
+    assert_that!(parentheses).is_equal_to(Some("-12.5".to_string()));
+}
+
+#[test]
+fn test_evaluate_aggregates() {
+    // GIVEN are the temperatures of three cores and another sensor
+    let sensor_values = vec![
+        create_sensor_value("core_0", "50", "°C"),
+        create_sensor_value("core_1", "70", "°C"),
+        create_sensor_value("core_2", "60", "°C"),
+        create_sensor_value("gpu_temp", "80", "°C"),
+    ];
+
+    // WHEN aggregating the cores selected by a regex
+    let max = evaluate("max(/^core_\\d+$/)", &sensor_values);
+    let avg = evaluate("avg(/^core_/)", &sensor_values);
+    let min = evaluate("min(/^core_/, {gpu_temp} - 40)", &sensor_values);
+    let sum = evaluate("sum({core_0}, {core_1})", &sensor_values);
+
+    // THEN only the matching sensors and the explicit arguments should be aggregated
+    assert_that!(max).is_equal_to(Some("70".to_string()));
+    assert_that!(avg).is_equal_to(Some("60".to_string()));
+    assert_that!(min).is_equal_to(Some("40".to_string()));
+    assert_that!(sum).is_equal_to(Some("120".to_string()));
+}
+
+#[test]
+fn test_evaluate_unavailable() {
+    // GIVEN is a single sensor, that is zero
+    let sensor_values = vec![create_sensor_value("fan_rpm", "0", "°C")];
+
+    // WHEN evaluating expressions with missing sensors or invalid results
+    let missing_sensor = evaluate("{fan_rpm} + {pump_rpm}", &sensor_values);
+    let empty_selection = evaluate("max(/^pump_/)", &sensor_values);
+    let division_by_zero = evaluate("100 / {fan_rpm}", &sensor_values);
+
+    // THEN no derived value should be produced
+    assert_that!(missing_sensor).is_equal_to(None);
+    assert_that!(empty_selection).is_equal_to(None);
+    assert_that!(division_by_zero).is_equal_to(None);
+}
+
+#[test]
+fn test_parse_errors() {
+    // GIVEN are invalid expressions
+    let expressions = [
+        "{cpu_temp} +",
+        "{cpu_temp",
+        "median({cpu_temp})",
+        "max(/[/)",
+        "(1 + 2",
+        "1 2",
+    ];
+
+    // WHEN parsing the expressions
+    let error_count = expressions
+        .iter()
+        .filter(|expression| derived_sensor::parse(expression).is_err())
+        .count();
+
+    // THEN every expression should be rejected
+    assert_that!(error_count).is_equal_to(expressions.len());
+}
+
+#[test]
+fn test_get_dependencies() {
+    // GIVEN is a derived sensor referencing a sensor and a regex selection
+    let derived_sensor = create_derived_sensor("{ambient_temp} + max(/^core_/)");
+    let known_sensor_ids = vec![
+        "core_0".to_string(),
+        "core_1".to_string(),
+        "gpu_temp".to_string(),
+    ];
+
+    // WHEN getting the dependencies
+    let dependencies = derived_sensor.get_dependencies(&known_sensor_ids);
+
+    // THEN the referenced sensor and all known matching sensors should be returned
+    let expected: HashSet<String> = ["ambient_temp", "core_0", "core_1"]
+        .iter()
+        .map(|sensor_id| sensor_id.to_string())
+        .collect();
+    assert_that!(dependencies).is_equal_to(expected);
+    assert_that!(derived_sensor.get_sensor_id()).is_equal_to("derived_test".to_string());
+}
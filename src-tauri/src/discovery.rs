@@ -0,0 +1,117 @@
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+/// Udp port on which sensor-display instances answer discovery probes
+pub const DISCOVERY_PORT: u16 = 10490;
+
+/// Payload of the discovery probe, that is broadcast to the local network
+pub const DISCOVERY_PROBE: &[u8] = b"SENSOR_BRIDGE_DISCOVERY";
+
+/// Duration to wait for responses after sending the probe
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A sensor-display instance that answered the discovery probe
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DiscoveredDevice {
+    pub name: String,
+    pub address: String,
+    pub port: u16,
+    pub resolution_width: u32,
+    pub resolution_height: u32,
+}
+
+/// Response of a sensor-display instance to the discovery probe.
+/// The address is not part of the response, it is taken from the sender of the udp packet.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DiscoveryResponse {
+    pub name: String,
+    #[serde(default = "default_network_port")]
+    pub port: u16,
+    pub resolution_width: u32,
+    pub resolution_height: u32,
+}
+
+/// Broadcasts the discovery probe to the local network.
+/// Returns all sensor-display instances that answered within the discovery timeout.
+pub fn discover() -> Vec<DiscoveredDevice> {
+    let broadcast_address = SocketAddr::from((Ipv4Addr::BROADCAST, DISCOVERY_PORT));
+    discover_at(broadcast_address, DISCOVERY_TIMEOUT)
+}
+
+/// Sends the discovery probe to the specified target address.
+/// Returns all sensor-display instances that answered within the specified timeout.
+/// Each device is only listed once, even if it answered multiple times.
+pub fn discover_at(target_address: SocketAddr, timeout: Duration) -> Vec<DiscoveredDevice> {
+    let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)) {
+        Ok(socket) => socket,
+        Err(err) => {
+            error!("Could not bind discovery socket: {err}");
+            return vec![];
+        }
+    };
+
+    if let Err(err) = socket
+        .set_broadcast(true)
+        .and_then(|_| socket.send_to(DISCOVERY_PROBE, target_address))
+    {
+        error!("Could not send discovery probe to {target_address}: {err}");
+        return vec![];
+    }
+
+    info!("Sent discovery probe to {target_address}");
+
+    let mut discovered_devices: Vec<DiscoveredDevice> = vec![];
+    let mut buffer = [0u8; 4096];
+    let deadline = Instant::now() + timeout;
+
+    // Collect responses until the deadline is reached
+    while let Some(remaining_time) = deadline.checked_duration_since(Instant::now()) {
+        if remaining_time.is_zero() || socket.set_read_timeout(Some(remaining_time)).is_err() {
+            break;
+        }
+
+        let (received_bytes, sender_address) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(_) => break,
+        };
+
+        let response: DiscoveryResponse = match serde_json::from_slice(&buffer[..received_bytes]) {
+            Ok(response) => response,
+            Err(err) => {
+                debug!("Ignoring invalid discovery response from {sender_address}: {err}");
+                continue;
+            }
+        };
+
+        let discovered_device = DiscoveredDevice {
+            name: response.name,
+            address: sender_address.ip().to_string(),
+            port: response.port,
+            resolution_width: response.resolution_width,
+            resolution_height: response.resolution_height,
+        };
+
+        let is_known = discovered_devices.iter().any(|device| {
+            device.address == discovered_device.address && device.port == discovered_device.port
+        });
+        if !is_known {
+            info!(
+                "Discovered device {}({}:{})",
+                discovered_device.name, discovered_device.address, discovered_device.port
+            );
+            discovered_devices.push(discovered_device);
+        }
+    }
+
+    discovered_devices
+}
+
+/// Returns the default port, used if the display did not report its port
+fn default_network_port() -> u16 {
+    config::DEFAULT_NETWORK_PORT
+}
@@ -0,0 +1,66 @@
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+
+use assertor::{assert_that, EqualityAssertion};
+
+use crate::discovery;
+use crate::discovery::{DiscoveredDevice, DiscoveryResponse};
+
+/// Starts a responder on loopback, that answers the first received discovery probe.
+/// Returns the address of the responder.
+fn start_local_responder(response: DiscoveryResponse) -> std::net::SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let responder_address = socket.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut buffer = [0u8; 1024];
+        let (received_bytes, sender_address) = socket.recv_from(&mut buffer).unwrap();
+        if &buffer[..received_bytes] == discovery::DISCOVERY_PROBE {
+            let response = serde_json::to_vec(&response).unwrap();
+            socket.send_to(&response, sender_address).unwrap();
+            // Answer twice, to simulate a display that is reachable via multiple broadcasts
+            socket.send_to(&response, sender_address).unwrap();
+        }
+    });
+
+    responder_address
+}
+
+#[test]
+fn test_discover_local_responder() {
+    // GIVEN is a sensor-display responder on loopback
+    let responder_address = start_local_responder(DiscoveryResponse {
+        name: "Gaming overlay".to_string(),
+        port: 10500,
+        resolution_width: 800,
+        resolution_height: 480,
+    });
+
+    // WHEN discovering devices
+    let discovered_devices = discovery::discover_at(responder_address, Duration::from_millis(500));
+
+    // THEN the responder should be found exactly once, with its sender address
+    assert_that!(discovered_devices).is_equal_to(vec![DiscoveredDevice {
+        name: "Gaming overlay".to_string(),
+        address: "127.0.0.1".to_string(),
+        port: 10500,
+        resolution_width: 800,
+        resolution_height: 480,
+    }]);
+}
+
+#[test]
+fn test_discover_without_responder() {
+    // GIVEN is a socket that never answers
+    let silent_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    // WHEN discovering devices
+    let discovered_devices = discovery::discover_at(
+        silent_socket.local_addr().unwrap(),
+        Duration::from_millis(200),
+    );
+
+    // THEN no device should be found
+    assert_that!(discovered_devices).is_equal_to(vec![]);
+}
@@ -2,20 +2,21 @@ use std::fs;
 
 use sensor_core::ElementType;
 use serde::{Deserialize, Serialize};
-use serde_json::Error;
 
 use crate::config::AppConfig;
-use crate::{config, fonts, utils};
+use crate::{config, fonts, sensor_transform, utils};
 
 /// Exports the current configuration to the specified file.
-pub fn export_configuration(file_path: String) {
+/// Credentials are left out, an import keeps the credentials of the existing configuration.
+pub fn export_configuration(file_path: String) -> Result<(), String> {
     // Read the current config
     let mut app_config: AppConfig = config::read_from_app_config();
 
-    inline_files(&mut app_config);
+    inline_files(&mut app_config)?;
+    app_config.mqtt.password = String::new();
 
     // Serialize the config to a JSON string
-    let json_config = serde_json::to_string_pretty(&app_config).unwrap();
+    let json_config = serde_json::to_string_pretty(&app_config).map_err(|err| err.to_string())?;
     let file_path = if file_path.ends_with(".json") {
         file_path
     } else {
@@ -23,11 +24,12 @@ pub fn export_configuration(file_path: String) {
     };
 
     // Write the JSON string to the specified file
-    fs::write(file_path, json_config).unwrap();
+    fs::write(&file_path, json_config)
+        .map_err(|err| format!("Failed to write {}: {}", file_path, err))
 }
 
 /// Inlines all files in the config as base64 encoded string.
-fn inline_files(app_config: &mut AppConfig) {
+fn inline_files(app_config: &mut AppConfig) -> Result<(), String> {
     for network_device in app_config.network_devices.values_mut() {
         for element in &mut network_device.display_config.elements {
             match element.element_type {
@@ -52,7 +54,8 @@ fn inline_files(app_config: &mut AppConfig) {
                         continue;
                     }
 
-                    let img_data = fs::read(file_path).unwrap();
+                    let img_data = fs::read(file_path)
+                        .map_err(|err| format!("Failed to read {}: {}", file_path, err))?;
                     let img_base64 = to_base64_string(img_data);
                     img_config.image_path = img_base64;
                 }
@@ -67,23 +70,32 @@ fn inline_files(app_config: &mut AppConfig) {
                         continue;
                     };
 
-                    let img_data = fs::read(&cond_image_config.images_path).unwrap();
+                    let img_data = fs::read(&cond_image_config.images_path).map_err(|err| {
+                        format!("Failed to read {}: {}", cond_image_config.images_path, err)
+                    })?;
                     let img_base64 = to_base64_string(img_data);
                     cond_image_config.images_path = img_base64;
                 }
             }
         }
     }
+
+    Ok(())
 }
 
-/// Imports the configuration from the specified file.
-pub fn import_configuration(file_path: String) -> Result<AppConfig, Error> {
-    let json_config = fs::read_to_string(file_path).unwrap();
-    let mut app_config: AppConfig = serde_json::from_str(&json_config)?;
+/// Imports the configuration from the specified file and saves it as the new configuration.
+/// As exports leave credentials out, the credentials of the existing configuration are kept.
+pub fn import_configuration(file_path: String) -> Result<AppConfig, String> {
+    let json_config = fs::read_to_string(&file_path)
+        .map_err(|err| format!("Failed to read {}: {}", file_path, err))?;
+    let mut app_config: AppConfig =
+        serde_json::from_str(&json_config).map_err(|err| err.to_string())?;
+    sensor_transform::verify_config(&app_config.sensor_transforms)?;
+    keep_credentials(&mut app_config, &config::read_from_app_config());
 
-    // Cleanup the config dir (remove and crate)
-    let _ = fs::remove_dir_all(sensor_core::get_config_dir());
-    let _ = fs::create_dir_all(sensor_core::get_config_dir());
+    // Cleanup the unpacked images of the previous configuration
+    let _ = fs::remove_dir_all(sensor_core::get_config_dir().join("static-image"));
+    let _ = fs::remove_dir_all(sensor_core::get_config_dir().join("conditional-image"));
 
     for network_device in app_config.network_devices.values_mut() {
         for element in &mut network_device.display_config.elements {
@@ -145,9 +157,23 @@ pub fn import_configuration(file_path: String) -> Result<AppConfig, Error> {
         }
     }
 
+    config::write_to_app_config(&app_config);
+
     Ok(app_config)
 }
 
+/// Keeps the mqtt password of the existing config, if the imported config has none for the same broker and user.
+fn keep_credentials(app_config: &mut AppConfig, existing_app_config: &AppConfig) {
+    let mqtt = &mut app_config.mqtt;
+    let existing_mqtt = &existing_app_config.mqtt;
+    if mqtt.password.is_empty()
+        && mqtt.host == existing_mqtt.host
+        && mqtt.username == existing_mqtt.username
+    {
+        mqtt.password = existing_mqtt.password.clone();
+    }
+}
+
 /// Checks if the given string is a valid json.
 fn is_json(some_string: &str) -> bool {
     serde_json::from_str::<serde_json::Value>(some_string).is_ok()
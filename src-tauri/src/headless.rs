@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{error, info, warn, Level};
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+
+use crate::utils::LockResultExt;
+use crate::ThreadHandle;
+
+/// Command line argument to run the sync engine without the Tauri window and tray icon
+pub const HEADLESS_ARG: &str = "--headless";
+
+/// Maximum duration to wait for the sync threads to stop on shutdown
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Checks if the application was started in headless mode
+pub fn is_requested() -> bool {
+    std::env::args().any(|arg| arg == HEADLESS_ARG)
+}
+
+/// Initializes the logger for the headless mode, which logs info messages by default.
+/// If stderr is connected to the systemd journal, the log level is passed as syslog priority
+/// and the timestamp is omitted, as the journal records it itself.
+pub fn init_logger() {
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"));
+
+    if std::env::var_os("JOURNAL_STREAM").is_some() {
+        builder.format(|buf, record| {
+            writeln!(
+                buf,
+                "{}",
+                format_journal_line(record.level(), record.target(), &record.args().to_string())
+            )
+        });
+    }
+
+    builder.init();
+}
+
+/// Formats a log line for the systemd journal, prefixed with the syslog priority of the log level
+pub fn format_journal_line(level: Level, target: &str, message: &str) -> String {
+    format!("<{}>{target}: {message}", get_syslog_priority(level))
+}
+
+/// Returns the syslog priority of the log level, as understood by the systemd journal
+fn get_syslog_priority(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+/// Blocks until SIGTERM or SIGINT is received, then stops all sync threads.
+/// The sync threads must have been started before, as there is no UI to start them.
+pub fn run(port_handles: &Mutex<HashMap<String, ThreadHandle>>) {
+    info!(
+        "Running headless with {} active device(s)",
+        port_handles.lock().ignore_poison().len()
+    );
+
+    let mut signals = match Signals::new([SIGTERM, SIGINT]) {
+        Ok(signals) => signals,
+        Err(err) => {
+            error!("Could not register signal handler: {err}");
+            return;
+        }
+    };
+
+    if let Some(signal) = signals.forever().next() {
+        info!("Received signal {signal}, shutting down");
+    }
+
+    shutdown(port_handles, SHUTDOWN_TIMEOUT);
+}
+
+/// Stops all sync threads and waits until they finished, at most for the timeout.
+/// Returns true if all sync threads stopped within the timeout.
+pub fn shutdown(port_handles: &Mutex<HashMap<String, ThreadHandle>>, timeout: Duration) -> bool {
+    let port_handles = port_handles.lock().ignore_poison();
+
+    for thread_handle in port_handles.values() {
+        *thread_handle.running.lock().ignore_poison() = false;
+        thread_handle.handle.thread().unpark();
+    }
+
+    let deadline = Instant::now() + timeout;
+    while port_handles
+        .values()
+        .any(|thread_handle| !thread_handle.handle.is_finished())
+    {
+        if Instant::now() >= deadline {
+            warn!("Sync threads did not stop within {timeout:?}");
+            return false;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    info!("All sync threads stopped");
+    true
+}
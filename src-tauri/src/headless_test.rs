@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use assertor::{assert_that, BooleanAssertion, EqualityAssertion};
+use log::Level;
+
+use crate::headless;
+use crate::utils::LockResultExt;
+use crate::ThreadHandle;
+
+/// Starts a sync thread, that parks until it is stopped if it is cooperative, or keeps running otherwise
+fn start_thread(is_cooperative: bool) -> ThreadHandle {
+    let running = Arc::new(Mutex::new(true));
+    let thread_running = running.clone();
+    let handle = thread::spawn(move || {
+        if is_cooperative {
+            while *thread_running.lock().ignore_poison() {
+                thread::park();
+            }
+        } else {
+            thread::sleep(Duration::from_secs(2));
+        }
+    });
+
+    ThreadHandle {
+        running,
+        handle: Arc::new(handle),
+    }
+}
+
+#[test]
+fn test_format_journal_line() {
+    // GIVEN are log messages of different levels
+
+    // WHEN formatting them for the systemd journal
+    let error = headless::format_journal_line(Level::Error, "sensor_bridge", "Could not connect");
+    let info = headless::format_journal_line(Level::Info, "sensor_bridge", "Connected");
+    let trace = headless::format_journal_line(Level::Trace, "sensor_bridge", "Tick");
+
+    // THEN the lines should be prefixed with the syslog priority, without timestamp
+    assert_that!(error).is_equal_to("<3>sensor_bridge: Could not connect".to_string());
+    assert_that!(info).is_equal_to("<6>sensor_bridge: Connected".to_string());
+    assert_that!(trace).is_equal_to("<7>sensor_bridge: Tick".to_string());
+}
+
+#[test]
+fn test_shutdown_stops_sync_threads() {
+    // GIVEN are running sync threads, that stop when they are unparked
+    let port_handles = Mutex::new(HashMap::from([
+        ("a".to_string(), start_thread(true)),
+        ("b".to_string(), start_thread(true)),
+    ]));
+
+    // WHEN shutting down
+    let is_stopped = headless::shutdown(&port_handles, Duration::from_secs(5));
+
+    // THEN all sync threads should be stopped
+    assert_that!(is_stopped).is_true();
+}
+
+#[test]
+fn test_shutdown_gives_up_after_timeout() {
+    // GIVEN is a sync thread, that does not stop when requested
+    let port_handles = Mutex::new(HashMap::from([("a".to_string(), start_thread(false))]));
+
+    // WHEN shutting down with a short timeout
+    let started_at = Instant::now();
+    let is_stopped = headless::shutdown(&port_handles, Duration::from_millis(200));
+
+    // THEN the shutdown should give up after the timeout
+    assert_that!(is_stopped).is_false();
+    assert_that!(started_at.elapsed() < Duration::from_secs(1)).is_true();
+}
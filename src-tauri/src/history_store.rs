@@ -0,0 +1,546 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, SecondsFormat};
+use log::{error, info, warn};
+use sensor_core::{SensorType, SensorValue};
+use serde::{Deserialize, Serialize};
+
+use crate::config::HistoryStoreConfig;
+use crate::sensor_history;
+use crate::sensor_history::HistorySnapshot;
+use crate::sensor_sampler::{SensorSampler, SENSOR_VALUE_HISTORY_RETENTION};
+use crate::utils;
+
+/// Time span of a single segment file in milliseconds
+const SEGMENT_DURATION_MS: u64 = 60 * 60 * 1000;
+
+/// Age after which segments are downsampled, newer segments keep every sample
+const RAW_RETENTION: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Interval in milliseconds to which older segments are downsampled
+const DOWNSAMPLE_INTERVAL_MS: u64 = 60 * 1000;
+
+/// File extension of segments with every sample
+const RAW_SEGMENT_EXTENSION: &str = "raw";
+
+/// File extension of downsampled segments
+const DOWNSAMPLED_SEGMENT_EXTENSION: &str = "min";
+
+/// File extension of downsampled segments while they are written, they are renamed once complete
+const TEMPORARY_SEGMENT_EXTENSION: &str = "tmp";
+
+/// Upper bound of the length of a record, a longer length prefix means the segment is corrupted
+const MAX_RECORD_LENGTH: usize = 16 * 1024 * 1024;
+
+/// Header of the timestamp column of csv exports
+const CSV_TIMESTAMP_HEADER: &str = "timestamp";
+
+/// Timeout after which the persisting thread checks for a new sample again
+const SAMPLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A segment file of the history store, covering one hour
+struct Segment {
+    /// Timestamp of the beginning of the covered hour, in milliseconds since the unix epoch
+    start: u64,
+    downsampled: bool,
+    path: PathBuf,
+}
+
+/// A record of a segment file
+#[derive(Serialize, Deserialize)]
+enum Record {
+    /// Metadata of a sensor, written once per segment before its first value and whenever it changes
+    Sensor {
+        id: String,
+        unit: String,
+        label: String,
+    },
+    /// The numeric values of a sample, by sensor id
+    Sample {
+        timestamp: u64,
+        values: Vec<(String, f64)>,
+    },
+}
+
+/// Writes snapshots to a segment file, with the metadata of each sensor written only once
+struct SegmentWriter {
+    writer: BufWriter<File>,
+    /// Unit and label of the sensors, whose metadata was written to the segment
+    sensors: HashMap<String, (String, String)>,
+}
+
+/// Append-only on-disk store of the sampled sensor values.
+/// Samples are written to one segment file per hour, each record is a length prefixed bincode record.
+/// Only numeric sensors are stored, their unit and label once per segment and their values per sample.
+/// Segments older than the raw retention are downsampled, the oldest segments are removed above the size cap.
+pub struct HistoryStore {
+    directory: PathBuf,
+    max_size: u64,
+    /// The segment samples are currently appended to, with the start of its hour
+    current_segment: Option<(u64, SegmentWriter)>,
+}
+
+/// Restores the stored sensor value history and persists all new samples in a new thread, if enabled.
+/// Must be called before the sampler starts, so that the graphs resume with the stored values.
+pub fn start(sensor_sampler: &Arc<SensorSampler>, history_store_config: &HistoryStoreConfig) {
+    if !history_store_config.enabled {
+        return;
+    }
+
+    let directory = get_history_dir();
+    let mut history_store = match HistoryStore::open(&directory, history_store_config.max_size()) {
+        Ok(history_store) => history_store,
+        Err(err) => {
+            error!(
+                "Could not open history store {}: {err}",
+                directory.display()
+            );
+            return;
+        }
+    };
+
+    let now = sensor_history::to_timestamp(SystemTime::now());
+    let retention = SENSOR_VALUE_HISTORY_RETENTION.as_millis() as u64;
+    let snapshots = history_store.read(now.saturating_sub(retention), now);
+    info!(
+        "Restored {} snapshots from the history store",
+        snapshots.len()
+    );
+    sensor_sampler.restore_history(snapshots);
+
+    let sensor_sampler = sensor_sampler.clone();
+    thread::spawn(move || loop {
+        if sensor_sampler
+            .wait_for_next_sample(SAMPLE_TIMEOUT)
+            .is_none()
+        {
+            continue;
+        }
+
+        let snapshot = sensor_sampler
+            .history()
+            .get_snapshots(Some(Duration::ZERO))
+            .into_iter()
+            .next();
+        if let Some(snapshot) = snapshot {
+            if let Err(err) = history_store.append(&snapshot) {
+                warn!("Could not write to history store: {err}");
+            }
+        }
+    });
+}
+
+/// Returns the directory of the history store.
+/// The history is kept apart from the config dir, which is replaced on config imports.
+pub fn get_history_dir() -> PathBuf {
+    match dirs::data_local_dir() {
+        Some(data_dir) => data_dir.join("sensor-bridge").join("history"),
+        None => sensor_core::get_config_dir().join("history"),
+    }
+}
+
+impl HistoryStore {
+    /// Opens the history store in the specified directory, which is created if it does not exist.
+    pub fn open(directory: &Path, max_size: u64) -> io::Result<HistoryStore> {
+        fs::create_dir_all(directory)?;
+
+        Ok(HistoryStore {
+            directory: directory.to_path_buf(),
+            max_size,
+            current_segment: None,
+        })
+    }
+
+    /// Appends the snapshot to the segment of its hour.
+    /// Starting a new segment downsamples old segments and enforces the size cap.
+    pub fn append(&mut self, snapshot: &HistorySnapshot) -> io::Result<()> {
+        let segment_start = snapshot.timestamp - snapshot.timestamp % SEGMENT_DURATION_MS;
+
+        let is_current_segment = self
+            .current_segment
+            .as_ref()
+            .is_some_and(|(start, _)| *start == segment_start);
+        if !is_current_segment {
+            self.current_segment = None;
+            let path = self.get_segment_path(segment_start, false);
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            self.current_segment = Some((segment_start, SegmentWriter::new(file)));
+            self.compact(snapshot.timestamp)?;
+        }
+
+        let (_, segment_writer) = self.current_segment.as_mut().unwrap();
+        segment_writer.write(snapshot)?;
+        segment_writer.flush()
+    }
+
+    /// Returns all stored snapshots between the timestamps, oldest snapshot first.
+    /// Unreadable segments are skipped.
+    pub fn read(&self, from: u64, to: u64) -> Vec<HistorySnapshot> {
+        let mut snapshots: Vec<HistorySnapshot> = self
+            .list_segments()
+            .iter()
+            .filter(|segment| segment.start <= to && segment.start + SEGMENT_DURATION_MS > from)
+            .flat_map(|segment| read_segment(&segment.path))
+            .filter(|snapshot| snapshot.timestamp >= from && snapshot.timestamp <= to)
+            .collect();
+        snapshots.sort_by_key(|snapshot| snapshot.timestamp);
+
+        snapshots
+    }
+
+    /// Downsamples all raw segments older than the raw retention.
+    /// Removes the oldest segments, until all segments fit into the size cap.
+    /// The current segment is never touched.
+    fn compact(&self, now: u64) -> io::Result<()> {
+        let current_segment_start = self.current_segment.as_ref().map(|(start, _)| *start);
+        let raw_retention_start = now.saturating_sub(RAW_RETENTION.as_millis() as u64);
+
+        // A raw segment is left next to its downsampled segment, if the removal was interrupted
+        for segment in self.list_segment_files() {
+            if !segment.downsampled && self.get_segment_path(segment.start, true).exists() {
+                fs::remove_file(&segment.path)?;
+            }
+        }
+
+        for segment in self.list_segments() {
+            if segment.downsampled
+                || Some(segment.start) == current_segment_start
+                || segment.start + SEGMENT_DURATION_MS > raw_retention_start
+            {
+                continue;
+            }
+
+            let snapshots = downsample(&read_segment(&segment.path), DOWNSAMPLE_INTERVAL_MS);
+            let downsampled_path = self.get_segment_path(segment.start, true);
+            let temporary_path = downsampled_path.with_extension(format!(
+                "{DOWNSAMPLED_SEGMENT_EXTENSION}.{TEMPORARY_SEGMENT_EXTENSION}"
+            ));
+            let mut segment_writer = SegmentWriter::new(File::create(&temporary_path)?);
+            for snapshot in &snapshots {
+                segment_writer.write(snapshot)?;
+            }
+            segment_writer.flush()?;
+            drop(segment_writer);
+            fs::rename(&temporary_path, &downsampled_path)?;
+            fs::remove_file(&segment.path)?;
+        }
+
+        let mut segments: Vec<(Segment, u64)> = self
+            .list_segments()
+            .into_iter()
+            .map(|segment| {
+                let size = fs::metadata(&segment.path).map_or(0, |metadata| metadata.len());
+                (segment, size)
+            })
+            .collect();
+        let mut total_size: u64 = segments.iter().map(|(_, size)| size).sum();
+
+        segments.retain(|(segment, _)| Some(segment.start) != current_segment_start);
+        for (segment, size) in segments {
+            if total_size <= self.max_size {
+                break;
+            }
+            fs::remove_file(&segment.path)?;
+            total_size -= size;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the segments of the store, oldest segment first.
+    /// A raw segment is skipped if its hour was already downsampled, so that no sample is read twice.
+    fn list_segments(&self) -> Vec<Segment> {
+        let segments = self.list_segment_files();
+        let downsampled_starts: Vec<u64> = segments
+            .iter()
+            .filter(|segment| segment.downsampled)
+            .map(|segment| segment.start)
+            .collect();
+
+        segments
+            .into_iter()
+            .filter(|segment| segment.downsampled || !downsampled_starts.contains(&segment.start))
+            .collect()
+    }
+
+    /// Returns all segment files of the store, oldest segment first
+    fn list_segment_files(&self) -> Vec<Segment> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(_) => return vec![],
+        };
+
+        let mut segments: Vec<Segment> = entries
+            .flat_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                let start = path.file_stem()?.to_str()?.parse().ok()?;
+                let downsampled = match path.extension()?.to_str()? {
+                    RAW_SEGMENT_EXTENSION => false,
+                    DOWNSAMPLED_SEGMENT_EXTENSION => true,
+                    _ => return None,
+                };
+                Some(Segment {
+                    start,
+                    downsampled,
+                    path,
+                })
+            })
+            .collect();
+        segments.sort_by_key(|segment| (segment.start, !segment.downsampled));
+
+        segments
+    }
+
+    /// Returns the path of the segment file of the hour
+    fn get_segment_path(&self, segment_start: u64, downsampled: bool) -> PathBuf {
+        let extension = if downsampled {
+            DOWNSAMPLED_SEGMENT_EXTENSION
+        } else {
+            RAW_SEGMENT_EXTENSION
+        };
+        self.directory.join(format!("{segment_start}.{extension}"))
+    }
+}
+
+impl SegmentWriter {
+    fn new(file: File) -> SegmentWriter {
+        SegmentWriter {
+            writer: BufWriter::new(file),
+            sensors: HashMap::new(),
+        }
+    }
+
+    /// Writes the numeric values of the snapshot as sample record.
+    /// The metadata of sensors, that are new to the segment or changed, is written before.
+    /// Text sensors are skipped, e.g. the static strings of dmidecode.
+    fn write(&mut self, snapshot: &HistorySnapshot) -> io::Result<()> {
+        let mut values = vec![];
+        for sensor_value in &snapshot.sensor_values {
+            if !matches!(sensor_value.sensor_type, SensorType::Number) {
+                continue;
+            }
+            let value: f64 = match sensor_value.value.trim().parse() {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+
+            let metadata = (sensor_value.unit.clone(), sensor_value.label.clone());
+            if self.sensors.get(&sensor_value.id) != Some(&metadata) {
+                write_record(
+                    &mut self.writer,
+                    &Record::Sensor {
+                        id: sensor_value.id.clone(),
+                        unit: metadata.0.clone(),
+                        label: metadata.1.clone(),
+                    },
+                )?;
+                self.sensors.insert(sensor_value.id.clone(), metadata);
+            }
+            values.push((sensor_value.id.clone(), value));
+        }
+
+        write_record(
+            &mut self.writer,
+            &Record::Sample {
+                timestamp: snapshot.timestamp,
+                values,
+            },
+        )
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Writes the record as bincode, prefixed with its length
+fn write_record(writer: &mut impl Write, record: &Record) -> io::Result<()> {
+    let data = bincode::serialize(record).map_err(io::Error::other)?;
+    if data.len() > MAX_RECORD_LENGTH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("record of {} bytes exceeds the maximum length", data.len()),
+        ));
+    }
+    writer.write_all(&(data.len() as u32).to_le_bytes())?;
+    writer.write_all(&data)
+}
+
+/// Reads all samples of the segment file as snapshots, with the metadata of the sensor records.
+/// A truncated record at the end, e.g. after a power loss, or a record above the maximum length ends the segment.
+fn read_segment(path: &Path) -> Vec<HistorySnapshot> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) => {
+            warn!("Could not read history segment {}: {err}", path.display());
+            return vec![];
+        }
+    };
+
+    let mut reader = BufReader::new(file);
+    let mut sensors: HashMap<String, (String, String)> = HashMap::new();
+    let mut snapshots = vec![];
+    loop {
+        let mut length = [0u8; 4];
+        if reader.read_exact(&mut length).is_err() {
+            break;
+        }
+        let length = u32::from_le_bytes(length) as usize;
+        if length > MAX_RECORD_LENGTH {
+            warn!(
+                "Skipping the rest of history segment {}, record of {length} bytes exceeds the maximum length",
+                path.display()
+            );
+            break;
+        }
+        let mut data = vec![0u8; length];
+        if reader.read_exact(&mut data).is_err() {
+            break;
+        }
+        match bincode::deserialize(&data) {
+            Ok(Record::Sensor { id, unit, label }) => {
+                sensors.insert(id, (unit, label));
+            }
+            Ok(Record::Sample { timestamp, values }) => snapshots.push(HistorySnapshot {
+                timestamp,
+                sensor_values: values
+                    .into_iter()
+                    .filter_map(|(id, value)| {
+                        let (unit, label) = sensors.get(&id)?;
+                        Some(SensorValue {
+                            id,
+                            value: value.to_string(),
+                            unit: unit.clone(),
+                            label: label.clone(),
+                            sensor_type: SensorType::Number,
+                        })
+                    })
+                    .collect(),
+            }),
+            Err(_) => break,
+        }
+    }
+
+    snapshots
+}
+
+/// Reduces the snapshots to one snapshot per interval, oldest snapshot first.
+/// Numeric values are averaged, text values keep the latest value of the interval.
+pub fn downsample(snapshots: &[HistorySnapshot], interval_ms: u64) -> Vec<HistorySnapshot> {
+    // Values of each sensor grouped by interval, sensors are sorted like in the snapshots
+    let mut intervals: BTreeMap<u64, Vec<(SensorValue, Vec<String>)>> = BTreeMap::new();
+    for snapshot in snapshots {
+        let interval_start = snapshot.timestamp - snapshot.timestamp % interval_ms;
+        let interval = intervals.entry(interval_start).or_default();
+        for sensor_value in &snapshot.sensor_values {
+            match interval
+                .iter_mut()
+                .find(|(known_value, _)| known_value.id == sensor_value.id)
+            {
+                Some((known_value, values)) => {
+                    *known_value = sensor_value.clone();
+                    values.push(sensor_value.value.clone());
+                }
+                None => interval.push((sensor_value.clone(), vec![sensor_value.value.clone()])),
+            }
+        }
+    }
+
+    intervals
+        .into_iter()
+        .map(|(timestamp, sensor_values)| HistorySnapshot {
+            timestamp,
+            sensor_values: sensor_values
+                .into_iter()
+                .map(|(sensor_value, values)| {
+                    let value = if matches!(sensor_value.sensor_type, SensorType::Number) {
+                        get_average(&values).unwrap_or(sensor_value.value.clone())
+                    } else {
+                        sensor_value.value.clone()
+                    };
+                    SensorValue {
+                        value,
+                        ..sensor_value
+                    }
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Returns the average of the values, or None if any value is not a number
+fn get_average(values: &[String]) -> Option<String> {
+    let numbers: Vec<f64> = values
+        .iter()
+        .map(|value| value.trim().parse::<f64>().ok())
+        .collect::<Option<Vec<f64>>>()?;
+    let average = numbers.iter().sum::<f64>() / numbers.len() as f64;
+
+    Some(utils::format_number(average))
+}
+
+/// Writes the snapshots as csv, with a column per sensor id.
+/// The timestamp column is formatted as RFC 3339, sensors missing in a snapshot are left empty.
+pub fn write_csv(writer: &mut impl Write, snapshots: &[HistorySnapshot]) -> io::Result<()> {
+    let mut sensor_ids: Vec<String> = snapshots
+        .iter()
+        .flat_map(|snapshot| snapshot.sensor_values.iter().map(|value| value.id.clone()))
+        .collect();
+    sensor_ids.sort();
+    sensor_ids.dedup();
+
+    write_csv_header(writer, &sensor_ids)?;
+    for snapshot in snapshots {
+        write_csv_row(writer, &sensor_ids, snapshot)?;
+    }
+
+    writer.flush()
+}
+
+/// Writes the csv header, with the timestamp column followed by a column per sensor id
+pub fn write_csv_header(writer: &mut impl Write, sensor_ids: &[String]) -> io::Result<()> {
+    let header: Vec<String> = std::iter::once(CSV_TIMESTAMP_HEADER.to_string())
+        .chain(sensor_ids.iter().map(|sensor_id| escape_csv(sensor_id)))
+        .collect();
+    writeln!(writer, "{}", header.join(","))
+}
+
+/// Writes the snapshot as csv row, with the columns of the header
+pub fn write_csv_row(
+    writer: &mut impl Write,
+    sensor_ids: &[String],
+    snapshot: &HistorySnapshot,
+) -> io::Result<()> {
+    let timestamp = DateTime::from_timestamp_millis(snapshot.timestamp as i64)
+        .map(|timestamp| timestamp.to_rfc3339_opts(SecondsFormat::Millis, true))
+        .unwrap_or_default();
+    let row: Vec<String> = std::iter::once(timestamp)
+        .chain(sensor_ids.iter().map(|sensor_id| {
+            snapshot
+                .sensor_values
+                .iter()
+                .find(|sensor_value| &sensor_value.id == sensor_id)
+                .map(|sensor_value| escape_csv(&sensor_value.value))
+                .unwrap_or_default()
+        }))
+        .collect();
+    writeln!(writer, "{}", row.join(","))
+}
+
+/// Quotes the csv field, if it contains a separator, quote or line break
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
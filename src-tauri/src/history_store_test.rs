@@ -0,0 +1,192 @@
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use assertor::{assert_that, EqualityAssertion};
+use sensor_core::SensorValue;
+use uuid::Uuid;
+
+use crate::history_store;
+use crate::history_store::HistoryStore;
+use crate::sensor_history::HistorySnapshot;
+use crate::test_utils::{create_sensor_value, create_text_sensor_value};
+
+fn create_snapshot(timestamp: u64, sensor_values: Vec<SensorValue>) -> HistorySnapshot {
+    HistorySnapshot {
+        timestamp,
+        sensor_values,
+    }
+}
+
+/// Returns the values of the sensor in all snapshots
+fn get_values(snapshots: &[HistorySnapshot], sensor_id: &str) -> Vec<String> {
+    snapshots
+        .iter()
+        .flat_map(|snapshot| &snapshot.sensor_values)
+        .filter(|sensor_value| sensor_value.id == sensor_id)
+        .map(|sensor_value| sensor_value.value.clone())
+        .collect()
+}
+
+#[test]
+fn test_append_and_read() {
+    // GIVEN is an empty history store
+    let directory = std::env::temp_dir().join(format!("sensor-bridge-test-{}", Uuid::new_v4()));
+    let mut history_store = HistoryStore::open(&directory, u64::MAX).unwrap();
+
+    // WHEN appending snapshots across two hours
+    let timestamps = [3_599_000, 3_600_000, 3_601_000];
+    for (index, timestamp) in timestamps.iter().enumerate() {
+        let sensor_value = create_sensor_value("cpu", &index.to_string(), "");
+        history_store
+            .append(&create_snapshot(*timestamp, vec![sensor_value]))
+            .unwrap();
+    }
+
+    // THEN the snapshots should be read back in order, limited to the requested time span
+    let all_snapshots = history_store.read(0, u64::MAX);
+    let later_snapshots = history_store.read(3_600_000, u64::MAX);
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert_that!(get_values(&all_snapshots, "cpu")).is_equal_to(vec![
+        "0".to_string(),
+        "1".to_string(),
+        "2".to_string(),
+    ]);
+    assert_that!(get_values(&later_snapshots, "cpu"))
+        .is_equal_to(vec!["1".to_string(), "2".to_string()]);
+}
+
+#[test]
+fn test_append_stores_only_numeric_sensors() {
+    // GIVEN is an empty history store
+    let directory = std::env::temp_dir().join(format!("sensor-bridge-test-{}", Uuid::new_v4()));
+    let mut history_store = HistoryStore::open(&directory, u64::MAX).unwrap();
+
+    // WHEN appending snapshots with a numeric and a text sensor, whose label changes in between
+    let mut cpu = create_sensor_value("cpu", "10.5", "");
+    cpu.unit = "%".to_string();
+    let serial = create_text_sensor_value("serial", "ABC123");
+    history_store
+        .append(&create_snapshot(1_000, vec![cpu.clone(), serial.clone()]))
+        .unwrap();
+    cpu.label = "CPU load".to_string();
+    history_store
+        .append(&create_snapshot(2_000, vec![cpu.clone(), serial]))
+        .unwrap();
+
+    // THEN only the numeric sensor should be read back, with its unit and latest label
+    let snapshots = history_store.read(0, u64::MAX);
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert_that!(snapshots.len()).is_equal_to(2);
+    assert_that!(get_values(&snapshots, "cpu"))
+        .is_equal_to(vec!["10.5".to_string(), "10.5".to_string()]);
+    assert_that!(get_values(&snapshots, "serial").len()).is_equal_to(0);
+    assert_that!(snapshots[0].sensor_values[0].unit.clone()).is_equal_to("%".to_string());
+    assert_that!(snapshots[0].sensor_values[0].label.clone()).is_equal_to("cpu".to_string());
+    assert_that!(snapshots[1].sensor_values[0].label.clone()).is_equal_to("CPU load".to_string());
+}
+
+#[test]
+fn test_read_stops_at_record_above_maximum_length() {
+    // GIVEN is a segment, whose last record has a corrupted length prefix
+    let directory = std::env::temp_dir().join(format!("sensor-bridge-test-{}", Uuid::new_v4()));
+    let mut history_store = HistoryStore::open(&directory, u64::MAX).unwrap();
+    let sensor_value = create_sensor_value("cpu", "10", "");
+    history_store
+        .append(&create_snapshot(1_000, vec![sensor_value]))
+        .unwrap();
+    let mut segment_file = OpenOptions::new()
+        .append(true)
+        .open(directory.join("0.raw"))
+        .unwrap();
+    segment_file.write_all(&u32::MAX.to_le_bytes()).unwrap();
+
+    // WHEN reading the history
+    let snapshots = history_store.read(0, u64::MAX);
+    fs::remove_dir_all(&directory).unwrap();
+
+    // THEN the snapshots before the corrupted record should be read
+    assert_that!(get_values(&snapshots, "cpu")).is_equal_to(vec!["10".to_string()]);
+}
+
+#[test]
+fn test_read_skips_raw_segment_of_downsampled_hour() {
+    // GIVEN is a raw segment, that was left next to its downsampled segment
+    let directory = std::env::temp_dir().join(format!("sensor-bridge-test-{}", Uuid::new_v4()));
+    let mut history_store = HistoryStore::open(&directory, u64::MAX).unwrap();
+    let sensor_value = create_sensor_value("cpu", "10", "");
+    history_store
+        .append(&create_snapshot(1_000, vec![sensor_value]))
+        .unwrap();
+    fs::copy(directory.join("0.raw"), directory.join("0.min")).unwrap();
+
+    // WHEN reading the history
+    let snapshots = history_store.read(0, u64::MAX);
+    fs::remove_dir_all(&directory).unwrap();
+
+    // THEN the samples of the hour should be read only once
+    assert_that!(get_values(&snapshots, "cpu")).is_equal_to(vec!["10".to_string()]);
+}
+
+#[test]
+fn test_downsample() {
+    // GIVEN are snapshots within two intervals
+    let snapshots = vec![
+        create_snapshot(
+            0,
+            vec![
+                create_sensor_value("cpu", "10", ""),
+                create_text_sensor_value("os", "Linux"),
+            ],
+        ),
+        create_snapshot(
+            30_000,
+            vec![
+                create_sensor_value("cpu", "15", ""),
+                create_text_sensor_value("os", "Linux 6"),
+            ],
+        ),
+        create_snapshot(60_000, vec![create_sensor_value("cpu", "40", "")]),
+    ];
+
+    // WHEN downsampling to one snapshot per minute
+    let downsampled = history_store::downsample(&snapshots, 60_000);
+
+    // THEN numbers should be averaged and texts should keep the latest value
+    assert_that!(downsampled.len()).is_equal_to(2);
+    assert_that!(downsampled[0].timestamp).is_equal_to(0);
+    assert_that!(downsampled[1].timestamp).is_equal_to(60_000);
+    assert_that!(get_values(&downsampled, "cpu"))
+        .is_equal_to(vec!["12.5".to_string(), "40".to_string()]);
+    assert_that!(get_values(&downsampled, "os")).is_equal_to(vec!["Linux 6".to_string()]);
+}
+
+#[test]
+fn test_write_csv() {
+    // GIVEN are snapshots, where a sensor is missing in the second snapshot
+    let snapshots = vec![
+        create_snapshot(
+            0,
+            vec![
+                create_sensor_value("cpu", "10", ""),
+                create_text_sensor_value("os", "Linux, 6"),
+            ],
+        ),
+        create_snapshot(1_500, vec![create_sensor_value("cpu", "20", "")]),
+    ];
+
+    // WHEN writing the snapshots as csv
+    let mut csv = vec![];
+    history_store::write_csv(&mut csv, &snapshots).unwrap();
+
+    // THEN there should be a column per sensor and fields with separators should be quoted
+    assert_that!(String::from_utf8(csv).unwrap()).is_equal_to(
+        "timestamp,cpu,os
+1970-01-01T00:00:00.000Z,10,\"Linux, 6\"
+1970-01-01T00:00:01.500Z,20,
+"
+        .to_string(),
+    );
+}
@@ -0,0 +1,340 @@
+use std::io::Cursor;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use log::{debug, error, info};
+use sensor_core::SensorValue;
+use serde::Serialize;
+use tiny_http::{Header, Request, Response, Server, StatusCode};
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+use uuid::Uuid;
+
+use crate::config::HttpServerConfig;
+use crate::metrics;
+use crate::sensor_sampler::{SensorDemand, SensorSampler};
+
+/// Consumer id of the http api at the sensor sampler
+const CONSUMER_ID: &str = "http_server";
+
+/// Consumer id of the metrics endpoint at the sensor sampler
+const METRICS_CONSUMER_ID: &str = "metrics";
+
+/// Duration for which all sensors are sampled, after the last request to the api.
+/// WebSocket clients require all sensors for as long as they are connected.
+const REQUEST_LEASE: Duration = Duration::from_secs(10);
+
+/// Duration for which all sensors are sampled, after the last scrape of the metrics endpoint.
+/// This covers common scrape intervals, so that each scrape finds up-to-date values.
+const SCRAPE_LEASE: Duration = Duration::from_secs(120);
+
+/// Time the first scrape of the metrics endpoint waits for a sample of all sensors
+const FIRST_SCRAPE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Interval in which an idle WebSocket stream is pinged, to detect disconnected clients
+const STREAM_PING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Starts the local http api in a new thread, if it is enabled.
+///
+/// Endpoints:
+/// - `GET /api/sensors`: The latest sampled sensor values
+/// - `GET /api/sensors/{id}`: The latest value of a single sensor
+/// - `GET /api/sensors/{id}/history?seconds={s}&limit={n}`: The timestamped values of a single sensor, newest first
+/// - `GET /api/history?seconds={s}&limit={n}`: The timestamped snapshots of all sensors, newest first
+/// - `GET /api/stream`: WebSocket, that pushes each new snapshot
+/// - `GET /metrics`: The latest numeric sensor values in the Prometheus text format
+///
+/// Requests are only answered if their host is localhost, the bind address or an allowed host,
+/// so that web pages can not read the api by rebinding their domain name to a local address.
+/// Returns the address the server listens on, or None if it is disabled or could not be started.
+pub fn start(
+    sensor_sampler: &Arc<SensorSampler>,
+    http_server_config: &HttpServerConfig,
+) -> Option<SocketAddr> {
+    if !http_server_config.enabled {
+        return None;
+    }
+
+    let address = format!("{}:{}", http_server_config.address, http_server_config.port);
+    let server = match Server::http(&address) {
+        Ok(server) => server,
+        Err(err) => {
+            error!("Could not start http server on {address}: {err}");
+            return None;
+        }
+    };
+
+    let server_address = server.server_addr().to_ip();
+    info!("Http server listening on {address}");
+
+    let sensor_sampler = sensor_sampler.clone();
+    let http_server_config = Arc::new(http_server_config.clone());
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            handle_request(&sensor_sampler, &http_server_config, request);
+        }
+    });
+
+    server_address
+}
+
+/// Answers a single request, WebSocket streams are served in their own thread
+fn handle_request(
+    sensor_sampler: &Arc<SensorSampler>,
+    http_server_config: &Arc<HttpServerConfig>,
+    request: Request,
+) {
+    debug!("Http request {} {}", request.method(), request.url());
+
+    let host = get_header_value(&request, "Host").unwrap_or_default();
+    if !is_allowed_host(&host, http_server_config) {
+        respond_error(request, 403, "Host not allowed");
+        return;
+    }
+
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let limit = get_query_parameter(query, "limit").and_then(|limit| limit.parse().ok());
+    let window = get_query_parameter(query, "seconds")
+        .and_then(|seconds| seconds.parse().ok())
+        .map(Duration::from_secs);
+    let segments: Vec<String> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(percent_decode)
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    if request.method() != &tiny_http::Method::Get {
+        respond_error(request, 405, "Method not allowed");
+        return;
+    }
+
+    if matches!(segments.as_slice(), ["metrics"]) {
+        let is_lease_renewed = sensor_sampler.register_consumer_with_lease(
+            METRICS_CONSUMER_ID,
+            SensorDemand::All,
+            SCRAPE_LEASE,
+        );
+
+        // Without an active lease only the sensors of other consumers were sampled,
+        // thus the first scrape waits for a sample of all sensors
+        let sensor_values = if is_lease_renewed {
+            sensor_sampler.latest()
+        } else {
+            sensor_sampler
+                .wait_for_next_sample(FIRST_SCRAPE_TIMEOUT)
+                .unwrap_or_else(|| sensor_sampler.latest())
+        };
+        let response = Response::from_string(metrics::render(&sensor_values)).with_header(
+            create_header("Content-Type", "text/plain; version=0.0.4; charset=utf-8"),
+        );
+        respond(request, response);
+        return;
+    }
+
+    if matches!(segments.as_slice(), ["api", "stream"]) {
+        // Browsers do not restrict WebSockets to the same origin, thus the origin is checked here
+        let origin = get_header_value(&request, "Origin");
+        if origin.is_some_and(|origin| !is_allowed_origin(&origin, http_server_config)) {
+            respond_error(request, 403, "Origin not allowed");
+            return;
+        }
+
+        let sensor_sampler = sensor_sampler.clone();
+        thread::spawn(move || stream(&sensor_sampler, request));
+        return;
+    }
+
+    sensor_sampler.register_consumer_with_lease(CONSUMER_ID, SensorDemand::All, REQUEST_LEASE);
+
+    let config = http_server_config;
+    match segments.as_slice() {
+        ["api", "sensors"] => respond_json(request, config, &sensor_sampler.latest()),
+        ["api", "sensors", sensor_id] => match find_sensor_value(sensor_sampler, sensor_id) {
+            Some(sensor_value) => respond_json(request, config, &sensor_value),
+            None => respond_error(request, 404, "Sensor not found"),
+        },
+        ["api", "sensors", sensor_id, "history"] => {
+            let mut history_entries = sensor_sampler.history().get_entries(sensor_id, window);
+            history_entries.truncate(limit.unwrap_or(usize::MAX));
+            respond_json(request, config, &history_entries)
+        }
+        ["api", "history"] => {
+            let mut snapshots = sensor_sampler.history().get_snapshots(window);
+            snapshots.truncate(limit.unwrap_or(usize::MAX));
+            respond_json(request, config, &snapshots)
+        }
+        _ => respond_error(request, 404, "Not found"),
+    }
+}
+
+/// Upgrades the request to a WebSocket and pushes each new snapshot until the client disconnects
+fn stream(sensor_sampler: &Arc<SensorSampler>, request: Request) {
+    let accept_key = match request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Sec-WebSocket-Key"))
+    {
+        Some(header) => tungstenite::handshake::derive_accept_key(header.value.as_bytes()),
+        None => {
+            respond_error(request, 400, "Expected a WebSocket request");
+            return;
+        }
+    };
+
+    let response = Response::new_empty(StatusCode(101))
+        .with_header(create_header("Upgrade", "websocket"))
+        .with_header(create_header("Connection", "Upgrade"))
+        .with_header(create_header("Sec-WebSocket-Accept", &accept_key));
+    let stream = request.upgrade("websocket", response);
+    let mut websocket = WebSocket::from_raw_socket(stream, Role::Server, None);
+
+    // Each connection is a consumer on its own, so that all sensors are sampled while connected
+    let consumer_id = Uuid::new_v4().to_string();
+    sensor_sampler.register_consumer(&consumer_id, SensorDemand::All);
+    info!("WebSocket client connected");
+
+    loop {
+        let message = match sensor_sampler.wait_for_next_sample(STREAM_PING_INTERVAL) {
+            Some(sensor_values) => Message::text(serde_json::to_string(&sensor_values).unwrap()),
+            None => Message::Ping(Default::default()),
+        };
+
+        if let Err(err) = websocket.send(message) {
+            debug!("Could not send to WebSocket client: {err}");
+            break;
+        }
+    }
+
+    sensor_sampler.unregister_consumer(&consumer_id);
+    info!("WebSocket client disconnected");
+}
+
+/// Returns the latest value of the specified sensor
+fn find_sensor_value(sensor_sampler: &SensorSampler, sensor_id: &str) -> Option<SensorValue> {
+    sensor_sampler
+        .latest()
+        .into_iter()
+        .find(|sensor_value| sensor_value.id == sensor_id)
+}
+
+/// Responds with the value serialized as json.
+/// Web pages of the allowed origins may read the response, so that browser based overlays can use the api.
+fn respond_json<T: Serialize + ?Sized>(
+    request: Request,
+    http_server_config: &HttpServerConfig,
+    value: &T,
+) {
+    let json = serde_json::to_string(value).unwrap();
+    let mut response =
+        Response::from_string(json).with_header(create_header("Content-Type", "application/json"));
+
+    let origin = get_header_value(&request, "Origin");
+    if let Some(origin) = origin.filter(|origin| is_allowed_origin(origin, http_server_config)) {
+        response.add_header(create_header("Access-Control-Allow-Origin", &origin));
+        response.add_header(create_header("Vary", "Origin"));
+    }
+
+    respond(request, response);
+}
+
+/// Responds with the status code and a json error message
+fn respond_error(request: Request, status_code: u16, message: &str) {
+    let json = serde_json::json!({ "error": message }).to_string();
+    let response = Response::from_string(json)
+        .with_status_code(status_code)
+        .with_header(create_header("Content-Type", "application/json"));
+    respond(request, response);
+}
+
+/// Sends the response, a client that already disconnected is not an error
+fn respond(request: Request, response: Response<Cursor<Vec<u8>>>) {
+    if let Err(err) = request.respond(response) {
+        debug!("Could not send http response: {err}");
+    }
+}
+
+/// Returns the value of the specified request header
+fn get_header_value(request: &Request, field: &str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(field))
+        .map(|header| header.value.to_string())
+}
+
+/// Returns true if the host of a request, with or without port, is localhost,
+/// the bind address or one of the allowed hosts
+pub fn is_allowed_host(host: &str, http_server_config: &HttpServerConfig) -> bool {
+    let host_name = match host.strip_prefix('[') {
+        // IPv6 address, e.g. [::1]:10491
+        Some(host) => host.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+
+    // An unspecified bind address, like 0.0.0.0, is no host under which the api is reachable
+    let bind_address = Some(http_server_config.address.as_str()).filter(|address| {
+        !address
+            .parse::<IpAddr>()
+            .is_ok_and(|address| address.is_unspecified())
+    });
+
+    ["localhost", "127.0.0.1", "::1"]
+        .into_iter()
+        .chain(bind_address)
+        .chain(http_server_config.allowed_hosts.iter().map(String::as_str))
+        .any(|allowed_host| !host_name.is_empty() && host_name.eq_ignore_ascii_case(allowed_host))
+}
+
+/// Returns true if web pages of the origin may read the api
+pub fn is_allowed_origin(origin: &str, http_server_config: &HttpServerConfig) -> bool {
+    http_server_config
+        .allowed_origins
+        .iter()
+        .any(|allowed_origin| allowed_origin == "*" || allowed_origin == origin)
+}
+
+/// Creates a http header, the field and value must be valid ascii
+fn create_header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).unwrap()
+}
+
+/// Returns the value of the specified parameter of the url query
+pub fn get_query_parameter(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|parameter| parameter.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| percent_decode(value))
+}
+
+/// Decodes a percent encoded url component, invalid escape sequences are kept as they are
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
@@ -0,0 +1,283 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+use assertor::{assert_that, BooleanAssertion, EqualityAssertion};
+use sensor_core::{SensorType, SensorValue};
+use serde_json::Value;
+
+use crate::config::HttpServerConfig;
+use crate::http_server;
+use crate::sensor::{SensorProvider, SensorProviderRegistry};
+use crate::sensor_history::HistorySnapshot;
+use crate::sensor_sampler::{SensorDemand, SensorSampler};
+
+/// Timestamp of the oldest snapshot in the history of the test server
+const HISTORY_START: u64 = 1_700_000_000_000;
+
+/// A provider with the single sensor `cpu_load`
+struct TestProvider;
+
+impl SensorProvider for TestProvider {
+    fn get_name(&self) -> String {
+        "test".to_string()
+    }
+
+    fn get_preferred_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn read(&self) -> Vec<SensorValue> {
+        vec![create_sensor_value("40")]
+    }
+}
+
+fn create_sensor_value(value: &str) -> SensorValue {
+    SensorValue {
+        id: "cpu_load".to_string(),
+        value: value.to_string(),
+        unit: "%".to_string(),
+        label: "CPU load".to_string(),
+        sensor_type: SensorType::Number,
+    }
+}
+
+fn create_config(allowed_origins: &[&str], allowed_hosts: &[&str]) -> HttpServerConfig {
+    HttpServerConfig {
+        enabled: true,
+        address: "127.0.0.1".to_string(),
+        port: 0,
+        allowed_origins: allowed_origins
+            .iter()
+            .map(|origin| origin.to_string())
+            .collect(),
+        allowed_hosts: allowed_hosts.iter().map(|host| host.to_string()).collect(),
+    }
+}
+
+/// Starts a server on an ephemeral port, whose history contains 4 snapshots, one second apart
+fn start_server(http_server_config: &HttpServerConfig) -> SocketAddr {
+    let registry = SensorProviderRegistry::with_providers(vec![Arc::new(TestProvider)], &[]);
+    let sensor_sampler = Arc::new(SensorSampler::new(registry));
+    sensor_sampler.register_consumer("test", SensorDemand::All);
+    sensor_sampler.discover();
+    sensor_sampler.restore_history(
+        (0..4)
+            .map(|index| HistorySnapshot {
+                timestamp: HISTORY_START + index * 1000,
+                sensor_values: vec![create_sensor_value(&(index * 10).to_string())],
+            })
+            .collect(),
+    );
+
+    http_server::start(&sensor_sampler, http_server_config).unwrap()
+}
+
+/// A response of the test server
+struct TestResponse {
+    status_code: u16,
+    headers: String,
+    body: String,
+}
+
+impl TestResponse {
+    fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+
+    fn header(&self, field: &str) -> Option<String> {
+        self.headers.lines().find_map(|line| {
+            line.split_once(": ")
+                .filter(|(name, _)| name.eq_ignore_ascii_case(field))
+                .map(|(_, value)| value.to_string())
+        })
+    }
+}
+
+/// Sends a get request with the host and additional header lines to the server
+fn get(address: SocketAddr, path: &str, host: &str, extra_headers: &str) -> TestResponse {
+    let mut stream = TcpStream::connect(address).unwrap();
+    let request =
+        format!("GET {path} HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\n{extra_headers}\r\n");
+    stream.write_all(request.as_bytes()).unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (headers, body) = response.split_once("\r\n\r\n").unwrap();
+    let status_code = headers.split(' ').nth(1).unwrap().parse().unwrap();
+
+    TestResponse {
+        status_code,
+        headers: headers.to_string(),
+        body: body.to_string(),
+    }
+}
+
+#[test]
+fn test_percent_decode() {
+    // GIVEN are encoded url components
+    let plain = "cpu_load_total";
+    let encoded = "Tctl%20%2F%20Tdie";
+    let invalid = "100%_%zz";
+
+    // WHEN decoding the components
+    let plain = http_server::percent_decode(plain);
+    let encoded = http_server::percent_decode(encoded);
+    let invalid = http_server::percent_decode(invalid);
+
+    // THEN escape sequences should be decoded and invalid ones kept
+    assert_that!(plain).is_equal_to("cpu_load_total".to_string());
+    assert_that!(encoded).is_equal_to("Tctl / Tdie".to_string());
+    assert_that!(invalid).is_equal_to("100%_%zz".to_string());
+}
+
+#[test]
+fn test_get_query_parameter() {
+    // GIVEN is an url query with multiple parameters
+    let query = "format=json&limit=60";
+
+    // WHEN reading the parameters
+    let limit = http_server::get_query_parameter(query, "limit");
+    let missing = http_server::get_query_parameter(query, "offset");
+
+    // THEN only existing parameters should be returned
+    assert_that!(limit).is_equal_to(Some("60".to_string()));
+    assert_that!(missing).is_equal_to(None);
+}
+
+#[test]
+fn test_is_allowed_host() {
+    // GIVEN is a server bound to all interfaces, that allows an additional host name
+    let http_server_config = HttpServerConfig {
+        address: "0.0.0.0".to_string(),
+        ..create_config(&[], &["desktop.local"])
+    };
+
+    // WHEN checking hosts with and without port
+    let allowed = [
+        "localhost:10491",
+        "127.0.0.1",
+        "[::1]:10491",
+        "Desktop.local:10491",
+    ]
+    .map(|host| http_server::is_allowed_host(host, &http_server_config));
+    let rejected = ["0.0.0.0:10491", "attacker.example:10491", ""]
+        .map(|host| http_server::is_allowed_host(host, &http_server_config));
+
+    // THEN only localhost and the allowed host should be accepted, but not the unspecified bind address
+    assert_that!(allowed).is_equal_to([true; 4]);
+    assert_that!(rejected).is_equal_to([false; 3]);
+}
+
+#[test]
+fn test_is_allowed_origin() {
+    // GIVEN are configs with an allowed origin, any allowed origin, and no allowed origin
+    let single_origin = create_config(&["http://localhost:8080"], &[]);
+    let any_origin = create_config(&["*"], &[]);
+    let no_origin = create_config(&[], &[]);
+
+    // WHEN checking an origin
+    let origin = "http://localhost:8080";
+
+    // THEN the origin should only be allowed if configured
+    assert_that!(http_server::is_allowed_origin(origin, &single_origin)).is_true();
+    assert_that!(http_server::is_allowed_origin(
+        "http://evil.example",
+        &single_origin
+    ))
+    .is_false();
+    assert_that!(http_server::is_allowed_origin(origin, &any_origin)).is_true();
+    assert_that!(http_server::is_allowed_origin(origin, &no_origin)).is_false();
+}
+
+#[test]
+fn test_sensor_endpoints() {
+    // GIVEN is a running server
+    let address = start_server(&create_config(&[], &[]));
+
+    // WHEN requesting all sensors, a single sensor and an unknown sensor
+    let snapshot = get(address, "/api/sensors", "localhost", "");
+    let single_sensor = get(address, "/api/sensors/cpu_load", "localhost", "");
+    let unknown_sensor = get(address, "/api/sensors/unknown", "localhost", "");
+    let unknown_path = get(address, "/api/unknown", "localhost", "");
+
+    // THEN the latest values should be returned, and 404 for unknown sensors and paths
+    assert_that!(snapshot.status_code).is_equal_to(200);
+    assert_that!(snapshot.json()[0]["id"].clone()).is_equal_to(Value::from("cpu_load"));
+    assert_that!(snapshot.json()[0]["value"].clone()).is_equal_to(Value::from("40"));
+    assert_that!(single_sensor.status_code).is_equal_to(200);
+    assert_that!(single_sensor.json()["label"].clone()).is_equal_to(Value::from("CPU load"));
+    assert_that!(unknown_sensor.status_code).is_equal_to(404);
+    assert_that!(unknown_path.status_code).is_equal_to(404);
+}
+
+#[test]
+fn test_history_endpoints() {
+    // GIVEN is a running server with 4 snapshots in its history
+    let address = start_server(&create_config(&[], &[]));
+
+    // WHEN requesting the history of a sensor within a window and with a limit, and all snapshots with a limit
+    let window = get(
+        address,
+        "/api/sensors/cpu_load/history?seconds=2",
+        "localhost",
+        "",
+    );
+    let limit = get(
+        address,
+        "/api/sensors/cpu_load/history?limit=1",
+        "localhost",
+        "",
+    );
+    let snapshots = get(address, "/api/history?seconds=1&limit=5", "localhost", "");
+
+    // THEN only the values within the window and up to the limit should be returned, newest first
+    let values = |response: &TestResponse| -> Vec<Value> {
+        response
+            .json()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["value"].clone())
+            .collect()
+    };
+    assert_that!(values(&window)).is_equal_to(vec![
+        Value::from("30"),
+        Value::from("20"),
+        Value::from("10"),
+    ]);
+    assert_that!(values(&limit)).is_equal_to(vec![Value::from("30")]);
+    assert_that!(snapshots.json().as_array().unwrap().len()).is_equal_to(2);
+    assert_that!(snapshots.json()[0]["timestamp"].clone())
+        .is_equal_to(Value::from(HISTORY_START + 3000));
+}
+
+#[test]
+fn test_host_and_origin_checks() {
+    // GIVEN is a running server that allows a single origin
+    let address = start_server(&create_config(&["http://localhost:8080"], &[]));
+
+    // WHEN requesting with a foreign host, with an allowed origin, and with a foreign origin
+    let foreign_host = get(address, "/api/sensors", "attacker.example", "");
+    let allowed_origin = get(
+        address,
+        "/api/sensors",
+        "localhost",
+        "Origin: http://localhost:8080\r\n",
+    );
+    let foreign_origin = get(
+        address,
+        "/api/sensors",
+        "localhost",
+        "Origin: http://attacker.example\r\n",
+    );
+
+    // THEN foreign hosts should be rejected, and only allowed origins may read the response
+    assert_that!(foreign_host.status_code).is_equal_to(403);
+    assert_that!(allowed_origin.status_code).is_equal_to(200);
+    assert_that!(allowed_origin.header("Access-Control-Allow-Origin"))
+        .is_equal_to(Some("http://localhost:8080".to_string()));
+    assert_that!(foreign_origin.status_code).is_equal_to(200);
+    assert_that!(foreign_origin.header("Access-Control-Allow-Origin")).is_equal_to(None);
+}
@@ -1,25 +1,35 @@
 use std::collections::HashMap;
-use std::ops::Deref;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
 
+use image::{ImageBuffer, Rgba};
 use log::info;
 use rayon::prelude::*;
 use sensor_core::{DisplayConfig, ElementConfig, ElementType, SensorValue};
 use tauri::{AppHandle, Manager};
 
 use crate::config::NetworkDeviceConfig;
-use crate::utils::LockResultExt;
+use crate::sensor_sampler::{SensorDemand, SensorSampler};
 use crate::{conditional_image, sensor, static_image, text, utils};
 
 /// Constant for the window label
 pub const WINDOW_LABEL: &str = "lcd_preview";
 
+/// Duration for which the previewed sensors are sampled, after the last preview image was requested
+const PREVIEW_LEASE: Duration = Duration::from_secs(10);
+
 /// Shows the display preview window
 /// This function is called from the main thread
 /// Therefore we need to spawn a new thread to show the window
 /// Otherwise the window will not be shown
-pub fn show(app_handle: AppHandle, port_config: NetworkDeviceConfig) {
+pub fn show(
+    app_handle: AppHandle,
+    sensor_sampler: &SensorSampler,
+    port_config: NetworkDeviceConfig,
+) {
+    require_sensors(sensor_sampler, &port_config.display_config);
+
     let network_device_id = port_config.id.clone();
     let width = port_config.display_config.resolution_width;
     let height = port_config.display_config.resolution_height;
@@ -74,8 +84,8 @@ pub fn show(app_handle: AppHandle, port_config: NetworkDeviceConfig) {
     });
 }
 
-/// Prepares the static assets for the lcd preview window
-fn prepare_assets(elements: Vec<ElementConfig>) {
+/// Prepares the static and conditional images of the display elements for rendering
+pub fn prepare_assets(elements: Vec<ElementConfig>) {
     elements
         .par_iter()
         .filter(|element| element.element_type == ElementType::StaticImage)
@@ -98,27 +108,25 @@ fn prepare_assets(elements: Vec<ElementConfig>) {
 /// This function is called from the main thread
 /// Therefore we need to spawn a new thread to render the image
 pub fn render(
-    sensor_value_history: &Arc<Mutex<Vec<Vec<SensorValue>>>>,
-    static_sensor_values: &Arc<Vec<SensorValue>>,
-    lcd_config: DisplayConfig,
+    sensor_sampler: &Arc<SensorSampler>,
+    network_device_config: NetworkDeviceConfig,
 ) -> std::thread::Result<String> {
-    let static_sensor_values = static_sensor_values.clone();
-    let sensor_value_history = sensor_value_history.clone();
-    let lcd_config = lcd_config.clone();
-
-    thread::spawn(move || {
-        // Read the sensor values
-        sensor::read_all_sensor_values(&sensor_value_history, &static_sensor_values);
+    require_sensors(sensor_sampler, &network_device_config.display_config);
 
-        // Build font data hashmap
-        let fonts_data: HashMap<String, Vec<u8>> = text::build_fonts_data(&lcd_config);
+    let sensor_sampler = sensor_sampler.clone();
+    let sensor_windows = get_sensor_windows(&network_device_config);
+    let auto_scale_units = network_device_config.auto_scale_units;
+    let mut lcd_config = network_device_config.display_config;
 
-        // Render the image
-        let image = sensor_core::render_lcd_image(
-            lcd_config,
-            sensor_value_history.lock().ignore_poison().deref(),
-            &fonts_data,
-        );
+    thread::spawn(move || {
+        // Render the image from the sampled values of the referenced sensors, within their time windows
+        let mut sensor_value_history = sensor_sampler
+            .history()
+            .get_sensor_values_within(&sensor_windows);
+        if auto_scale_units {
+            text::auto_scale_units(&mut lcd_config, &mut sensor_value_history);
+        }
+        let image = render_image(lcd_config, &sensor_value_history);
 
         let buf = utils::rgb_to_jpeg_bytes(image);
 
@@ -130,3 +138,67 @@ pub fn render(
     })
     .join()
 }
+
+/// Renders the display config with the specified sensor value history, newest snapshot first.
+/// The assets of the display elements must have been prepared before.
+pub fn render_image(
+    lcd_config: DisplayConfig,
+    sensor_value_history: &[Vec<SensorValue>],
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    // Build font data hashmap
+    let fonts_data: HashMap<String, Vec<u8>> = text::build_fonts_data(&lcd_config);
+
+    sensor_core::render_lcd_image(lcd_config, sensor_value_history, &fonts_data)
+}
+
+/// Returns the time window of the history each referenced sensor needs, None for the complete history.
+/// Graphs show their time window, text elements calculate the min, max and average over the complete history,
+/// and conditional images only show the latest value.
+/// A sensor referenced by multiple elements gets the largest time window.
+pub fn get_sensor_windows(
+    network_device_config: &NetworkDeviceConfig,
+) -> HashMap<String, Option<Duration>> {
+    let mut sensor_windows: HashMap<String, Option<Duration>> = HashMap::new();
+
+    for element in network_device_config.display_config.elements.iter() {
+        let (sensor_id, window) = match element.element_type {
+            ElementType::Text => match &element.text_config {
+                Some(text_config) => (&text_config.sensor_id, None),
+                None => continue,
+            },
+            ElementType::Graph => match &element.graph_config {
+                Some(graph_config) => (
+                    &graph_config.sensor_id,
+                    network_device_config.get_graph_window(&element.id),
+                ),
+                None => continue,
+            },
+            ElementType::ConditionalImage => match &element.conditional_image_config {
+                Some(conditional_image_config) => {
+                    (&conditional_image_config.sensor_id, Some(Duration::ZERO))
+                }
+                None => continue,
+            },
+            ElementType::StaticImage => continue,
+        };
+
+        sensor_windows
+            .entry(sensor_id.clone())
+            .and_modify(|sensor_window| {
+                *sensor_window = (*sensor_window).zip(window).map(|(a, b)| a.max(b))
+            })
+            .or_insert(window);
+    }
+
+    sensor_windows
+}
+
+/// Requires the sensors referenced by the previewed display config from the sampler.
+/// The preview polls its image, so the requirement is renewed with every rendered image.
+fn require_sensors(sensor_sampler: &SensorSampler, display_config: &DisplayConfig) {
+    sensor_sampler.register_consumer_with_lease(
+        WINDOW_LABEL,
+        SensorDemand::Only(sensor::get_referenced_sensor_ids(display_config)),
+        PREVIEW_LEASE,
+    );
+}
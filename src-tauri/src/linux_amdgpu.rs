@@ -1,14 +1,49 @@
 use lazy_static::lazy_static;
+use log::info;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use crate::utils;
+use crate::linux_drm::{CardNames, DrmCard, HwmonChannelType};
+use crate::{config, linux_drm, sensor, units};
 use sensor_core::{SensorType, SensorValue};
 
+/// Reads the sensors of all cards driven by amdgpu, from the card directory and its hwmon directory.
+/// Cards are identified by their pci slot, as the card numbers can swap between boots.
+/// The binary `gpu_metrics` table is not read, its layout differs between revisions
+/// and its values are covered by the hwmon directory.
+pub struct AmdGpuSensors {
+    drm_path: PathBuf,
+    card_names: CardNames,
+}
+
+impl Default for AmdGpuSensors {
+    fn default() -> AmdGpuSensors {
+        AmdGpuSensors::new(Path::new(linux_drm::DRM_PATH))
+    }
+}
+
+impl sensor::SensorProvider for AmdGpuSensors {
+    fn get_name(&self) -> String {
+        "AmdGpu".to_string()
+    }
+
+    fn get_preferred_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn read(&self) -> Vec<SensorValue> {
+        self.read_all_sensors()
+    }
+}
+
 /// Represents a sensor of the amdgpu driver
 struct AmdGpuSensor {
     label: String,
     file_name: String,
     unit: String,
+    /// Factor to convert the value of the file to the base unit
+    factor: f64,
     multi_line_output: bool,
 }
 
@@ -18,66 +53,122 @@ lazy_static! {
         AmdGpuSensor {
             label: "GPU utilization".to_string(),
             file_name: "gpu_busy_percent".to_string(),
-            unit: "%".to_string(),
+            unit: units::PERCENT.to_string(),
+            factor: 1.0,
+            multi_line_output: false
+        },
+        AmdGpuSensor {
+            label: "VRAM utilization".to_string(),
+            file_name: "mem_busy_percent".to_string(),
+            unit: units::PERCENT.to_string(),
+            factor: 1.0,
             multi_line_output: false
         },
         AmdGpuSensor {
             label: "GPU frequency".to_string(),
             file_name: "pp_dpm_sclk".to_string(),
-            unit: "Mhz".to_string(),
+            unit: units::HERTZ.to_string(),
+            factor: 1e6,
             multi_line_output: true
         },
         AmdGpuSensor {
             label: "VRAM frequency".to_string(),
             file_name: "pp_dpm_mclk".to_string(),
-            unit: "Mhz".to_string(),
+            unit: units::HERTZ.to_string(),
+            factor: 1e6,
             multi_line_output: true
         },
         AmdGpuSensor {
             label: "VRAM usage".to_string(),
             file_name: "mem_info_vram_used".to_string(),
-            unit: "B".to_string(),
+            unit: units::BYTES.to_string(),
+            factor: 1.0,
             multi_line_output: false
         },
         AmdGpuSensor {
             label: "VRAM total".to_string(),
             file_name: "mem_info_vram_total".to_string(),
-            unit: "B".to_string(),
+            unit: units::BYTES.to_string(),
+            factor: 1.0,
             multi_line_output: false
         },
     ];
 }
 
-/// Returns all available sensor values for the linux gpu found
-pub fn get_sensor_values() -> Vec<SensorValue> {
-    read_all_sensors()
-}
-
-/// Returns all available gpu cards in the system
-fn get_gpu_cards() -> Vec<String> {
-    let sys_class_drm = fs::read_dir("/sys/class/drm");
+/// Types of hwmon channels, that are read from the amdgpu hwmon directory
+const HWMON_CHANNEL_TYPES: &[HwmonChannelType] = &[
+    HwmonChannelType {
+        prefix: "temp",
+        unit: units::CELSIUS,
+        factor: 0.001,
+        suffixes: &["input", "crit", "crit_hyst", "emergency"],
+    },
+    HwmonChannelType {
+        prefix: "power",
+        unit: units::WATT,
+        factor: 1e-6,
+        suffixes: &["average", "input", "cap", "cap_max"],
+    },
+    HwmonChannelType {
+        prefix: "fan",
+        unit: units::RPM,
+        factor: 1.0,
+        suffixes: &["input", "target", "min", "max"],
+    },
+    HwmonChannelType {
+        prefix: "pwm",
+        unit: units::PERCENT,
+        factor: 100.0 / 255.0,
+        suffixes: &[""],
+    },
+    HwmonChannelType {
+        prefix: "in",
+        unit: units::VOLT,
+        factor: 0.001,
+        suffixes: &["input", "min", "max"],
+    },
+    HwmonChannelType {
+        prefix: "freq",
+        unit: units::HERTZ,
+        factor: 1.0,
+        suffixes: &["input"],
+    },
+];
 
-    if sys_class_drm.is_err() {
-        return vec![];
+impl AmdGpuSensors {
+    /// Creates the provider for the drm devices in the specified directory
+    pub fn new(drm_path: &Path) -> AmdGpuSensors {
+        AmdGpuSensors {
+            drm_path: drm_path.to_path_buf(),
+            card_names: CardNames::default(),
+        }
     }
 
-    sys_class_drm
-        .unwrap()
-        .flat_map(|path| path.ok())
-        .flat_map(|path| path.file_name().into_string().ok())
-        .filter(|path_str| path_str.starts_with("card"))
-        .filter(|path_str| path_str.chars().last().unwrap().is_numeric())
-        .collect()
+    /// Reads all available sensors from all gpu cards
+    pub fn read_all_sensors(&self) -> Vec<SensorValue> {
+        linux_drm::get_drm_cards(&self.drm_path, &["amdgpu"])
+            .iter()
+            .flat_map(|card| {
+                let card_name = self.card_names.get(card);
+                let mut sensor_values = read_sensors(card, &card_name);
+                sensor_values.extend(linux_drm::read_hwmon_sensors(
+                    card,
+                    &card_name,
+                    HWMON_CHANNEL_TYPES,
+                ));
+                sensor_values
+            })
+            .collect()
+    }
 }
 
-/// Reads the specified sensor file from the specified gpu card
+/// Reads the specified sensor file from the device directory of the card
 fn read_sensor_file(
-    card_name: &str,
-    sensor_id: &str,
+    device_path: &Path,
+    file_name: &str,
     multi_line_output: bool,
 ) -> std::io::Result<String> {
-    let path = format!("/sys/class/drm/{}/device/{}", card_name, sensor_id);
-    let file_content = fs::read_to_string(path)?;
+    let file_content = fs::read_to_string(device_path.join(file_name))?;
 
     // If we got a multi line output, we need to find the active sensor value
     // This is usually the line that ends with a '*'
@@ -97,7 +188,10 @@ fn get_active_line(file_content: &str) -> std::io::Result<String> {
         .unwrap_or("");
 
     // Get only the value after the ":" (Example: "0: 500Mhz *")
-    let contents = active_line.split(':').collect::<Vec<&str>>()[1].trim();
+    let contents = active_line
+        .split_once(':')
+        .map(|(_, contents)| contents.trim())
+        .unwrap_or("");
 
     // Extract number from string (Example: " 500Mhz *" -> "500")
     let extracted_number = contents
@@ -109,46 +203,69 @@ fn get_active_line(file_content: &str) -> std::io::Result<String> {
 }
 
 /// Reads all available sensors for the specified gpu card
-fn read_sensors(card_name: &str) -> Vec<SensorValue> {
+fn read_sensors(card: &DrmCard, card_name: &str) -> Vec<SensorValue> {
     CARD_SENSORS
         .iter()
-        .flat_map(|card_sensor| get_gpu_card_sensor_values(card_name, card_sensor).ok())
+        .flat_map(|card_sensor| get_gpu_card_sensor_values(card, card_name, card_sensor).ok())
         .collect()
 }
 
 /// Returns the sensor value for the specified gpu card and sensor
 fn get_gpu_card_sensor_values(
+    card: &DrmCard,
     card_name: &str,
     card_sensor: &AmdGpuSensor,
 ) -> std::io::Result<SensorValue> {
-    let mut sensor_unit = card_sensor.unit.clone();
-
-    let mut sensor_value = read_sensor_file(
-        card_name,
+    let sensor_value = read_sensor_file(
+        &card.device_path,
         &card_sensor.file_name,
         card_sensor.multi_line_output,
     )?;
-
-    if sensor_unit.eq("B") {
-        // Pretty bytes
-        let (value, unit) = utils::pretty_bytes(sensor_value.parse::<f64>().unwrap());
-        sensor_unit = unit;
-        sensor_value = format!("{:.2}", value);
-    }
+    let sensor_value = sensor_value
+        .parse::<f64>()
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
 
     Ok(SensorValue {
-        id: format!("gpu_{}_{}", card_name, card_sensor.file_name),
-        value: sensor_value,
-        unit: sensor_unit,
-        label: format!("GPU {} {}", card_name, card_sensor.label),
+        id: format!(
+            "gpu_{}_{}",
+            linux_drm::get_card_id(card),
+            card_sensor.file_name
+        ),
+        value: format!("{:.0}", sensor_value * card_sensor.factor),
+        unit: card_sensor.unit.clone(),
+        label: format!("{} {}", card_name, card_sensor.label),
         sensor_type: SensorType::Number,
     })
 }
 
-/// Reads all available sensors from all gpu cards
-fn read_all_sensors() -> Vec<SensorValue> {
-    get_gpu_cards()
+/// Returns the sensor id prefixes of the cards, from before the cards were identified by their pci slot,
+/// with the prefixes that replace them, e.g. `gpu_card0_` and `gpu_0000_03_00_0_`.
+/// The card numbers of the current boot are used, they match the saved ids unless the cards swapped.
+pub fn get_legacy_id_prefixes(drm_path: &Path) -> Vec<(String, String)> {
+    linux_drm::get_drm_cards(drm_path, &["amdgpu"])
         .iter()
-        .flat_map(|card| read_sensors(card))
+        .filter_map(|card| {
+            let card_directory_name = card.card_path.file_name()?.to_string_lossy().to_string();
+            Some((
+                format!("gpu_{card_directory_name}_"),
+                format!("gpu_{}_", linux_drm::get_card_id(card)),
+            ))
+        })
         .collect()
 }
+
+/// Replaces the legacy sensor ids of the amdgpu cards in the config, e.g. `gpu_card0_gpu_busy_percent`,
+/// so that layouts, alert rules, overrides and derived sensors keep working.
+/// The config is only written if it references a legacy sensor id, thus the migration happens once.
+pub fn migrate_legacy_sensor_ids() {
+    let prefixes = get_legacy_id_prefixes(Path::new(linux_drm::DRM_PATH));
+    if prefixes.is_empty() {
+        return;
+    }
+
+    let mut app_config = config::read_from_app_config();
+    if app_config.replace_sensor_id_prefixes(&prefixes) {
+        info!("Migrated the amdgpu sensor ids to the pci slot of the cards");
+        config::write_to_app_config(&app_config);
+    }
+}
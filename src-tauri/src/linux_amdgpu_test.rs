@@ -0,0 +1,137 @@
+use std::fs;
+
+use assertor::{assert_that, EqualityAssertion};
+use uuid::Uuid;
+
+use crate::linux_amdgpu;
+use crate::linux_amdgpu::AmdGpuSensors;
+use crate::test_utils::write_files;
+
+#[test]
+fn test_read_all_sensors() {
+    // GIVEN is a drm directory with an amdgpu card, its connector and an intel card
+    let drm_path = std::env::temp_dir().join(format!("sensor-bridge-test-{}", Uuid::new_v4()));
+    write_files(
+        &drm_path,
+        &[
+            (
+                "card1/device/uevent",
+                "DRIVER=amdgpu\nPCI_ID=1002:73BF\nPCI_SLOT_NAME=0000:03:00.0\n",
+            ),
+            ("card1/device/product_name", "Radeon RX 6800\n"),
+            ("card1/device/gpu_busy_percent", "42\n"),
+            ("card1/device/pp_dpm_sclk", "0: 500Mhz\n1: 2100Mhz *\n"),
+            ("card1/device/hwmon/hwmon4/temp1_input", "45500\n"),
+            ("card1/device/hwmon/hwmon4/temp1_label", "edge\n"),
+            ("card1/device/hwmon/hwmon4/temp1_crit", "100000\n"),
+            ("card1/device/hwmon/hwmon4/power1_average", "35000000\n"),
+            ("card1/device/hwmon/hwmon4/pwm1", "255\n"),
+            ("card1/device/hwmon/hwmon4/pwm1_enable", "2\n"),
+            ("card1/device/hwmon/hwmon4/in0_input", "856\n"),
+            ("card1/device/hwmon/hwmon4/in0_label", "vddgfx\n"),
+            ("card1-DP-1/status", "connected\n"),
+            (
+                "card0/device/uevent",
+                "DRIVER=i915\nPCI_ID=8086:9A49\nPCI_SLOT_NAME=0000:00:02.0\n",
+            ),
+            ("card0/device/gpu_busy_percent", "1\n"),
+        ],
+    );
+
+    // WHEN reading all sensors
+    let sensor_values = AmdGpuSensors::new(&drm_path).read_all_sensors();
+    fs::remove_dir_all(&drm_path).unwrap();
+
+    // THEN only the amdgpu card should be read, identified by its pci slot and named after the product
+    let sensors: Vec<(String, String, String, String)> = sensor_values
+        .into_iter()
+        .map(|sensor_value| {
+            (
+                sensor_value.id,
+                sensor_value.value,
+                sensor_value.unit,
+                sensor_value.label,
+            )
+        })
+        .collect();
+    let expected = vec![
+        (
+            "gpu_0000_03_00_0_gpu_busy_percent",
+            "42",
+            "%",
+            "Radeon RX 6800 GPU utilization",
+        ),
+        (
+            "gpu_0000_03_00_0_pp_dpm_sclk",
+            "2100000000",
+            "Hz",
+            "Radeon RX 6800 GPU frequency",
+        ),
+        (
+            "gpu_0000_03_00_0_in0_input",
+            "0.86",
+            "V",
+            "Radeon RX 6800 vddgfx",
+        ),
+        (
+            "gpu_0000_03_00_0_power1_average",
+            "35",
+            "W",
+            "Radeon RX 6800 power1",
+        ),
+        ("gpu_0000_03_00_0_pwm1", "100", "%", "Radeon RX 6800 pwm1"),
+        (
+            "gpu_0000_03_00_0_temp1_crit",
+            "100",
+            "°C",
+            "Radeon RX 6800 edge crit",
+        ),
+        (
+            "gpu_0000_03_00_0_temp1_input",
+            "45.5",
+            "°C",
+            "Radeon RX 6800 edge",
+        ),
+    ];
+    let expected: Vec<(String, String, String, String)> = expected
+        .into_iter()
+        .map(|(id, value, unit, label)| {
+            (
+                id.to_string(),
+                value.to_string(),
+                unit.to_string(),
+                label.to_string(),
+            )
+        })
+        .collect();
+    assert_that!(sensors).is_equal_to(expected);
+}
+
+#[test]
+fn test_get_legacy_id_prefixes() {
+    // GIVEN is a drm directory with an amdgpu card and an intel card
+    let drm_path = std::env::temp_dir().join(format!("sensor-bridge-test-{}", Uuid::new_v4()));
+    write_files(
+        &drm_path,
+        &[
+            (
+                "card1/device/uevent",
+                "DRIVER=amdgpu\nPCI_ID=1002:73BF\nPCI_SLOT_NAME=0000:03:00.0\n",
+            ),
+            (
+                "card0/device/uevent",
+                "DRIVER=i915\nPCI_ID=8086:9A49\nPCI_SLOT_NAME=0000:00:02.0\n",
+            ),
+        ],
+    );
+
+    // WHEN getting the legacy sensor id prefixes
+    let prefixes = linux_amdgpu::get_legacy_id_prefixes(&drm_path);
+    fs::remove_dir_all(&drm_path).unwrap();
+
+    // THEN the card number prefix of the amdgpu card should be replaced by its pci slot prefix
+    assert_that!(prefixes).is_equal_to(vec![(
+        "gpu_card1_".to_string(),
+        "gpu_0000_03_00_0_".to_string(),
+    )]);
+}
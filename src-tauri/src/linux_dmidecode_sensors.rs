@@ -1,16 +1,21 @@
 #[cfg(target_os = "linux")]
 use std::fs;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 #[cfg(target_os = "linux")]
 use dmidecode::{BaseBoard, Bios, EntryPoint, Structure};
 #[cfg(target_os = "linux")]
-use log::info;
+use log::{info, warn};
 #[cfg(target_os = "linux")]
 use sensor_core::SensorType;
 use sensor_core::SensorValue;
 use super_shell::RootShell;
 
+use crate::sensor;
+#[cfg(target_os = "linux")]
+use crate::utils::LockResultExt;
+
 pub struct DmiDecodeSensors {
     pub root_shell: Arc<Mutex<Option<RootShell>>>,
 }
@@ -25,6 +30,9 @@ impl DmiDecodeSensors {
         }
     }
 
+    /// Reads the hardware information with dmidecode in the root shell.
+    /// Returns an empty vector if dmidecode is not available or its output can not be read,
+    /// as the provider is read on the sampler thread.
     #[cfg(target_os = "linux")]
     pub fn get_sensor_values(&self) -> Vec<SensorValue> {
         // Check if dmidecode is installed
@@ -33,22 +41,62 @@ impl DmiDecodeSensors {
             return vec![];
         }
 
-        let mut root_shell = self.root_shell.lock().unwrap();
-        let root_shell = root_shell.as_mut().unwrap();
+        let mut root_shell = self.root_shell.lock().ignore_poison();
+        let root_shell = match root_shell.as_mut() {
+            Some(root_shell) => root_shell,
+            None => {
+                info!("No root shell available to run dmidecode");
+                return vec![];
+            }
+        };
         root_shell.execute(format!("rm -f {DMIDECODE_DATA_PATH}"));
         root_shell.execute(format!(
             "dmidecode --dump-bin {DMIDECODE_DATA_PATH} &>/dev/null"
         ));
-        parse_sensor_values(fs::read(DMIDECODE_DATA_PATH).unwrap().as_slice())
+        match fs::read(DMIDECODE_DATA_PATH) {
+            Ok(dmidecode_data) => parse_sensor_values(&dmidecode_data),
+            Err(err) => {
+                warn!("Could not read the output of dmidecode: {err}");
+                vec![]
+            }
+        }
+    }
+}
+
+/// Hardware information does not change at runtime, thus it is only refreshed hourly
+impl sensor::SensorProvider for DmiDecodeSensors {
+    fn get_name(&self) -> String {
+        "DmiDecode".to_string()
+    }
+
+    fn get_preferred_interval(&self) -> Duration {
+        Duration::from_secs(60 * 60)
+    }
+
+    fn read(&self) -> Vec<SensorValue> {
+        self.get_sensor_values()
     }
 }
 
 #[cfg(target_os = "linux")]
 fn parse_sensor_values(dmidecode_data: &[u8]) -> Vec<SensorValue> {
-    let entry_point = EntryPoint::search(dmidecode_data).unwrap();
+    let entry_point = match EntryPoint::search(dmidecode_data) {
+        Ok(entry_point) => entry_point,
+        Err(err) => {
+            warn!("Could not find the entry point in the output of dmidecode: {err:?}");
+            return vec![];
+        }
+    };
+    let structure_data = match dmidecode_data.get(entry_point.smbios_address() as usize..) {
+        Some(structure_data) => structure_data,
+        None => {
+            warn!("The output of dmidecode does not contain the smbios structures");
+            return vec![];
+        }
+    };
 
     entry_point
-        .structures(&dmidecode_data[(entry_point.smbios_address() as usize)..])
+        .structures(structure_data)
         .filter_map(|s| s.ok())
         .flat_map(|entry| match entry {
             Structure::MemoryDevice(memory_device) => parse_memory_device(memory_device),
@@ -0,0 +1,231 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::utils;
+use crate::utils::LockResultExt;
+use sensor_core::{SensorType, SensorValue};
+
+/// Directory of the drm devices in sysfs
+pub const DRM_PATH: &str = "/sys/class/drm";
+
+/// Locations of the pci id database, used to resolve the marketing name of a card
+const PCI_IDS_PATHS: &[&str] = &["/usr/share/hwdata/pci.ids", "/usr/share/misc/pci.ids"];
+
+lazy_static! {
+    /// Matches hwmon files like `temp1_input` or `pwm1`, with the channel type, number and suffix
+    static ref HWMON_FILE_NAME: Regex = Regex::new(r"^([a-z]+)(\d+)(?:_(\w+))?$").unwrap();
+}
+
+/// A gpu card of the drm subsystem
+pub struct DrmCard {
+    /// Directory of the card, e.g. `/sys/class/drm/card0`
+    pub card_path: PathBuf,
+    /// Device directory of the card
+    pub device_path: PathBuf,
+    /// Pci slot, e.g. `0000:03:00.0`
+    pub pci_slot: String,
+    /// Vendor and device id, e.g. `1002:73BF`
+    pub pci_id: String,
+    /// Kernel driver of the card, e.g. `amdgpu`
+    pub driver: String,
+}
+
+/// Represents a type of hwmon channel, e.g. temperatures with files like `temp1_input` and `temp1_crit`
+pub struct HwmonChannelType {
+    pub prefix: &'static str,
+    pub unit: &'static str,
+    /// Factor to convert the value of the file to the base unit
+    pub factor: f64,
+    /// Suffixes of the files, that are read, the first one is the value of the channel
+    pub suffixes: &'static [&'static str],
+}
+
+/// Names of the cards by pci slot, resolving a name requires reading the pci id database
+#[derive(Default)]
+pub struct CardNames {
+    card_names: Mutex<HashMap<String, String>>,
+}
+
+impl CardNames {
+    /// Returns the marketing name of the card, resolved once per card
+    pub fn get(&self, card: &DrmCard) -> String {
+        self.card_names
+            .lock()
+            .ignore_poison()
+            .entry(card.pci_slot.clone())
+            .or_insert_with(|| resolve_card_name(card))
+            .clone()
+    }
+}
+
+/// Returns all gpu cards driven by one of the drivers, e.g. `card0`, but not connectors like `card0-DP-1`.
+/// The cards are sorted by pci slot, so that identical cards are listed in a stable order.
+pub fn get_drm_cards(drm_path: &Path, drivers: &[&str]) -> Vec<DrmCard> {
+    let sys_class_drm = match fs::read_dir(drm_path) {
+        Ok(sys_class_drm) => sys_class_drm,
+        Err(_) => return vec![],
+    };
+
+    let mut cards: Vec<DrmCard> = sys_class_drm
+        .flat_map(|entry| entry.ok())
+        .filter(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            file_name
+                .strip_prefix("card")
+                .is_some_and(|number| !number.is_empty() && number.chars().all(char::is_numeric))
+        })
+        .flat_map(|entry| read_card(&entry.path()))
+        .filter(|card| drivers.contains(&card.driver.as_str()))
+        .collect();
+
+    cards.sort_by(|a, b| a.pci_slot.cmp(&b.pci_slot));
+    cards
+}
+
+/// Reads the driver, pci slot and id of the card from its uevent file
+fn read_card(card_path: &Path) -> Option<DrmCard> {
+    let device_path = card_path.join("device");
+    let uevent = fs::read_to_string(device_path.join("uevent")).ok()?;
+    let properties: HashMap<&str, &str> = uevent
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .collect();
+
+    Some(DrmCard {
+        card_path: card_path.to_path_buf(),
+        device_path,
+        pci_slot: properties.get("PCI_SLOT_NAME")?.to_string(),
+        pci_id: properties.get("PCI_ID").unwrap_or(&"").to_string(),
+        driver: properties.get("DRIVER")?.to_string(),
+    })
+}
+
+/// Returns the marketing name of the card from the driver or the pci id database.
+/// Falls back to the pci id, if the name is unknown.
+fn resolve_card_name(card: &DrmCard) -> String {
+    let product_name = fs::read_to_string(card.device_path.join("product_name"))
+        .map(|product_name| product_name.trim().to_string())
+        .unwrap_or_default();
+    if !product_name.is_empty() {
+        return product_name;
+    }
+
+    PCI_IDS_PATHS
+        .iter()
+        .find_map(|path| {
+            let pci_ids = fs::read_to_string(path).ok()?;
+            find_pci_device_name(&pci_ids, &card.pci_id)
+        })
+        .unwrap_or_else(|| format!("GPU {}", card.pci_id))
+}
+
+/// Looks up the device name of the pci id, e.g. `1002:73BF`, in the content of the pci id database
+pub fn find_pci_device_name(pci_ids: &str, pci_id: &str) -> Option<String> {
+    let pci_id = pci_id.to_lowercase();
+    let (vendor_id, device_id) = pci_id.split_once(':')?;
+
+    let mut is_vendor = false;
+    for line in pci_ids.lines() {
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+        if !line.starts_with('\t') {
+            is_vendor = line.to_lowercase().starts_with(vendor_id);
+            continue;
+        }
+        if !is_vendor || line.starts_with("\t\t") {
+            continue;
+        }
+        if let Some((id, name)) = line.trim().split_once(char::is_whitespace) {
+            if id.to_lowercase() == device_id {
+                return Some(name.trim().to_string());
+            }
+        }
+    }
+
+    None
+}
+
+/// Returns the id of the card in sensor ids, the pci slot without separators, e.g. `0000_03_00_0`
+pub fn get_card_id(card: &DrmCard) -> String {
+    card.pci_slot.replace([':', '.'], "_")
+}
+
+/// Returns the hwmon directory of the card, if the driver exposes one
+pub fn get_hwmon_path(card: &DrmCard) -> Option<PathBuf> {
+    fs::read_dir(card.device_path.join("hwmon"))
+        .ok()?
+        .flat_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .next()
+}
+
+/// Reads the channels of the specified types from the hwmon directory of the card.
+/// The channels are discovered from the files, labels like `edge` or `pkg` are read from the label files.
+pub fn read_hwmon_sensors(
+    card: &DrmCard,
+    card_name: &str,
+    channel_types: &[HwmonChannelType],
+) -> Vec<SensorValue> {
+    let hwmon_path = match get_hwmon_path(card) {
+        Some(hwmon_path) => hwmon_path,
+        None => return vec![],
+    };
+    let mut file_names: Vec<String> = match fs::read_dir(&hwmon_path) {
+        Ok(entries) => entries
+            .flat_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect(),
+        Err(_) => return vec![],
+    };
+    file_names.sort();
+
+    let mut sensor_values = vec![];
+    for file_name in file_names {
+        let captures = match HWMON_FILE_NAME.captures(&file_name) {
+            Some(captures) => captures,
+            None => continue,
+        };
+        let prefix = &captures[1];
+        let channel = format!("{prefix}{}", &captures[2]);
+        let suffix = captures.get(3).map_or("", |suffix| suffix.as_str());
+
+        let channel_type = match channel_types
+            .iter()
+            .find(|channel_type| channel_type.prefix == prefix)
+        {
+            Some(channel_type) if channel_type.suffixes.contains(&suffix) => channel_type,
+            _ => continue,
+        };
+        let value = match fs::read_to_string(hwmon_path.join(&file_name))
+            .ok()
+            .and_then(|value| value.trim().parse::<f64>().ok())
+        {
+            Some(value) => value,
+            None => continue,
+        };
+
+        let channel_label = fs::read_to_string(hwmon_path.join(format!("{channel}_label")))
+            .map(|label| label.trim().to_string())
+            .unwrap_or(channel);
+        let label = if suffix == channel_type.suffixes[0] {
+            format!("{card_name} {channel_label}")
+        } else {
+            format!("{card_name} {channel_label} {}", suffix.replace('_', " "))
+        };
+
+        sensor_values.push(SensorValue {
+            id: format!("gpu_{}_{}", get_card_id(card), file_name),
+            value: utils::format_number(value * channel_type.factor),
+            unit: channel_type.unit.to_string(),
+            label,
+            sensor_type: SensorType::Number,
+        });
+    }
+
+    sensor_values
+}
@@ -0,0 +1,26 @@
+use assertor::{assert_that, EqualityAssertion};
+
+use crate::linux_drm;
+
+#[test]
+fn test_find_pci_device_name() {
+    // GIVEN is an excerpt of the pci id database
+    let pci_ids = "# Comment
+1002  Advanced Micro Devices, Inc. [AMD/ATI]
+\t73bf  Navi 21 [Radeon RX 6800/6800 XT / 6900 XT]
+\t\t1002 0e3a  Radeon RX 6900 XT
+\t73df  Navi 22 [Radeon RX 6700/6700 XT/6750 XT / 6800M/6850M XT]
+8086  Intel Corporation
+\t73bf  Not an AMD device
+";
+
+    // WHEN looking up pci ids
+    let known = linux_drm::find_pci_device_name(pci_ids, "1002:73BF");
+    let unknown = linux_drm::find_pci_device_name(pci_ids, "1002:1234");
+
+    // THEN the device name of the vendor should be found
+    assert_that!(known).is_equal_to(Some(
+        "Navi 21 [Radeon RX 6800/6800 XT / 6900 XT]".to_string(),
+    ));
+    assert_that!(unknown).is_equal_to(None);
+}
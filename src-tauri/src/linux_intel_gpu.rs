@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::linux_drm::{CardNames, DrmCard, HwmonChannelType};
+use crate::utils::LockResultExt;
+use crate::{linux_drm, sensor, units, utils};
+use sensor_core::{SensorType, SensorValue};
+
+/// Kernel drivers of intel gpus
+const DRIVERS: &[&str] = &["i915", "xe"];
+
+/// Reads the sensors of all cards driven by i915 or xe, from the card directory and its hwmon directory.
+/// The utilization is estimated from the rc6 residency, the time the gpu was idle,
+/// and the power from the energy counter, thus the first read reports neither.
+pub struct IntelGpuSensors {
+    drm_path: PathBuf,
+    card_names: CardNames,
+    /// Counters of the previous read by pci slot, to calculate the utilization and power
+    previous_reads: Mutex<HashMap<String, PreviousRead>>,
+}
+
+/// Counters of a card at the previous read
+struct PreviousRead {
+    read_at: Instant,
+    idle_residency_ms: Option<f64>,
+    energy_uj: Option<f64>,
+}
+
+/// A gpu frequency in MHz, exposed by i915 in the card directory and by xe in the gt directory
+struct IntelGpuFrequency {
+    name: &'static str,
+    label: &'static str,
+    i915_file_name: &'static str,
+    xe_file_name: &'static str,
+}
+
+impl Default for IntelGpuSensors {
+    fn default() -> IntelGpuSensors {
+        IntelGpuSensors::new(Path::new(linux_drm::DRM_PATH))
+    }
+}
+
+impl sensor::SensorProvider for IntelGpuSensors {
+    fn get_name(&self) -> String {
+        "IntelGpu".to_string()
+    }
+
+    fn get_preferred_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn read(&self) -> Vec<SensorValue> {
+        self.read_all_sensors(Instant::now())
+    }
+}
+
+/// Frequencies read from each card
+const FREQUENCIES: &[IntelGpuFrequency] = &[
+    IntelGpuFrequency {
+        name: "cur_freq",
+        label: "GPU frequency",
+        i915_file_name: "gt_cur_freq_mhz",
+        xe_file_name: "cur_freq",
+    },
+    IntelGpuFrequency {
+        name: "act_freq",
+        label: "GPU actual frequency",
+        i915_file_name: "gt_act_freq_mhz",
+        xe_file_name: "act_freq",
+    },
+    IntelGpuFrequency {
+        name: "min_freq",
+        label: "GPU min frequency",
+        i915_file_name: "gt_min_freq_mhz",
+        xe_file_name: "min_freq",
+    },
+    IntelGpuFrequency {
+        name: "max_freq",
+        label: "GPU max frequency",
+        i915_file_name: "gt_max_freq_mhz",
+        xe_file_name: "max_freq",
+    },
+];
+
+/// Types of hwmon channels, that are read from the i915 and xe hwmon directories
+const HWMON_CHANNEL_TYPES: &[HwmonChannelType] = &[
+    HwmonChannelType {
+        prefix: "temp",
+        unit: units::CELSIUS,
+        factor: 0.001,
+        suffixes: &["input", "crit"],
+    },
+    HwmonChannelType {
+        prefix: "power",
+        unit: units::WATT,
+        factor: 1e-6,
+        suffixes: &["input", "max", "crit"],
+    },
+    HwmonChannelType {
+        prefix: "energy",
+        unit: units::JOULE,
+        factor: 1e-6,
+        suffixes: &["input"],
+    },
+    HwmonChannelType {
+        prefix: "in",
+        unit: units::VOLT,
+        factor: 0.001,
+        suffixes: &["input"],
+    },
+    HwmonChannelType {
+        prefix: "fan",
+        unit: units::RPM,
+        factor: 1.0,
+        suffixes: &["input"],
+    },
+];
+
+impl IntelGpuSensors {
+    /// Creates the provider for the drm devices in the specified directory
+    pub fn new(drm_path: &Path) -> IntelGpuSensors {
+        IntelGpuSensors {
+            drm_path: drm_path.to_path_buf(),
+            card_names: CardNames::default(),
+            previous_reads: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Reads all available sensors from all gpu cards.
+    /// The utilization and power are calculated over the time since the previous read.
+    pub fn read_all_sensors(&self, now: Instant) -> Vec<SensorValue> {
+        linux_drm::get_drm_cards(&self.drm_path, DRIVERS)
+            .iter()
+            .flat_map(|card| {
+                let card_name = self.card_names.get(card);
+                let mut sensor_values = read_frequencies(card, &card_name);
+                sensor_values.extend(self.read_counter_sensors(card, &card_name, now));
+                sensor_values.extend(linux_drm::read_hwmon_sensors(
+                    card,
+                    &card_name,
+                    HWMON_CHANNEL_TYPES,
+                ));
+                sensor_values
+            })
+            .collect()
+    }
+
+    /// Returns the utilization and power of the card, from the counters since the previous read
+    fn read_counter_sensors(
+        &self,
+        card: &DrmCard,
+        card_name: &str,
+        now: Instant,
+    ) -> Vec<SensorValue> {
+        let idle_residency_ms = read_idle_residency_ms(card);
+        let energy_uj = linux_drm::get_hwmon_path(card)
+            .and_then(|hwmon_path| read_number(&hwmon_path.join("energy1_input")));
+        let current_read = PreviousRead {
+            read_at: now,
+            idle_residency_ms,
+            energy_uj,
+        };
+        let previous_read = self
+            .previous_reads
+            .lock()
+            .ignore_poison()
+            .insert(card.pci_slot.clone(), current_read);
+
+        let previous_read = match previous_read {
+            Some(previous_read) => previous_read,
+            None => return vec![],
+        };
+        let elapsed_ms = now.duration_since(previous_read.read_at).as_secs_f64() * 1000.0;
+        if elapsed_ms <= 0.0 {
+            return vec![];
+        }
+
+        let card_id = linux_drm::get_card_id(card);
+        let mut sensor_values = vec![];
+
+        if let Some(idle_ms) = get_counter_delta(previous_read.idle_residency_ms, idle_residency_ms)
+        {
+            let busy_percent = (100.0 - idle_ms / elapsed_ms * 100.0).clamp(0.0, 100.0);
+            sensor_values.push(SensorValue {
+                id: format!("gpu_{card_id}_busy_percent"),
+                value: utils::format_number(busy_percent),
+                unit: units::PERCENT.to_string(),
+                label: format!("{card_name} GPU utilization"),
+                sensor_type: SensorType::Number,
+            });
+        }
+
+        if let Some(energy_delta_uj) = get_counter_delta(previous_read.energy_uj, energy_uj) {
+            // Microjoules per millisecond are milliwatts
+            let power = energy_delta_uj / elapsed_ms / 1000.0;
+            sensor_values.push(SensorValue {
+                id: format!("gpu_{card_id}_power"),
+                value: utils::format_number(power),
+                unit: units::WATT.to_string(),
+                label: format!("{card_name} GPU power"),
+                sensor_type: SensorType::Number,
+            });
+        }
+
+        sensor_values
+    }
+}
+
+/// Returns the increase of a counter, None if it is unavailable or was reset
+fn get_counter_delta(previous: Option<f64>, current: Option<f64>) -> Option<f64> {
+    match (previous, current) {
+        (Some(previous), Some(current)) if current >= previous => Some(current - previous),
+        _ => None,
+    }
+}
+
+/// Reads the frequencies of the card, converted from MHz to the base unit
+fn read_frequencies(card: &DrmCard, card_name: &str) -> Vec<SensorValue> {
+    let card_id = linux_drm::get_card_id(card);
+
+    FREQUENCIES
+        .iter()
+        .flat_map(|frequency| {
+            let path = match card.driver.as_str() {
+                "xe" => get_xe_gt_path(card)
+                    .join("freq0")
+                    .join(frequency.xe_file_name),
+                _ => card.card_path.join(frequency.i915_file_name),
+            };
+            let value = read_number(&path)?;
+
+            Some(SensorValue {
+                id: format!("gpu_{card_id}_{}", frequency.name),
+                value: format!("{:.0}", value * 1e6),
+                unit: units::HERTZ.to_string(),
+                label: format!("{card_name} {}", frequency.label),
+                sensor_type: SensorType::Number,
+            })
+        })
+        .collect()
+}
+
+/// Reads the total time in milliseconds the gpu was idle, in the rc6 power state
+fn read_idle_residency_ms(card: &DrmCard) -> Option<f64> {
+    match card.driver.as_str() {
+        "xe" => read_number(&get_xe_gt_path(card).join("gtidle/idle_residency_ms")),
+        _ => read_number(&card.card_path.join("gt/gt0/rc6_residency_ms"))
+            .or_else(|| read_number(&card.card_path.join("power/rc6_residency_ms"))),
+    }
+}
+
+/// Returns the directory of the primary gt of a card driven by xe
+fn get_xe_gt_path(card: &DrmCard) -> PathBuf {
+    card.device_path.join("tile0/gt0")
+}
+
+/// Reads a file containing a single number
+fn read_number(path: &Path) -> Option<f64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
@@ -0,0 +1,148 @@
+use std::fs;
+use std::time::{Duration, Instant};
+
+use assertor::{assert_that, EqualityAssertion};
+use sensor_core::SensorValue;
+use uuid::Uuid;
+
+use crate::linux_intel_gpu::IntelGpuSensors;
+use crate::test_utils::write_files;
+
+/// Returns value, unit and label of the sensor, or None if it was not read
+fn find(sensor_values: &[SensorValue], id: &str) -> Option<(String, String, String)> {
+    sensor_values
+        .iter()
+        .find(|sensor_value| sensor_value.id == id)
+        .map(|sensor_value| {
+            (
+                sensor_value.value.clone(),
+                sensor_value.unit.clone(),
+                sensor_value.label.clone(),
+            )
+        })
+}
+
+fn expected(value: &str, unit: &str, label: &str) -> Option<(String, String, String)> {
+    Some((value.to_string(), unit.to_string(), label.to_string()))
+}
+
+#[test]
+fn test_read_i915() {
+    // GIVEN is a drm directory with an integrated gpu driven by i915
+    let drm_path = std::env::temp_dir().join(format!("sensor-bridge-test-{}", Uuid::new_v4()));
+    write_files(
+        &drm_path,
+        &[
+            (
+                "card0/device/uevent",
+                "DRIVER=i915\nPCI_ID=8086:9A49\nPCI_SLOT_NAME=0000:00:02.0\n",
+            ),
+            ("card0/device/product_name", "Iris Xe Graphics\n"),
+            ("card0/gt_cur_freq_mhz", "1300\n"),
+            ("card0/gt_act_freq_mhz", "1250\n"),
+            ("card0/gt_min_freq_mhz", "100\n"),
+            ("card0/gt_max_freq_mhz", "1350\n"),
+            ("card0/gt/gt0/rc6_residency_ms", "10000\n"),
+            ("card0/device/hwmon/hwmon3/energy1_input", "5000000\n"),
+            ("card0/device/hwmon/hwmon3/power1_max", "25000000\n"),
+        ],
+    );
+    let intel_gpu_sensors = IntelGpuSensors::new(&drm_path);
+    let start = Instant::now();
+
+    // WHEN reading twice, while the gpu was idle 600 ms and consumed 20 J within one second
+    let first_read = intel_gpu_sensors.read_all_sensors(start);
+    write_files(
+        &drm_path,
+        &[
+            ("card0/gt/gt0/rc6_residency_ms", "10600\n"),
+            ("card0/device/hwmon/hwmon3/energy1_input", "25000000\n"),
+        ],
+    );
+    let second_read = intel_gpu_sensors.read_all_sensors(start + Duration::from_secs(1));
+    fs::remove_dir_all(&drm_path).unwrap();
+
+    // THEN the frequencies and limits should be read,
+    // and the utilization and power should be calculated from the second read on
+    let card = "gpu_0000_00_02_0";
+    assert_that!(find(&first_read, &format!("{card}_cur_freq"))).is_equal_to(expected(
+        "1300000000",
+        "Hz",
+        "Iris Xe Graphics GPU frequency",
+    ));
+    assert_that!(find(&first_read, &format!("{card}_act_freq"))).is_equal_to(expected(
+        "1250000000",
+        "Hz",
+        "Iris Xe Graphics GPU actual frequency",
+    ));
+    assert_that!(find(&first_read, &format!("{card}_power1_max"))).is_equal_to(expected(
+        "25",
+        "W",
+        "Iris Xe Graphics power1 max",
+    ));
+    assert_that!(find(&first_read, &format!("{card}_busy_percent"))).is_equal_to(None);
+    assert_that!(find(&second_read, &format!("{card}_busy_percent"))).is_equal_to(expected(
+        "40",
+        "%",
+        "Iris Xe Graphics GPU utilization",
+    ));
+    assert_that!(find(&second_read, &format!("{card}_power"))).is_equal_to(expected(
+        "20",
+        "W",
+        "Iris Xe Graphics GPU power",
+    ));
+}
+
+#[test]
+fn test_read_xe() {
+    // GIVEN is a drm directory with a discrete gpu driven by xe and an amd gpu
+    let drm_path = std::env::temp_dir().join(format!("sensor-bridge-test-{}", Uuid::new_v4()));
+    write_files(
+        &drm_path,
+        &[
+            (
+                "card1/device/uevent",
+                "DRIVER=xe\nPCI_ID=8086:E20B\nPCI_SLOT_NAME=0000:03:00.0\n",
+            ),
+            ("card1/device/product_name", "Arc B580\n"),
+            ("card1/device/tile0/gt0/freq0/cur_freq", "2850\n"),
+            ("card1/device/tile0/gt0/gtidle/idle_residency_ms", "500\n"),
+            ("card1/device/hwmon/hwmon5/temp2_input", "52000\n"),
+            ("card1/device/hwmon/hwmon5/temp2_label", "pkg\n"),
+            (
+                "card0/device/uevent",
+                "DRIVER=amdgpu\nPCI_ID=1002:73BF\nPCI_SLOT_NAME=0000:0a:00.0\n",
+            ),
+        ],
+    );
+    let intel_gpu_sensors = IntelGpuSensors::new(&drm_path);
+    let start = Instant::now();
+
+    // WHEN reading twice, while the gpu was idle the whole time
+    intel_gpu_sensors.read_all_sensors(start);
+    write_files(
+        &drm_path,
+        &[("card1/device/tile0/gt0/gtidle/idle_residency_ms", "1500\n")],
+    );
+    let sensor_values = intel_gpu_sensors.read_all_sensors(start + Duration::from_secs(1));
+    fs::remove_dir_all(&drm_path).unwrap();
+
+    // THEN only the xe card should be read, with sensors from its gt and hwmon directories
+    let card = "gpu_0000_03_00_0";
+    assert_that!(sensor_values.len()).is_equal_to(3);
+    assert_that!(find(&sensor_values, &format!("{card}_cur_freq"))).is_equal_to(expected(
+        "2850000000",
+        "Hz",
+        "Arc B580 GPU frequency",
+    ));
+    assert_that!(find(&sensor_values, &format!("{card}_busy_percent"))).is_equal_to(expected(
+        "0",
+        "%",
+        "Arc B580 GPU utilization",
+    ));
+    assert_that!(find(&sensor_values, &format!("{card}_temp2_input"))).is_equal_to(expected(
+        "52",
+        "°C",
+        "Arc B580 pkg",
+    ));
+}
@@ -1,48 +1,101 @@
+use std::sync::{Mutex, Once};
+use std::time::{Duration, Instant};
+
+use log::warn;
 use sensor_core::{SensorType, SensorValue};
 
+use crate::utils::LockResultExt;
+use crate::{sensor, units};
+
+/// Suffixes of subfeatures, that configure the chip instead of reporting a value
+const IGNORED_SUB_FEATURE_SUFFIXES: &[&str] =
+    &["beep", "type", "offset", "enable", "div", "pulses"];
+
+/// Time after a failed initialization, in which libsensors is not initialized again.
+/// This keeps the sampling thread from trying the initialization on every read.
+const INIT_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Warns only once, if libsensors can not be initialized, as the provider is read every second
+static INIT_FAILURE_WARNING: Once = Once::new();
+
+/// Time of the last failed initialization of libsensors.
+/// libsensors can only be initialized once per process, so the lock is held while it is initialized and read.
+static LAST_INIT_FAILURE: Mutex<Option<Instant>> = Mutex::new(None);
+
+pub struct LmSensors {}
+
+impl sensor::SensorProvider for LmSensors {
+    fn get_name(&self) -> String {
+        "LmSensors".to_string()
+    }
+
+    fn get_preferred_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn read(&self) -> Vec<SensorValue> {
+        get_sensor_values()
+    }
+}
+
 pub fn get_sensor_values() -> Vec<SensorValue> {
     get_all_available_sensors()
 }
 
+/// Reads every relevant subfeature of all chips, e.g. the input, min, max, crit and alarm of a temperature.
+/// The first subfeature of a feature is its value, with the id and label of the feature.
+/// Further subfeatures get the suffix of their name, e.g. `lm_coretemp-temp1_crit`.
 fn get_all_available_sensors() -> Vec<SensorValue> {
-    let mut sensor_values: Vec<SensorValue> = vec![];
+    // Declared before the sensors, so that libsensors is cleaned up before the lock is released
+    let mut last_init_failure = LAST_INIT_FAILURE.lock().ignore_poison();
+    let sensors = match initialize(&mut last_init_failure) {
+        Some(sensors) => sensors,
+        None => return vec![],
+    };
 
-    // Get all available sensors
-    // Initialize LM sensors library and wait until it is not used by another process
-    let mut sensors = lm_sensors::Initializer::default().initialize().ok();
-    while sensors.is_none() {
-        sensors = lm_sensors::Initializer::default().initialize().ok();
-        std::thread::sleep(std::time::Duration::from_millis(50));
-    }
-    let sensors = sensors.unwrap();
-
-    // Print all chips.
+    let mut sensor_values: Vec<SensorValue> = vec![];
     for chip in sensors.chip_iter(None) {
-        let chip_name = chip.prefix().unwrap().unwrap();
+        let chip_name = match chip.prefix() {
+            Some(Ok(chip_name)) => chip_name.to_string(),
+            _ => continue,
+        };
+
         for feature in chip.feature_iter() {
-            let name = feature.name().transpose().unwrap().unwrap();
-            // Get first sub feature
-            let first_sub_feature = feature.sub_feature_iter().next();
-            if let Some(sub_feature) = first_sub_feature {
-                let sub_feature_value = sub_feature.value();
-                if sub_feature_value.is_err() {
-                    continue;
-                }
-
-                let feature_value = sub_feature_value.unwrap();
-
-                let sensor_name = format!("{}-{}", chip_name, name);
-                let sensor_value = feature_value.raw_value();
-                let mut sensor_unit = feature_value.unit().to_string();
-                if sensor_unit == "C" {
-                    sensor_unit = "°C".to_string();
-                }
+            let feature_name = match feature.name() {
+                Some(Ok(feature_name)) => feature_name.to_string(),
+                _ => continue,
+            };
+            // The label respects the labels configured in sensors.conf
+            let feature_label = feature.label().unwrap_or_else(|_| feature_name.clone());
+
+            for (index, sub_feature) in feature.sub_feature_iter().enumerate() {
+                let sub_feature_name = match sub_feature.name() {
+                    Some(Ok(sub_feature_name)) => sub_feature_name.to_string(),
+                    _ => continue,
+                };
+                let suffix = match index {
+                    0 => None,
+                    _ => match get_sub_feature_suffix(&feature_name, &sub_feature_name) {
+                        Some(suffix) => Some(suffix),
+                        None => continue,
+                    },
+                };
+                let value = match sub_feature.value() {
+                    Ok(value) => value,
+                    Err(_) => continue,
+                };
+
+                let (value, unit) = get_value_and_unit(
+                    &sub_feature_name,
+                    value.raw_value(),
+                    &value.unit().to_string(),
+                );
 
                 sensor_values.push(SensorValue {
-                    id: format!("lm_{sensor_name}"),
-                    value: sensor_value.to_string(),
-                    unit: sensor_unit,
-                    label: sensor_name,
+                    id: get_sensor_id(&chip_name, &feature_name, suffix),
+                    value,
+                    unit,
+                    label: get_sensor_label(&chip_name, &feature_label, suffix),
                     sensor_type: SensorType::Number,
                 });
             }
@@ -51,3 +104,86 @@ fn get_all_available_sensors() -> Vec<SensorValue> {
 
     sensor_values
 }
+
+/// Initializes libsensors, the caller must hold the lock of the last initialization failure.
+/// Returns None if it could not be initialized, or if the last initialization failed within the backoff.
+fn initialize(last_init_failure: &mut Option<Instant>) -> Option<lm_sensors::LMSensors> {
+    if !is_init_due(*last_init_failure, Instant::now()) {
+        return None;
+    }
+
+    match lm_sensors::Initializer::default().initialize() {
+        Ok(sensors) => {
+            *last_init_failure = None;
+            Some(sensors)
+        }
+        Err(err) => {
+            INIT_FAILURE_WARNING.call_once(|| {
+                warn!("Could not initialize lm-sensors, retrying every {INIT_BACKOFF:?}: {err}")
+            });
+            *last_init_failure = Some(Instant::now());
+            None
+        }
+    }
+}
+
+/// Returns true if libsensors should be initialized, as the backoff after the last failure elapsed
+pub fn is_init_due(last_init_failure: Option<Instant>, now: Instant) -> bool {
+    last_init_failure
+        .is_none_or(|last_init_failure| now.duration_since(last_init_failure) >= INIT_BACKOFF)
+}
+
+/// Returns the value and unit of the subfeature.
+/// Alarms and faults are flags without unit, reported as 1 if raised and 0 otherwise.
+pub fn get_value_and_unit(sub_feature_name: &str, raw_value: f64, unit: &str) -> (String, String) {
+    if is_flag_sub_feature(sub_feature_name) {
+        let value = if raw_value != 0.0 { "1" } else { "0" };
+        return (value.to_string(), String::new());
+    }
+
+    let unit = match unit {
+        "C" => units::CELSIUS,
+        unit => unit,
+    };
+
+    (raw_value.to_string(), unit.to_string())
+}
+
+/// Returns true if the subfeature is an alarm or fault flag, e.g. `temp1_crit_alarm` or `fan1_fault`
+fn is_flag_sub_feature(sub_feature_name: &str) -> bool {
+    sub_feature_name.ends_with("alarm") || sub_feature_name.ends_with("fault")
+}
+
+/// Returns the suffix of the subfeature, e.g. `max` of `temp1_max` in feature `temp1`.
+/// Returns None for subfeatures that configure the chip, like `temp1_type`.
+pub fn get_sub_feature_suffix<'a>(
+    feature_name: &str,
+    sub_feature_name: &'a str,
+) -> Option<&'a str> {
+    let suffix = sub_feature_name
+        .strip_prefix(feature_name)
+        .and_then(|suffix| suffix.strip_prefix('_'))
+        .unwrap_or(sub_feature_name);
+
+    if suffix.is_empty() || IGNORED_SUB_FEATURE_SUFFIXES.contains(&suffix) {
+        return None;
+    }
+
+    Some(suffix)
+}
+
+/// Returns the sensor id of the feature, with the suffix of the subfeature unless it is the value
+pub fn get_sensor_id(chip_name: &str, feature_name: &str, suffix: Option<&str>) -> String {
+    match suffix {
+        Some(suffix) => format!("lm_{chip_name}-{feature_name}_{suffix}"),
+        None => format!("lm_{chip_name}-{feature_name}"),
+    }
+}
+
+/// Returns the sensor label of the feature, with the suffix of the subfeature unless it is the value
+pub fn get_sensor_label(chip_name: &str, feature_label: &str, suffix: Option<&str>) -> String {
+    match suffix {
+        Some(suffix) => format!("{chip_name}-{feature_label} {suffix}"),
+        None => format!("{chip_name}-{feature_label}"),
+    }
+}
@@ -0,0 +1,73 @@
+use std::time::{Duration, Instant};
+
+use assertor::{assert_that, BooleanAssertion, EqualityAssertion};
+
+use crate::linux_lm_sensors;
+
+#[test]
+fn test_get_sub_feature_suffix() {
+    // GIVEN are subfeatures of a temperature feature
+    let sub_feature_names = ["temp1_max", "temp1_crit_alarm", "temp1_type", "temp1_beep"];
+
+    // WHEN getting their suffixes
+    let suffixes: Vec<Option<&str>> = sub_feature_names
+        .iter()
+        .map(|sub_feature_name| linux_lm_sensors::get_sub_feature_suffix("temp1", sub_feature_name))
+        .collect();
+
+    // THEN thresholds and alarms should be kept and configuration subfeatures should be ignored
+    assert_that!(suffixes).is_equal_to(vec![Some("max"), Some("crit_alarm"), None, None]);
+}
+
+#[test]
+fn test_get_sensor_id_and_label() {
+    // GIVEN is a feature with a label from sensors.conf
+
+    // WHEN getting the id and label of its value and its critical threshold
+    let value_id = linux_lm_sensors::get_sensor_id("coretemp", "temp1", None);
+    let crit_id = linux_lm_sensors::get_sensor_id("coretemp", "temp1", Some("crit"));
+    let value_label = linux_lm_sensors::get_sensor_label("coretemp", "Package id 0", None);
+    let crit_label = linux_lm_sensors::get_sensor_label("coretemp", "Package id 0", Some("crit"));
+
+    // THEN the value should keep the id of the feature and the threshold should get a suffix
+    assert_that!(value_id).is_equal_to("lm_coretemp-temp1".to_string());
+    assert_that!(crit_id).is_equal_to("lm_coretemp-temp1_crit".to_string());
+    assert_that!(value_label).is_equal_to("coretemp-Package id 0".to_string());
+    assert_that!(crit_label).is_equal_to("coretemp-Package id 0 crit".to_string());
+}
+
+#[test]
+fn test_get_value_and_unit() {
+    // GIVEN are a temperature, a raised alarm and a fault that is not raised
+
+    // WHEN getting their values and units
+    let temperature = linux_lm_sensors::get_value_and_unit("temp1_input", 45.5, "C");
+    let alarm = linux_lm_sensors::get_value_and_unit("temp1_crit_alarm", 1.0, "C");
+    let fault = linux_lm_sensors::get_value_and_unit("fan1_fault", 0.0, "RPM");
+
+    // THEN the temperature should be in celsius and the flags should be 1 or 0 without unit
+    assert_that!(temperature).is_equal_to(("45.5".to_string(), "°C".to_string()));
+    assert_that!(alarm).is_equal_to(("1".to_string(), "".to_string()));
+    assert_that!(fault).is_equal_to(("0".to_string(), "".to_string()));
+}
+
+#[test]
+fn test_is_init_due() {
+    // GIVEN is the time of a failed initialization
+    let failure = Instant::now();
+
+    // WHEN checking if the initialization is due within and after the backoff
+
+    // THEN it should only be retried after the backoff
+    assert_that!(linux_lm_sensors::is_init_due(None, failure)).is_true();
+    assert_that!(linux_lm_sensors::is_init_due(
+        Some(failure),
+        failure + Duration::from_secs(30)
+    ))
+    .is_false();
+    assert_that!(linux_lm_sensors::is_init_due(
+        Some(failure),
+        failure + Duration::from_secs(60)
+    ))
+    .is_true();
+}
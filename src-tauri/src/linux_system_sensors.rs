@@ -1,5 +1,24 @@
 use sensor_core::{SensorType, SensorValue};
 use std::fs;
+use std::time::Duration;
+
+use crate::{sensor, units};
+
+pub struct LinuxSystemSensor {}
+
+impl sensor::SensorProvider for LinuxSystemSensor {
+    fn get_name(&self) -> String {
+        "LinuxSystem".to_string()
+    }
+
+    fn get_preferred_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn read(&self) -> Vec<SensorValue> {
+        get_sensor_values()
+    }
+}
 
 pub fn get_sensor_values() -> Vec<SensorValue> {
     get_all_available_sensors()
@@ -12,7 +31,7 @@ fn get_all_available_sensors() -> Vec<SensorValue> {
         .filter(|line| line.contains("cpu MHz"))
         .enumerate()
         .map(|(i, line)| {
-            let cpu_frequency: f32 = line.split(':').collect::<Vec<&str>>()[1]
+            let cpu_frequency_mhz: f64 = line.split(':').collect::<Vec<&str>>()[1]
                 .trim()
                 .parse()
                 .unwrap();
@@ -21,8 +40,8 @@ fn get_all_available_sensors() -> Vec<SensorValue> {
 
             SensorValue {
                 id: sensor_name,
-                value: format!("{:.0}", cpu_frequency),
-                unit: "MHz".to_string(),
+                value: format!("{:.0}", cpu_frequency_mhz * 1e6),
+                unit: units::HERTZ.to_string(),
                 label: sensor_label,
                 sensor_type: SensorType::Number,
             }
@@ -1,60 +1,134 @@
 #![cfg_attr(not(debug_assertions), deny(warnings))]
 
 use crate::config::{AppConfig, NetworkDeviceConfig};
-use crate::utils::LockResultExt;
-use log::error;
+use crate::discovery::DiscoveredDevice;
+use crate::recording::{Recorder, RecordingConfig};
+use crate::sensor::SensorProviderRegistry;
+use crate::sensor_sampler::{SensorDemand, SensorSampler};
+use crate::sensor_transform::SensorTransforms;
+use log::{error, info};
 use sensor_core::{
     conditional_image_renderer, graph_renderer, ConditionalImageConfig, ElementType, GraphConfig,
     SensorType, SensorValue, TextConfig,
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::ops::Deref;
 use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
 use std::{fs, thread};
 use super_shell::RootShell;
 use tauri::menu::{Menu, MenuItem};
 use tauri::{
     tray::{MouseButton, TrayIconBuilder, TrayIconEvent},
-    App, State,
+    App, State, Wry,
 };
 use tauri::{AppHandle, Manager};
 
+mod alerts;
+mod cli;
 mod conditional_image;
 pub(crate) mod config;
+mod derived_sensor;
+mod discovery;
 mod export_import;
 mod fonts;
+mod headless;
+mod history_store;
+mod http_server;
 mod lcd_preview;
 mod linux_dmidecode_sensors;
+mod linux_drm;
+mod linux_intel_gpu;
 mod linux_lm_sensors;
 mod linux_system_sensors;
+mod metrics;
 mod misc_sensor;
+mod mqtt;
 mod net_port;
+mod recording;
+mod render_frame;
 mod sensor;
+mod sensor_history;
+mod sensor_override;
+mod sensor_sampler;
+mod sensor_transform;
 mod static_image;
 mod system_stat_sensor;
 mod text;
+mod units;
 mod utils;
 
+#[cfg(test)]
+mod alerts_test;
+#[cfg(test)]
+mod cli_test;
+#[cfg(test)]
+mod config_test;
+#[cfg(test)]
+mod derived_sensor_test;
+#[cfg(test)]
+mod discovery_test;
 #[cfg(test)]
 mod fonts_test;
+#[cfg(test)]
+mod headless_test;
+#[cfg(test)]
+mod history_store_test;
+#[cfg(test)]
+mod http_server_test;
 mod linux_amdgpu;
+#[cfg(test)]
+mod linux_amdgpu_test;
+#[cfg(test)]
+mod linux_drm_test;
+#[cfg(test)]
+mod linux_intel_gpu_test;
+#[cfg(test)]
+mod linux_lm_sensors_test;
+#[cfg(test)]
+mod metrics_test;
+#[cfg(test)]
+mod mqtt_test;
+#[cfg(test)]
+mod net_port_test;
+#[cfg(test)]
+mod recording_test;
+#[cfg(test)]
+mod render_frame_test;
+#[cfg(test)]
+mod sensor_history_test;
+#[cfg(test)]
+mod sensor_override_test;
+#[cfg(test)]
+mod sensor_sampler_test;
+#[cfg(test)]
+mod sensor_test;
+#[cfg(test)]
+mod sensor_transform_test;
+#[cfg(test)]
+mod system_stat_sensor_test;
+#[cfg(test)]
+mod test_utils;
+#[cfg(test)]
+mod units_test;
 
 pub struct AppState {
     pub port_handle: Mutex<HashMap<String, ThreadHandle>>,
     pub root_shell: Arc<Mutex<Option<RootShell>>>,
-    pub static_sensor_values: Arc<Vec<SensorValue>>,
-    pub sensor_value_history: Arc<Mutex<Vec<Vec<SensorValue>>>>,
+    pub sensor_sampler: Arc<SensorSampler>,
+    pub recorder: Recorder,
+    /// Sensors shown by the designer, the layout of the open device and the previewed elements
+    pub ui_sensor_ids: Mutex<HashSet<String>>,
 }
 
+/// Consumer id of the UI at the sensor sampler
+const UI_CONSUMER_ID: &str = "ui";
+
 pub struct ThreadHandle {
     pub running: Arc<Mutex<bool>>,
     pub handle: Arc<thread::JoinHandle<()>>,
 }
 
-// Number of elements to be stored in the sensor value history
-pub const SENSOR_VALUE_HISTORY_SIZE: usize = 1000;
-
 fn main() {
     // Set the app name for the dynamic cache folder detection
     // TODO: fixme
@@ -62,24 +136,61 @@ fn main() {
         std::env::set_var("SENSOR_BRIDGE_APP_NAME", "sensor-bridge");
     }
 
+    // In headless mode, only the sync engine runs, without the Tauri window and tray icon
+    let is_headless = headless::is_requested();
+
     // Initialize the logger
-    env_logger::init();
+    if is_headless {
+        headless::init_logger();
+    } else {
+        env_logger::init();
+    }
+
+    // Migrate the sensor ids of the config, before any command or sync reads it
+    linux_amdgpu::migrate_legacy_sensor_ids();
+
+    // Run a command of the command line interface instead of the user interface, if specified
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(exit_code) = cli::run(&args) {
+        std::process::exit(exit_code);
+    }
 
     // Cleanup cache dir
     fs::remove_dir_all(sensor_core::get_cache_base_dir()).unwrap_or_default();
     fs::create_dir_all(sensor_core::get_cache_base_dir()).unwrap();
 
     // Request root shell
-    let root_shell = Arc::new(Mutex::new(RootShell::new()));
+    // A headless service has no session to ask for the password, thus root sensors are not read
+    let root_shell = Arc::new(Mutex::new(if is_headless {
+        None
+    } else {
+        RootShell::new()
+    }));
 
     // Create the port handle map wrapped in a mutex
     let app_state_network_handles = Mutex::new(HashMap::new());
 
-    // Read the static sensor values
-    let static_sensor_values = Arc::new(sensor::read_static_sensor_values(&root_shell));
+    // Register all sensor providers, except the ones disabled by the user
+    let app_config = config::read_from_app_config();
+    let sensor_provider_registry =
+        SensorProviderRegistry::new(&root_shell, &app_config.disabled_sensor_providers);
 
-    // Create sensor history vector
-    let sensor_value_history = Arc::new(Mutex::new(Vec::with_capacity(SENSOR_VALUE_HISTORY_SIZE)));
+    // Start the sensor sampler, that owns the sensor value history
+    let sensor_sampler = Arc::new(SensorSampler::new(sensor_provider_registry));
+    sensor_sampler.set_derived_sensors(derived_sensor::create_all(&app_config.derived_sensors));
+    sensor_sampler.set_sensor_transforms(SensorTransforms::new(&app_config.sensor_transforms));
+    sensor_sampler.set_sensor_overrides(app_config.sensor_overrides.clone());
+
+    // Restore and persist the sensor value history, if enabled, before the first sample is taken
+    history_store::start(&sensor_sampler, &app_config.history_store);
+    sensor_sampler.start();
+
+    // Expose the sampled sensor values to other applications, if enabled
+    http_server::start(&sensor_sampler, &app_config.http_server);
+    mqtt::start(&sensor_sampler, &app_config.mqtt);
+
+    // Evaluate the alert rules against each sample
+    alerts::start(&sensor_sampler, &app_config.alert_rules);
 
     // Load the config for all ports
     // If the port is active, start a sync thread
@@ -89,17 +200,18 @@ fn main() {
         .values()
         .filter(|net_config| net_config.active)
         .for_each(|net_config| {
-            let thread_handle = start_port_thread(
-                &sensor_value_history,
-                &static_sensor_values,
-                net_config.clone(),
-            );
+            let thread_handle = start_port_thread(&sensor_sampler, net_config.clone());
             app_state_network_handles
                 .lock()
                 .unwrap()
                 .insert(net_config.id.clone(), thread_handle);
         });
 
+    if is_headless {
+        headless::run(&app_state_network_handles);
+        return;
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
@@ -107,8 +219,9 @@ fn main() {
         .manage(AppState {
             port_handle: app_state_network_handles,
             root_shell: root_shell.clone(),
-            static_sensor_values,
-            sensor_value_history,
+            sensor_sampler,
+            recorder: Recorder::default(),
+            ui_sensor_ids: Mutex::new(HashSet::new()),
         })
         .setup(|app| {
             let title = format!("Sensor Bridge {}", env!("CARGO_PKG_VERSION"));
@@ -120,8 +233,15 @@ fn main() {
         })
         .invoke_handler(tauri::generate_handler![
             get_sensor_values,
+            get_sensor_providers,
+            set_sensor_provider_enabled,
+            start_recording,
+            stop_recording,
+            reset_sensor_sessions,
             get_app_config,
             create_network_device_config,
+            create_network_device_config_from_discovery,
+            discover_network_devices,
             get_network_device_config,
             remove_network_device_config,
             save_app_config,
@@ -143,6 +263,12 @@ fn main() {
             if let tauri::WindowEvent::CloseRequested { api, .. } = event {
                 let _ = window.hide();
                 api.prevent_close();
+
+                // While the main window is hidden, only displays and the preview require sensors
+                if window.label() == "main" {
+                    let app_state = window.app_handle().state::<AppState>();
+                    app_state.sensor_sampler.unregister_consumer(UI_CONSUMER_ID);
+                }
             }
         })
         .run(tauri::generate_context!())
@@ -151,21 +277,33 @@ fn main() {
 
 fn build_tray_icon(app: &mut App) -> Result<(), Box<dyn Error>> {
     let show_i = MenuItem::with_id(app, "show", "Show", true, None::<&str>)?;
+    let record_i = MenuItem::with_id(app, "record", "Start recording", true, None::<&str>)?;
+    let reset_sessions_i = MenuItem::with_id(
+        app,
+        "reset_sessions",
+        "Reset session min/max",
+        true,
+        None::<&str>,
+    )?;
     let quit_i = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-    let menu = Menu::with_items(app, &[&show_i, &quit_i])?;
+    let menu = Menu::with_items(app, &[&show_i, &record_i, &reset_sessions_i, &quit_i])?;
     let _ = TrayIconBuilder::new()
         .icon(app.default_window_icon().unwrap().clone())
         .menu(&menu)
-        .on_menu_event(|app, event| match event.id.as_ref() {
+        .on_menu_event(move |app, event| match event.id.as_ref() {
             "quit" => {
                 app.exit(0);
             }
             "show" => {
-                // in this example, let's show and focus the main window when the tray is clicked
-                if let Some(window) = app.get_webview_window("main") {
-                    let _ = window.show();
-                    let _ = window.set_focus();
-                }
+                show_main_window(app);
+            }
+            "record" => {
+                toggle_recording(app, &record_i);
+            }
+            "reset_sessions" => {
+                app.state::<AppState>()
+                    .sensor_sampler
+                    .reset_sensor_sessions();
             }
             _ => {
                 println!("menu item {:?} not handled", event.id);
@@ -177,12 +315,7 @@ fn build_tray_icon(app: &mut App) -> Result<(), Box<dyn Error>> {
                 ..
             } => {
                 println!("left click pressed and released");
-                // in this example, let's show and focus the main window when the tray is clicked
-                let app = tray.app_handle();
-                if let Some(window) = app.get_webview_window("main") {
-                    let _ = window.show();
-                    let _ = window.set_focus();
-                }
+                show_main_window(tray.app_handle());
             }
             _ => {
                 println!("unhandled event {event:?}");
@@ -192,23 +325,170 @@ fn build_tray_icon(app: &mut App) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Shows and focuses the main window.
+/// While the main window is visible, the designer requires the sensors it shows.
+fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+
+    register_ui_consumer(&app.state::<AppState>());
+}
+
+/// Requires the sensors shown by the designer from the sampler
+fn register_ui_consumer(app_state: &AppState) {
+    let sensor_ids = app_state.ui_sensor_ids.lock().unwrap().clone();
+    app_state
+        .sensor_sampler
+        .register_consumer(UI_CONSUMER_ID, SensorDemand::Only(sensor_ids));
+}
+
+/// Replaces the sensors shown by the designer, e.g. when a device was opened
+fn set_ui_sensor_ids(app_state: &AppState, sensor_ids: HashSet<String>) {
+    *app_state.ui_sensor_ids.lock().unwrap() = sensor_ids;
+    register_ui_consumer(app_state);
+}
+
+/// Adds a sensor previewed by the designer, e.g. of an element that is not saved yet
+fn add_ui_sensor_id(app_state: &AppState, sensor_id: &str) {
+    let is_new_sensor = app_state
+        .ui_sensor_ids
+        .lock()
+        .unwrap()
+        .insert(sensor_id.to_string());
+    if is_new_sensor {
+        register_ui_consumer(app_state);
+    }
+}
+
+/// Returns the latest value of a sensor previewed by the designer and requires it from the sampler.
+/// Until the sensor is sampled, its last read value is returned.
+fn get_ui_sensor_value(app_state: &AppState, sensor_id: &str) -> Option<SensorValue> {
+    add_ui_sensor_id(app_state, sensor_id);
+
+    let find_sensor_value = |sensor_values: Vec<SensorValue>| {
+        sensor_values
+            .into_iter()
+            .find(|sensor_value| sensor_value.id == sensor_id)
+    };
+    find_sensor_value(app_state.sensor_sampler.latest())
+        .or_else(|| find_sensor_value(app_state.sensor_sampler.list_sensors()))
+}
+
+/// Starts a recording of all sensors to the documents directory, or stops the running recording.
+fn toggle_recording(app: &AppHandle, record_item: &MenuItem<Wry>) {
+    let app_state = app.state::<AppState>();
+
+    if app_state.recorder.is_recording() {
+        match app_state.recorder.stop() {
+            Ok(summary) => info!("Recording saved to {}", summary.file_path),
+            Err(err) => error!("{err}"),
+        }
+    } else {
+        let recording_config = RecordingConfig::new(&recording::get_default_recording_path());
+        if let Err(err) = app_state
+            .recorder
+            .start(&app_state.sensor_sampler, recording_config)
+        {
+            error!("{err}");
+        }
+    }
+
+    let text = if app_state.recorder.is_recording() {
+        "Stop recording"
+    } else {
+        "Start recording"
+    };
+    let _ = record_item.set_text(text);
+}
+
+/// Starts recording the sensor values to a csv or json lines file.
+#[tauri::command]
+async fn start_recording(
+    app_state: State<'_, AppState>,
+    recording_config: RecordingConfig,
+) -> Result<(), String> {
+    app_state
+        .recorder
+        .start(&app_state.sensor_sampler, recording_config)
+}
+
+/// Stops the running recording and returns its summary with min, avg, max and p95 per sensor.
+#[tauri::command]
+async fn stop_recording(app_state: State<'_, AppState>) -> Result<String, String> {
+    let summary = app_state.recorder.stop()?;
+    serde_json::to_string(&summary).map_err(|err| err.to_string())
+}
+
+/// Resets the session min and max of all transformed sensors.
+#[tauri::command]
+async fn reset_sensor_sessions(app_state: State<'_, AppState>) -> Result<(), ()> {
+    app_state.sensor_sampler.reset_sensor_sessions();
+    Ok(())
+}
+
+/// Returns all available sensor values, as last read from their provider.
+/// Only providers that were not read yet are read.
 #[tauri::command]
 async fn get_sensor_values(app_state: State<'_, AppState>) -> Result<String, ()> {
-    let sensor_values = sensor::read_all_sensor_values(
-        &app_state.sensor_value_history,
-        &app_state.static_sensor_values,
-    );
+    let sensor_values = app_state.sensor_sampler.list_sensors();
     Ok(serde_json::to_string(&sensor_values).unwrap())
 }
 
+/// Returns all sensor providers with their interval and whether they are enabled.
+#[tauri::command]
+async fn get_sensor_providers(app_state: State<'_, AppState>) -> Result<String, String> {
+    let provider_infos = app_state.sensor_sampler.get_provider_infos();
+    serde_json::to_string(&provider_infos).map_err(|err| err.to_string())
+}
+
+/// Enables or disables the specified sensor provider and saves it to the app config.
+#[tauri::command]
+async fn set_sensor_provider_enabled(
+    app_state: State<'_, AppState>,
+    provider_name: String,
+    enabled: bool,
+) -> Result<(), String> {
+    if !app_state
+        .sensor_sampler
+        .set_provider_enabled(&provider_name, enabled)
+    {
+        return Err(format!("Sensor provider '{provider_name}' not found"));
+    }
+
+    config::set_sensor_provider_enabled(&provider_name, enabled);
+
+    Ok(())
+}
+
 #[tauri::command]
 async fn create_network_device_config() -> Result<String, ()> {
     let new_network_device_config = config::create_network_device_config();
     Ok(new_network_device_config.id)
 }
 
+/// Creates a new network device config from a device, that was found by the discovery.
+#[tauri::command]
+async fn create_network_device_config_from_discovery(
+    discovered_device: DiscoveredDevice,
+) -> Result<String, ()> {
+    let new_network_device_config = config::create_network_device_config_from(&discovered_device);
+    Ok(new_network_device_config.id)
+}
+
+/// Searches the local network for sensor-display instances.
 #[tauri::command]
-async fn get_network_device_config(network_device_id: String) -> Result<String, String> {
+async fn discover_network_devices() -> Result<String, String> {
+    let discovered_devices = discovery::discover();
+    serde_json::to_string(&discovered_devices).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+async fn get_network_device_config(
+    app_state: State<'_, AppState>,
+    network_device_id: String,
+) -> Result<String, String> {
     let network_device_config = match config::read(&network_device_id) {
         Some(config) => config,
         None => {
@@ -216,6 +496,12 @@ async fn get_network_device_config(network_device_id: String) -> Result<String,
         }
     };
 
+    // The designer shows the layout of the opened device
+    set_ui_sensor_ids(
+        &app_state,
+        sensor::get_referenced_sensor_ids(&network_device_config.display_config),
+    );
+
     serde_json::to_string(&network_device_config).map_err(|err| err.to_string())
 }
 
@@ -239,6 +525,11 @@ async fn save_app_config(
     id: String,
     name: String,
     address: String,
+    port: u16,
+    push_rate_ms: u64,
+    delta_frames: bool,
+    auto_scale_units: bool,
+    graph_window_seconds: HashMap<String, u64>,
     display_config: String,
 ) -> Result<(), String> {
     let mut network_device_config = match config::read(&id) {
@@ -250,12 +541,21 @@ async fn save_app_config(
 
     network_device_config.name = name;
     network_device_config.address = address;
+    network_device_config.port = port;
+    network_device_config.push_rate_ms = push_rate_ms;
+    network_device_config.delta_frames = delta_frames;
+    network_device_config.auto_scale_units = auto_scale_units;
+    network_device_config.graph_window_seconds = graph_window_seconds;
     network_device_config.display_config = serde_json::from_str(display_config.as_str()).unwrap();
 
     verify_config(&network_device_config)?;
 
     config::write(&network_device_config);
 
+    set_ui_sensor_ids(
+        &app_state,
+        sensor::get_referenced_sensor_ids(&network_device_config.display_config),
+    );
     reconnect_displays(app_state).await;
 
     Ok(())
@@ -282,11 +582,7 @@ async fn enable_display(
 
     // Start the sync for the port and hand
     // This creates a new thread and returns a handle to it
-    let thread_handle = start_port_thread(
-        &app_state.sensor_value_history,
-        &app_state.static_sensor_values,
-        network_device_config,
-    );
+    let thread_handle = start_port_thread(&app_state.sensor_sampler, network_device_config);
 
     // Add the port handle to the app state
     app_state
@@ -325,6 +621,7 @@ async fn disable_display(
 /// If the live preview is enabled, it will be disabled and vice versa.
 #[tauri::command]
 async fn show_lcd_live_preview(
+    app_state: State<'_, AppState>,
     app_handle: AppHandle,
     network_device_id: String,
 ) -> Result<(), String> {
@@ -344,7 +641,7 @@ async fn show_lcd_live_preview(
     }
 
     // Open a new lcd preview window
-    lcd_preview::show(app_handle, network_device_config);
+    lcd_preview::show(app_handle, &app_state.sensor_sampler, network_device_config);
 
     Ok(())
 }
@@ -362,8 +659,6 @@ async fn get_lcd_preview_image(
             return Err("Config not found".to_string());
         }
     };
-    let display_config = network_device_config.display_config;
-
     // If the window is not visible, return an empty string
     let maybe_window = app_handle.get_webview_window(lcd_preview::WINDOW_LABEL);
     if let Some(window) = maybe_window {
@@ -373,12 +668,8 @@ async fn get_lcd_preview_image(
         }
     }
 
-    lcd_preview::render(
-        &app_state.sensor_value_history,
-        &app_state.static_sensor_values,
-        display_config,
-    )
-    .map_err(|_| "Error rendering preview image".to_string())
+    lcd_preview::render(&app_state.sensor_sampler, network_device_config)
+        .map_err(|_| "Error rendering preview image".to_string())
 }
 
 #[tauri::command]
@@ -387,16 +678,19 @@ async fn get_text_preview_image(
     image_width: u32,
     image_height: u32,
     text_config: TextConfig,
+    auto_scale_units: bool,
 ) -> Result<String, ()> {
-    let sensor_values = &app_state.sensor_value_history.lock().ignore_poison()[0];
-    let sensor_id = &text_config.sensor_id;
-
-    let sensor_value = sensor_values
-        .iter()
-        .find(|sensor_value| sensor_value.id.eq(sensor_id));
+    let mut sensor_value = get_ui_sensor_value(&app_state, &text_config.sensor_id);
+    if auto_scale_units {
+        sensor_value = sensor_value.map(text::auto_scale_unit);
+    }
 
-    let text_image_data =
-        text::render_preview(sensor_value, image_width, image_height, &text_config);
+    let text_image_data = text::render_preview(
+        sensor_value.as_ref(),
+        image_width,
+        image_height,
+        &text_config,
+    );
 
     let engine = base64::engine::general_purpose::STANDARD;
     Ok(base64::Engine::encode(&engine, text_image_data))
@@ -406,17 +700,20 @@ async fn get_text_preview_image(
 async fn get_graph_preview_image(
     app_state: State<'_, AppState>,
     mut graph_config: GraphConfig,
+    window_seconds: Option<u64>,
 ) -> Result<String, ()> {
     let sensor_id = &graph_config.sensor_id;
-
-    graph_config.sensor_values = sensor_core::extract_value_sequence(
-        app_state
-            .sensor_value_history
-            .lock()
-            .ignore_poison()
-            .deref(),
-        sensor_id,
-    );
+    add_ui_sensor_id(&app_state, sensor_id);
+
+    // Only the values of the previewed sensor within the time window are needed
+    let sensor_windows =
+        HashMap::from([(sensor_id.clone(), window_seconds.map(Duration::from_secs))]);
+    let sensor_value_history = app_state
+        .sensor_sampler
+        .history()
+        .get_sensor_values_within(&sensor_windows);
+    graph_config.sensor_values =
+        sensor_core::extract_value_sequence(&sensor_value_history, sensor_id);
 
     let graph_data = graph_renderer::render(&graph_config);
     let engine = base64::engine::general_purpose::STANDARD;
@@ -429,15 +726,10 @@ async fn get_conditional_image_preview_image(
     element_id: String,
     mut conditional_image_config: ConditionalImageConfig,
 ) -> Result<String, ()> {
-    let sensor_values = &app_state.sensor_value_history.lock().ignore_poison()[0];
     let sensor_id = &conditional_image_config.sensor_id;
+    let sensor_value = get_ui_sensor_value(&app_state, sensor_id);
 
-    // Filter sensor values for provided sensor id
-    let sensor_value = sensor_values
-        .iter()
-        .find(|sensor_value| sensor_value.id.eq(sensor_id));
-
-    let (value, sensor_type): (&str, &SensorType) = match sensor_value {
+    let (value, sensor_type): (&str, &SensorType) = match &sensor_value {
         Some(sensor_value) => (&sensor_value.value, &sensor_value.sensor_type),
         _ => ("N/A", &SensorType::Text),
     };
@@ -463,26 +755,19 @@ async fn get_conditional_image_preview_image(
 }
 
 #[tauri::command]
-async fn verify_network_address(address: String) -> bool {
-    net_port::verify_network_address(&address)
+async fn verify_network_address(address: String, port: u16) -> bool {
+    net_port::verify_network_address(&address, port)
 }
 
 #[tauri::command]
-async fn export_config(file_path: String) -> Result<(), ()> {
-    export_import::export_configuration(file_path);
-    Ok(())
+async fn export_config(file_path: String) -> Result<(), String> {
+    export_import::export_configuration(file_path)
 }
 
 #[tauri::command]
-async fn import_config(file_path: String) -> Result<(), tauri::Error> {
-    let app_config = export_import::import_configuration(file_path);
-
-    if let Ok(app_config) = app_config {
-        app_config.network_devices.values().for_each(config::write);
-        Ok(())
-    } else {
-        Err(app_config.err().unwrap().into())
-    }
+async fn import_config(file_path: String) -> Result<(), String> {
+    export_import::import_configuration(file_path)?;
+    Ok(())
 }
 
 #[tauri::command]
@@ -504,14 +789,12 @@ async fn restart_app(app_handle: AppHandle) -> Result<(), ()> {
 /// Starts the sync thread for the specified port
 /// Returns a handle to the thread
 fn start_port_thread(
-    sensor_value_history: &Arc<Mutex<Vec<Vec<SensorValue>>>>,
-    static_sensor_values: &Arc<Vec<SensorValue>>,
+    sensor_sampler: &Arc<SensorSampler>,
     port_config: NetworkDeviceConfig,
 ) -> ThreadHandle {
     let port_running_state_handle = Arc::new(Mutex::new(true));
     let port_handle = net_port::start_sync(
-        sensor_value_history,
-        static_sensor_values,
+        sensor_sampler,
         port_config,
         port_running_state_handle.clone(),
     );
@@ -540,6 +823,28 @@ fn stop_sync_thread(
 
 /// Verifies the config for the specified network device
 fn verify_config(config: &NetworkDeviceConfig) -> Result<(), String> {
+    // Verify that the push rate is within the allowed bounds
+    if !(config::MIN_PUSH_RATE_MS..=config::MAX_PUSH_RATE_MS).contains(&config.push_rate_ms) {
+        return Err(format!(
+            "Update interval must be between {}ms and {}ms.",
+            config::MIN_PUSH_RATE_MS,
+            config::MAX_PUSH_RATE_MS
+        ));
+    }
+
+    // Port 0 would let the os pick a random port, which is never the port of the display
+    if config.port == 0 {
+        return Err("Port must be between 1 and 65535.".to_string());
+    }
+
+    // Verify that the layout is not resent more often than the values are pushed
+    if config.resync_interval_ms < config.push_rate_ms {
+        return Err(format!(
+            "Resync interval must be at least the update interval of {}ms.",
+            config.push_rate_ms
+        ));
+    }
+
     // Verify all static image path
     for element in config.display_config.elements.iter() {
         // Ensure that the image file exists
@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use sensor_core::{SensorType, SensorValue};
+
+use crate::units;
+
+/// Prefix of all exported metric names
+const METRIC_PREFIX: &str = "sensor_bridge";
+
+/// A metric family, all sensors with the same base unit are exported to the same family
+struct MetricFamily {
+    name: &'static str,
+    help: &'static str,
+    base_unit: &'static str,
+    /// Factor to convert a value in the base unit to the unit of the metric
+    factor: f64,
+}
+
+/// All metric families, sensors with an unknown unit are exported to the fallback family
+const METRIC_FAMILIES: &[MetricFamily] = &[
+    MetricFamily {
+        name: "temperature_celsius",
+        help: "Temperature in degrees celsius",
+        base_unit: "°C",
+        factor: 1.0,
+    },
+    MetricFamily {
+        name: "ratio",
+        help: "Load or utilization as ratio between 0 and 1",
+        base_unit: "%",
+        factor: 0.01,
+    },
+    MetricFamily {
+        name: "bytes",
+        help: "Size in bytes",
+        base_unit: "B",
+        factor: 1.0,
+    },
+    MetricFamily {
+        name: "bytes_per_second",
+        help: "Transfer rate in bytes per second",
+        base_unit: "B/s",
+        factor: 1.0,
+    },
+    MetricFamily {
+        name: "frequency_hertz",
+        help: "Clock frequency in hertz",
+        base_unit: "Hz",
+        factor: 1.0,
+    },
+    MetricFamily {
+        name: "fan_speed_rpm",
+        help: "Fan speed in revolutions per minute",
+        base_unit: "RPM",
+        factor: 1.0,
+    },
+    MetricFamily {
+        name: "voltage_volts",
+        help: "Voltage in volts",
+        base_unit: "V",
+        factor: 1.0,
+    },
+    MetricFamily {
+        name: "current_amperes",
+        help: "Current in amperes",
+        base_unit: "A",
+        factor: 1.0,
+    },
+    MetricFamily {
+        name: "power_watts",
+        help: "Power in watts",
+        base_unit: "W",
+        factor: 1.0,
+    },
+    MetricFamily {
+        name: "energy_joules",
+        help: "Energy in joules",
+        base_unit: "J",
+        factor: 1.0,
+    },
+    MetricFamily {
+        name: "duration_seconds",
+        help: "Duration in seconds",
+        base_unit: "s",
+        factor: 1.0,
+    },
+];
+
+/// Family of all sensors with an unknown unit, the unit is exported as label
+const FALLBACK_METRIC_FAMILY: MetricFamily = MetricFamily {
+    name: "value",
+    help: "Sensor value without a known unit",
+    base_unit: "",
+    factor: 1.0,
+};
+
+/// Renders all numeric sensor values as gauges in the Prometheus text format.
+/// Text sensors and values that are not a number are skipped.
+pub fn render(sensor_values: &[SensorValue]) -> String {
+    // Samples grouped by metric family, sorted for a stable output
+    let mut metric_samples: BTreeMap<&'static str, (&MetricFamily, Vec<String>)> = BTreeMap::new();
+
+    for sensor_value in sensor_values {
+        if !matches!(sensor_value.sensor_type, SensorType::Number) {
+            continue;
+        }
+        let value: f64 = match sensor_value.value.trim().parse() {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+
+        let (metric_family, value) = match units::to_base_unit(value, &sensor_value.unit) {
+            Some((value, base_unit)) => match get_metric_family(base_unit) {
+                Some(metric_family) => (metric_family, value * metric_family.factor),
+                None => (&FALLBACK_METRIC_FAMILY, value),
+            },
+            None => (&FALLBACK_METRIC_FAMILY, value),
+        };
+        let mut labels = format!(
+            "sensor_id=\"{}\",label=\"{}\"",
+            escape_label_value(&sensor_value.id),
+            escape_label_value(&sensor_value.label)
+        );
+        if metric_family.base_unit.is_empty() {
+            let _ = write!(
+                labels,
+                ",unit=\"{}\"",
+                escape_label_value(&sensor_value.unit)
+            );
+        }
+
+        metric_samples
+            .entry(metric_family.name)
+            .or_insert_with(|| (metric_family, vec![]))
+            .1
+            .push(format!(
+                "{METRIC_PREFIX}_{}{{{labels}}} {value}",
+                metric_family.name
+            ));
+    }
+
+    let mut metrics = String::new();
+    for (metric_family, samples) in metric_samples.values() {
+        let name = format!("{METRIC_PREFIX}_{}", metric_family.name);
+        let _ = writeln!(metrics, "# HELP {name} {}", metric_family.help);
+        let _ = writeln!(metrics, "# TYPE {name} gauge");
+        for sample in samples {
+            let _ = writeln!(metrics, "{sample}");
+        }
+    }
+
+    metrics
+}
+
+/// Returns the metric family of the base unit
+fn get_metric_family(base_unit: &str) -> Option<&'static MetricFamily> {
+    METRIC_FAMILIES
+        .iter()
+        .find(|metric_family| metric_family.base_unit == base_unit)
+}
+
+/// Escapes backslashes, double quotes and line feeds of a label value
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
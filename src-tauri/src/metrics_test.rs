@@ -0,0 +1,61 @@
+use assertor::{assert_that, EqualityAssertion};
+use sensor_core::{SensorType, SensorValue};
+
+use crate::metrics;
+
+fn create_sensor_value(id: &str, label: &str, value: &str, unit: &str) -> SensorValue {
+    SensorValue {
+        id: id.to_string(),
+        value: value.to_string(),
+        unit: unit.to_string(),
+        label: label.to_string(),
+        sensor_type: SensorType::Number,
+    }
+}
+
+#[test]
+fn test_render_converts_to_base_unit() {
+    // GIVEN are sensor values in pretty units
+    let sensor_values = vec![
+        create_sensor_value("network_rx_eth0", "eth0 download rate", "2.00", "MB/s"),
+        create_sensor_value("cpu_load_total", "Total CPU load", "25.00", "%"),
+    ];
+
+    // WHEN rendering the metrics
+    let metrics = metrics::render(&sensor_values);
+
+    // THEN the values should be exported in their base unit, grouped by metric family
+    assert_that!(metrics).is_equal_to(
+        "# HELP sensor_bridge_bytes_per_second Transfer rate in bytes per second
+# TYPE sensor_bridge_bytes_per_second gauge
+sensor_bridge_bytes_per_second{sensor_id=\"network_rx_eth0\",label=\"eth0 download rate\"} 2097152
+# HELP sensor_bridge_ratio Load or utilization as ratio between 0 and 1
+# TYPE sensor_bridge_ratio gauge
+sensor_bridge_ratio{sensor_id=\"cpu_load_total\",label=\"Total CPU load\"} 0.25
+"
+        .to_string(),
+    );
+}
+
+#[test]
+fn test_render_skips_text_and_keeps_unknown_units() {
+    // GIVEN are a text sensor and a sensor with an unknown unit and a quoted label
+    let mut text_sensor = create_sensor_value("system_uptime", "System uptime", "01:02:03", "");
+    text_sensor.sensor_type = SensorType::Text;
+    let sensor_values = vec![
+        text_sensor,
+        create_sensor_value("custom", "The \"custom\" sensor", "7", "ops"),
+    ];
+
+    // WHEN rendering the metrics
+    let metrics = metrics::render(&sensor_values);
+
+    // THEN the text sensor should be skipped and the unit exported as label
+    assert_that!(metrics).is_equal_to(
+        "# HELP sensor_bridge_value Sensor value without a known unit
+# TYPE sensor_bridge_value gauge
+sensor_bridge_value{sensor_id=\"custom\",label=\"The \\\"custom\\\" sensor\",unit=\"ops\"} 7
+"
+        .to_string(),
+    );
+}
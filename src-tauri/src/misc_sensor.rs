@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use sensor_core::{SensorType, SensorValue};
 
 use crate::sensor;
@@ -8,6 +10,14 @@ impl sensor::SensorProvider for SystemSensor {
     fn get_name(&self) -> String {
         "System".to_string()
     }
+
+    fn get_preferred_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn read(&self) -> Vec<SensorValue> {
+        get_sensor_values()
+    }
 }
 
 pub fn get_sensor_values() -> Vec<SensorValue> {
@@ -0,0 +1,297 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{error, info, warn};
+use rumqttc::{Client, Event, LastWill, MqttOptions, Packet, QoS};
+use sensor_core::{SensorType, SensorValue};
+use serde_json::{json, Map, Value};
+
+use crate::config::{MqttConfig, MqttPayloadFormat};
+use crate::sensor_sampler::{SensorDemand, SensorSampler};
+use crate::units;
+
+/// Consumer id of the mqtt publisher at the sensor sampler
+const CONSUMER_ID: &str = "mqtt";
+
+/// Duration to wait before the connection to the broker is retried
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Payload of the availability topic while sensor-bridge is connected
+const ONLINE_PAYLOAD: &str = "online";
+
+/// Payload of the availability topic, sent by the broker if the connection was lost
+const OFFLINE_PAYLOAD: &str = "offline";
+
+/// Starts publishing the sensor values to the mqtt broker in a new thread, if it is enabled.
+/// The connection is retried until it succeeds, the publishing runs until the application exits.
+pub fn start(sensor_sampler: &Arc<SensorSampler>, mqtt_config: &MqttConfig) {
+    if !mqtt_config.enabled {
+        return;
+    }
+
+    let sensor_demand = if mqtt_config.sensor_ids.is_empty() {
+        SensorDemand::All
+    } else {
+        SensorDemand::Only(mqtt_config.sensor_ids.iter().cloned().collect())
+    };
+    sensor_sampler.register_consumer(CONSUMER_ID, sensor_demand);
+
+    let mut mqtt_options =
+        MqttOptions::new(&mqtt_config.client_id, &mqtt_config.host, mqtt_config.port);
+    mqtt_options.set_keep_alive(Duration::from_secs(30));
+    mqtt_options.set_last_will(LastWill::new(
+        get_availability_topic(mqtt_config),
+        OFFLINE_PAYLOAD,
+        QoS::AtLeastOnce,
+        true,
+    ));
+    if !mqtt_config.username.is_empty() {
+        mqtt_options.set_credentials(&mqtt_config.username, &mqtt_config.password);
+    }
+
+    let (client, mut connection) = Client::new(mqtt_options, 100);
+
+    // Set on every (re)connect, so that the availability and discovery configs are published again
+    let is_connected = Arc::new(AtomicBool::new(false));
+    let is_reconnected = Arc::new(AtomicBool::new(false));
+
+    let connection_is_connected = is_connected.clone();
+    let connection_is_reconnected = is_reconnected.clone();
+    let address = format!("{}:{}", mqtt_config.host, mqtt_config.port);
+    thread::spawn(move || {
+        // Iterating the connection drives the network io and reconnects after errors
+        for notification in connection.iter() {
+            match notification {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    info!("Connected to mqtt broker {address}");
+                    connection_is_connected.store(true, Ordering::Relaxed);
+                    connection_is_reconnected.store(true, Ordering::Relaxed);
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    if connection_is_connected.swap(false, Ordering::Relaxed) {
+                        warn!("Connection to mqtt broker {address} lost: {err}");
+                    } else {
+                        error!("Could not connect to mqtt broker {address}: {err}");
+                    }
+                    thread::sleep(RECONNECT_DELAY);
+                }
+            }
+        }
+    });
+
+    let sensor_sampler = sensor_sampler.clone();
+    let mqtt_config = mqtt_config.clone();
+    thread::spawn(move || {
+        let mut announced_sensor_ids: HashSet<String> = HashSet::new();
+
+        loop {
+            let start_time = Instant::now();
+
+            if is_reconnected.swap(false, Ordering::Relaxed) {
+                announced_sensor_ids.clear();
+                publish(
+                    &client,
+                    get_availability_topic(&mqtt_config),
+                    true,
+                    ONLINE_PAYLOAD.to_string(),
+                );
+            }
+
+            if is_connected.load(Ordering::Relaxed) {
+                let sensor_values = get_published_sensor_values(&sensor_sampler, &mqtt_config);
+
+                if mqtt_config.home_assistant_discovery {
+                    for sensor_value in &sensor_values {
+                        if announced_sensor_ids.insert(sensor_value.id.clone()) {
+                            let (topic, payload) =
+                                create_discovery_config(&mqtt_config, sensor_value);
+                            publish(&client, topic, true, payload.to_string());
+                        }
+                    }
+                }
+
+                for (topic, payload) in create_state_messages(&mqtt_config, &sensor_values) {
+                    publish(&client, topic, false, payload);
+                }
+            }
+
+            // Keep a constant publish rate, by subtracting the time it took to publish
+            let processing_duration = Instant::now().duration_since(start_time);
+            thread::sleep(
+                mqtt_config
+                    .publish_rate()
+                    .saturating_sub(processing_duration),
+            );
+        }
+    });
+}
+
+/// Returns the latest sampled values of the sensors, that are selected for publishing
+fn get_published_sensor_values(
+    sensor_sampler: &SensorSampler,
+    mqtt_config: &MqttConfig,
+) -> Vec<SensorValue> {
+    sensor_sampler
+        .latest()
+        .into_iter()
+        .filter(|sensor_value| {
+            mqtt_config.sensor_ids.is_empty() || mqtt_config.sensor_ids.contains(&sensor_value.id)
+        })
+        .collect()
+}
+
+/// Publishes the payload, a full request queue is logged but not an error
+fn publish(client: &Client, topic: String, retain: bool, payload: String) {
+    if let Err(err) = client.try_publish(&topic, QoS::AtMostOnce, retain, payload) {
+        warn!("Could not publish to mqtt topic {topic}: {err}");
+    }
+}
+
+/// Returns the topic, that signals whether sensor-bridge is connected to the broker
+fn get_availability_topic(mqtt_config: &MqttConfig) -> String {
+    format!("{}/status", mqtt_config.topic_prefix)
+}
+
+/// Returns the topic to which the sensor value is published
+fn get_state_topic(mqtt_config: &MqttConfig, sensor_id: &str) -> String {
+    match mqtt_config.payload_format {
+        MqttPayloadFormat::TopicPerSensor => {
+            format!("{}/{}", mqtt_config.topic_prefix, get_object_id(sensor_id))
+        }
+        MqttPayloadFormat::JsonState => format!("{}/state", mqtt_config.topic_prefix),
+    }
+}
+
+/// Returns the sensor id, reduced to the characters that are allowed in topics and discovery ids
+pub fn get_object_id(sensor_id: &str) -> String {
+    sensor_id
+        .chars()
+        .map(|char| {
+            if char.is_ascii_alphanumeric() || char == '_' || char == '-' {
+                char.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Returns the published value of the sensor.
+/// Numbers are converted to their base unit, so that the unit does not change between messages.
+fn get_published_value(sensor_value: &SensorValue) -> (Value, String) {
+    if matches!(sensor_value.sensor_type, SensorType::Number) {
+        if let Ok(value) = sensor_value.value.trim().parse::<f64>() {
+            return match units::to_base_unit(value, &sensor_value.unit) {
+                Some((value, base_unit)) => (json!(value), base_unit.to_string()),
+                None => (json!(value), sensor_value.unit.clone()),
+            };
+        }
+    }
+
+    (json!(sensor_value.value), sensor_value.unit.clone())
+}
+
+/// Creates the messages with the sensor values, as topic and payload
+pub fn create_state_messages(
+    mqtt_config: &MqttConfig,
+    sensor_values: &[SensorValue],
+) -> Vec<(String, String)> {
+    match mqtt_config.payload_format {
+        MqttPayloadFormat::TopicPerSensor => sensor_values
+            .iter()
+            .map(|sensor_value| {
+                let payload = match get_published_value(sensor_value).0 {
+                    Value::String(value) => value,
+                    value => value.to_string(),
+                };
+                (get_state_topic(mqtt_config, &sensor_value.id), payload)
+            })
+            .collect(),
+        MqttPayloadFormat::JsonState => {
+            let state: Map<String, Value> = sensor_values
+                .iter()
+                .map(|sensor_value| {
+                    (
+                        get_object_id(&sensor_value.id),
+                        get_published_value(sensor_value).0,
+                    )
+                })
+                .collect();
+            vec![(
+                format!("{}/state", mqtt_config.topic_prefix),
+                Value::Object(state).to_string(),
+            )]
+        }
+    }
+}
+
+/// Creates the Home Assistant discovery config of the sensor, as topic and payload
+pub fn create_discovery_config(
+    mqtt_config: &MqttConfig,
+    sensor_value: &SensorValue,
+) -> (String, Value) {
+    let node_id = get_object_id(&mqtt_config.topic_prefix);
+    let object_id = get_object_id(&sensor_value.id);
+    let (_, unit) = get_published_value(sensor_value);
+
+    let mut config = json!({
+        "name": sensor_value.label,
+        "unique_id": format!("{node_id}_{object_id}"),
+        "object_id": format!("{node_id}_{object_id}"),
+        "state_topic": get_state_topic(mqtt_config, &sensor_value.id),
+        "availability_topic": get_availability_topic(mqtt_config),
+        "device": {
+            "identifiers": [node_id],
+            "name": get_device_name(),
+            "manufacturer": "sensor-bridge",
+        },
+    });
+
+    if mqtt_config.payload_format == MqttPayloadFormat::JsonState {
+        config["value_template"] = json!(format!("{{{{ value_json['{object_id}'] }}}}"));
+    }
+
+    if matches!(sensor_value.sensor_type, SensorType::Number) {
+        config["state_class"] = json!("measurement");
+        if !unit.is_empty() {
+            config["unit_of_measurement"] = json!(unit);
+        }
+        if let Some(device_class) = get_device_class(&unit) {
+            config["device_class"] = json!(device_class);
+        }
+    }
+
+    let topic = format!(
+        "{}/sensor/{node_id}/{object_id}/config",
+        mqtt_config.home_assistant_discovery_prefix
+    );
+
+    (topic, config)
+}
+
+/// Returns the Home Assistant device class of the base unit
+fn get_device_class(base_unit: &str) -> Option<&'static str> {
+    match base_unit {
+        "°C" => Some("temperature"),
+        "B" => Some("data_size"),
+        "B/s" => Some("data_rate"),
+        "Hz" => Some("frequency"),
+        "V" => Some("voltage"),
+        "A" => Some("current"),
+        "W" => Some("power"),
+        "s" => Some("duration"),
+        _ => None,
+    }
+}
+
+/// Returns the name of the device in Home Assistant, which is the name of this computer
+fn get_device_name() -> String {
+    match dns_lookup::get_hostname() {
+        Ok(hostname) => format!("sensor-bridge ({hostname})"),
+        Err(_) => "sensor-bridge".to_string(),
+    }
+}
@@ -0,0 +1,96 @@
+use assertor::{assert_that, EqualityAssertion};
+
+use crate::config::{MqttConfig, MqttPayloadFormat};
+use crate::mqtt;
+use crate::test_utils::create_sensor_value;
+
+/// Returns a config with a fixed topic prefix, independent of the hostname
+fn create_mqtt_config() -> MqttConfig {
+    MqttConfig {
+        topic_prefix: "sensor-bridge".to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_get_object_id() {
+    // GIVEN is a sensor id with characters that are not allowed in discovery ids
+    let sensor_id = "lm_sensors_k10temp-pci-00c3 Tctl/Tdie";
+
+    // WHEN reducing the sensor id
+    let object_id = mqtt::get_object_id(sensor_id);
+
+    // THEN the characters should be replaced
+    assert_that!(object_id).is_equal_to("lm_sensors_k10temp-pci-00c3_tctl_tdie".to_string());
+}
+
+#[test]
+fn test_create_state_messages_as_json_state() {
+    // GIVEN are sensor values in pretty units
+    let mqtt_config = create_mqtt_config();
+    let sensor_values = vec![
+        create_sensor_value("cpu_load_total", "12.50", "%"),
+        create_sensor_value("network_rx_eth0", "1.00", "KB/s"),
+    ];
+
+    // WHEN creating the state messages
+    let messages = mqtt::create_state_messages(&mqtt_config, &sensor_values);
+
+    // THEN all values should be published to a single topic in their base unit
+    assert_that!(messages).is_equal_to(vec![(
+        "sensor-bridge/state".to_string(),
+        r#"{"cpu_load_total":12.5,"network_rx_eth0":1024.0}"#.to_string(),
+    )]);
+}
+
+#[test]
+fn test_create_state_messages_as_topic_per_sensor() {
+    // GIVEN is a config to publish each sensor to its own topic
+    let mqtt_config = MqttConfig {
+        payload_format: MqttPayloadFormat::TopicPerSensor,
+        ..create_mqtt_config()
+    };
+    let sensor_values = vec![create_sensor_value("cpu_temp_package", "45.00", "°C")];
+
+    // WHEN creating the state messages
+    let messages = mqtt::create_state_messages(&mqtt_config, &sensor_values);
+
+    // THEN the value should be published to the topic of the sensor
+    assert_that!(messages).is_equal_to(vec![(
+        "sensor-bridge/cpu_temp_package".to_string(),
+        "45.0".to_string(),
+    )]);
+}
+
+#[test]
+fn test_create_discovery_config() {
+    // GIVEN is a temperature sensor
+    let mqtt_config = create_mqtt_config();
+    let sensor_value = create_sensor_value("cpu_temp_package", "45.00", "°C");
+
+    // WHEN creating the discovery config
+    let (topic, config) = mqtt::create_discovery_config(&mqtt_config, &sensor_value);
+
+    // THEN the sensor should be announced as temperature, read from the json state topic
+    assert_that!(topic)
+        .is_equal_to("homeassistant/sensor/sensor-bridge/cpu_temp_package/config".to_string());
+    assert_that!(config["state_topic"].as_str()).is_equal_to(Some("sensor-bridge/state"));
+    assert_that!(config["value_template"].as_str())
+        .is_equal_to(Some("{{ value_json['cpu_temp_package'] }}"));
+    assert_that!(config["device_class"].as_str()).is_equal_to(Some("temperature"));
+    assert_that!(config["unit_of_measurement"].as_str()).is_equal_to(Some("°C"));
+}
+
+#[test]
+fn test_default_ids_contain_hostname() {
+    // GIVEN is the hostname of this computer
+    let hostname = mqtt::get_object_id(&dns_lookup::get_hostname().unwrap());
+
+    // WHEN creating the default config
+    let mqtt_config = MqttConfig::default();
+
+    // THEN the client id and the topic prefix should be unique per computer
+    let expected_id = format!("sensor-bridge-{hostname}");
+    assert_that!(mqtt_config.client_id).is_equal_to(expected_id.clone());
+    assert_that!(mqtt_config.topic_prefix).is_equal_to(expected_id);
+}
@@ -1,41 +1,65 @@
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
 use std::str;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
 use log::{debug, error, info, warn};
-use message_io::network::{Endpoint, SendStatus, Transport};
-use message_io::node::NodeHandler;
+use message_io::network::{Endpoint, NetEvent, SendStatus, Transport};
+use message_io::node::{NodeEvent, NodeHandler, NodeListener};
 use sensor_core::{DisplayConfig, RenderData, SensorValue, TransportMessage, TransportType};
+use uuid::Uuid;
 
 use crate::config::NetworkDeviceConfig;
-use crate::{conditional_image, sensor, static_image, text, utils};
+use crate::render_frame::FrameEncoder;
+use crate::sensor_sampler::{SensorDemand, SensorSampler};
+use crate::{conditional_image, render_frame, sensor, static_image, text, utils};
+
+/// Opens a tcp socket to the specified address.
+/// The resync flag is set after connecting and whenever the device requests a resync,
+/// as a new connection or a device that lost its state needs the layout again.
+pub fn open(
+    net_port_config: &NetworkDeviceConfig,
+    resync_requested: &Arc<AtomicBool>,
+) -> Option<(NodeHandler<()>, Endpoint)> {
+    let (handler, listener) = message_io::node::split::<()>();
+    let endpoint = connect_to_tcp_socket(net_port_config, &handler, resync_requested)?;
 
-const PUSH_RATE: Duration = Duration::from_millis(1000);
-const NETWORK_PORT: u64 = 10489;
+    resync_requested.store(true, Ordering::Relaxed);
+    listen_for_resync_requests(listener, resync_requested.clone());
 
-/// Opens a tcp socket to the specified address
-pub fn open(net_port_config: &NetworkDeviceConfig) -> Option<(NodeHandler<()>, Endpoint)> {
-    let (handler, _listener) = message_io::node::split::<()>();
-    let tcp_endpoint = connect_to_tcp_socket(net_port_config, &handler);
+    Some((handler, endpoint))
+}
 
-    tcp_endpoint.map(|endpoint| (handler, endpoint))
+/// Starts a new thread that receives the messages of the device, until the node handler is stopped
+fn listen_for_resync_requests(listener: NodeListener<()>, resync_requested: Arc<AtomicBool>) {
+    thread::spawn(move || {
+        listener.for_each(move |event| {
+            if let NodeEvent::Network(NetEvent::Message(_, data)) = event {
+                if render_frame::is_resync_request(data) {
+                    info!("Device requested a resync");
+                    resync_requested.store(true, Ordering::Relaxed);
+                }
+            }
+        });
+    });
 }
 
 /// Establishes a tcp connection to the specified address
 fn connect_to_tcp_socket(
     net_port_config: &NetworkDeviceConfig,
     handler: &NodeHandler<()>,
+    resync_requested: &Arc<AtomicBool>,
 ) -> Option<Endpoint> {
-    let ip = resolve_hostname(&net_port_config.address);
-
-    if ip.is_none() {
-        error!("Could not resolve hostname {}", net_port_config.address);
-    }
-
-    let address = format!("{}:{NETWORK_PORT}", ip.unwrap());
+    let address = match resolve_endpoint(&net_port_config.address, net_port_config.port) {
+        Some(address) => address,
+        None => {
+            error!("Could not resolve address {}", net_port_config.address);
+            return None;
+        }
+    };
 
     info!(
         "Connecting to device {}({})",
@@ -45,7 +69,7 @@ fn connect_to_tcp_socket(
     // Blocks until the connection is established
     let endpoint = handler
         .network()
-        .connect_sync(Transport::FramedTcp, &address);
+        .connect_sync(Transport::FramedTcp, address);
 
     // If not ok, print error and return none
     match endpoint {
@@ -53,7 +77,7 @@ fn connect_to_tcp_socket(
             info!("Connected to device {}({})", net_port_config.name, &address);
 
             let mut net_port = (handler.clone(), endpoint.0);
-            prepare_static_data(net_port_config, &mut net_port);
+            prepare_static_data(net_port_config, &mut net_port, resync_requested);
 
             Some(endpoint.0)
         }
@@ -72,36 +96,85 @@ fn connect_to_tcp_socket(
 fn prepare_static_data(
     net_port_config: &NetworkDeviceConfig,
     net_port: &mut (NodeHandler<()>, Endpoint),
+    resync_requested: &Arc<AtomicBool>,
 ) {
     // Prepare text data
-    prepare_static_text_data_on_display(net_port_config, net_port);
+    prepare_static_text_data_on_display(net_port_config, net_port, resync_requested);
 
     // Prepare static image data
-    prepare_static_image_data_on_display(net_port_config, net_port);
+    prepare_static_image_data_on_display(net_port_config, net_port, resync_requested);
 
     // Prepare conditional image data
-    prepare_conditional_image_data_on_display(net_port_config, net_port);
+    prepare_conditional_image_data_on_display(net_port_config, net_port, resync_requested);
 
     // Wait 1 seconds for the assets to be loaded
     info!("Waiting 1s for assets to be processed by the display...");
     thread::sleep(Duration::from_secs(1));
 }
 
-/// Resolves the name of the device to an ip v4 address
-fn resolve_hostname(address: &str) -> Option<String> {
+/// Splits the specified address into host and port.
+/// Supported formats are 'host', 'host:port', 'ipv6' and '[ipv6]:port'.
+/// If the address does not contain a port, the fallback port is used.
+/// Returns None if the address is empty or malformed.
+pub fn parse_address(address: &str, fallback_port: u16) -> Option<(String, u16)> {
+    let address = address.trim();
+
+    if address.is_empty() {
+        return None;
+    }
+
+    // A plain ip v6 address contains colons, but never a port
+    if Ipv6Addr::from_str(address).is_ok() {
+        return Some((address.to_string(), fallback_port));
+    }
+
+    // An ip v6 address in brackets, optionally followed by a port
+    if let Some(bracketed_address) = address.strip_prefix('[') {
+        let (host, remainder) = bracketed_address.split_once(']')?;
+        Ipv6Addr::from_str(host).ok()?;
+
+        let port = match remainder {
+            "" => fallback_port,
+            _ => parse_port(remainder.strip_prefix(':')?)?,
+        };
+
+        return Some((host.to_string(), port));
+    }
+
+    // An ip v4 address or hostname, optionally followed by a port
+    match address.split_once(':') {
+        Some((host, port)) if !host.is_empty() => Some((host.to_string(), parse_port(port)?)),
+        Some(_) => None,
+        None => Some((address.to_string(), fallback_port)),
+    }
+}
+
+/// Parses the port of an address, port 0 is rejected as it never is the port of a display
+fn parse_port(port: &str) -> Option<u16> {
+    port.parse().ok().filter(|port| *port != 0)
+}
+
+/// Parses the specified address and resolves it to a socket address.
+/// If the address does not contain a port, the fallback port is used.
+pub fn resolve_endpoint(address: &str, fallback_port: u16) -> Option<SocketAddr> {
+    let (host, port) = parse_address(address, fallback_port)?;
+    let ip = resolve_hostname(&host)?;
+
+    Some(SocketAddr::new(ip, port))
+}
+
+/// Resolves the name of the device to an ip address.
+/// Hostnames are resolved to the first ip v4 address.
+fn resolve_hostname(address: &str) -> Option<IpAddr> {
     // Check if target string is an valid ip address
-    if IpAddr::from_str(address).is_ok() {
-        return Some(address.to_string());
+    if let Ok(ip) = IpAddr::from_str(address) {
+        return Some(ip);
     }
 
     // Otherwise we most likely have a hostname, try to resolve the hostname
     // and get the first ipv4 address
     match dns_lookup::lookup_host(address).ok() {
-        Some(ips) => ips
-            .iter()
-            .filter(|ip| ip.is_ipv4())
-            .map(|ip| ip.to_string())
-            .next(),
+        Some(ips) => ips.into_iter().find(|ip| ip.is_ipv4()),
         None => {
             error!("Could not resolve hostname {}", address);
             None
@@ -114,20 +187,37 @@ fn resolve_hostname(address: &str) -> Option<String> {
 /// The thread will be stopped when the port_running_state_handle is set to false.
 /// The thread will be joined when the handle is dropped.
 pub fn start_sync(
-    sensor_value_history: &Arc<Mutex<Vec<Vec<SensorValue>>>>,
-    static_sensor_values: &Arc<Vec<SensorValue>>,
+    sensor_sampler: &Arc<SensorSampler>,
     net_port_config: NetworkDeviceConfig,
     port_running_state_handle: Arc<Mutex<bool>>,
 ) -> Arc<thread::JoinHandle<()>> {
-    let static_sensor_values = static_sensor_values.clone();
-    let sensor_value_history = sensor_value_history.clone();
+    let sensor_sampler = sensor_sampler.clone();
 
     // Start new thread that writes to the remote tcp socket
     let handle = thread::spawn(move || {
+        // Only the sensors referenced by the display layout are sampled and sent to the device
+        // The consumer id is unique per thread, because a reconnect starts a new thread before the old one stopped
+        let consumer_id = Uuid::new_v4().to_string();
+        let referenced_sensor_ids =
+            sensor::get_referenced_sensor_ids(&net_port_config.display_config);
+        let resync_requested = Arc::new(AtomicBool::new(true));
+        let mut frame_encoder = FrameEncoder::new(net_port_config.resync_interval());
+        sensor_sampler.register_consumer(
+            &consumer_id,
+            SensorDemand::Only(referenced_sensor_ids.clone()),
+        );
+
         // Try to open the named network port
-        let mut net_port = match try_open_tcp_socket(&net_port_config, &port_running_state_handle) {
+        let mut net_port = match try_open_tcp_socket(
+            &net_port_config,
+            &port_running_state_handle,
+            &resync_requested,
+        ) {
             Some(value) => value,
-            None => return,
+            None => {
+                sensor_sampler.unregister_consumer(&consumer_id);
+                return;
+            }
         };
 
         // Send data until the port_running_state_handle is set to false (sync button in UI)
@@ -135,21 +225,46 @@ pub fn start_sync(
             // Measure duration
             let start_time = Instant::now();
 
-            // Read sensor values
-            let last_sensor_values =
-                sensor::read_all_sensor_values(&sensor_value_history, &static_sensor_values);
+            // Get the latest sampled values of the referenced sensors
+            let mut last_sensor_values: Vec<SensorValue> = sensor_sampler
+                .latest()
+                .into_iter()
+                .filter(|sensor_value| referenced_sensor_ids.contains(&sensor_value.id))
+                .collect();
+            let mut display_config = net_port_config.display_config.clone();
+            if net_port_config.auto_scale_units {
+                text::auto_scale_units(
+                    &mut display_config,
+                    std::slice::from_mut(&mut last_sensor_values),
+                );
+            }
 
-            // Serialize the transport struct to bytes using messagepack
-            let data_to_send =
-                serialize_render_data(net_port_config.display_config.clone(), last_sensor_values);
+            // Serialize either the full render data or a frame of the delta frame protocol
+            let data_to_send = if net_port_config.delta_frames {
+                if resync_requested.swap(false, Ordering::Relaxed) {
+                    frame_encoder.request_resync();
+                }
+                let frame = frame_encoder.encode(&display_config, last_sensor_values, start_time);
+                render_frame::serialize(&frame)
+            } else {
+                serialize_render_data(display_config, last_sensor_values)
+            };
 
             // Send to actual data to the remote tcp socket
-            send_tcp_data(&net_port_config, &mut net_port, data_to_send);
+            send_tcp_data(
+                &net_port_config,
+                &mut net_port,
+                &resync_requested,
+                data_to_send,
+            );
 
             // Wait for the next iteration
-            wait(start_time);
+            wait(start_time, net_port_config.push_rate());
         }
 
+        sensor_sampler.unregister_consumer(&consumer_id);
+        net_port.0.stop();
+
         // Wait for thread to be joined
         thread::park();
     });
@@ -162,6 +277,7 @@ pub fn start_sync(
 fn try_open_tcp_socket(
     net_port_config: &NetworkDeviceConfig,
     port_running_state_handle: &Arc<Mutex<bool>>,
+    resync_requested: &Arc<AtomicBool>,
 ) -> Option<(NodeHandler<()>, Endpoint)> {
     let mut net_port = None;
 
@@ -170,7 +286,7 @@ fn try_open_tcp_socket(
         thread::sleep(Duration::from_secs(1));
 
         // Try to open the named network port
-        net_port = open(net_port_config);
+        net_port = open(net_port_config, resync_requested);
     }
     net_port.as_ref()?;
 
@@ -181,37 +297,41 @@ fn try_open_tcp_socket(
 fn prepare_static_text_data_on_display(
     net_port_config: &NetworkDeviceConfig,
     net_port: &mut (NodeHandler<()>, Endpoint),
+    resync_requested: &Arc<AtomicBool>,
 ) {
     let text_data = text::get_preparation_data(&net_port_config.display_config);
     let data_to_send = text::serialize(text_data);
-    send_tcp_data(net_port_config, net_port, data_to_send);
+    send_tcp_data(net_port_config, net_port, resync_requested, data_to_send);
 }
 
 /// Prepares the render data for the remote tcp socket
 fn prepare_static_image_data_on_display(
     net_port_config: &NetworkDeviceConfig,
     net_port: &mut (NodeHandler<()>, Endpoint),
+    resync_requested: &Arc<AtomicBool>,
 ) {
     let static_image_data = static_image::get_preparation_data(&net_port_config.display_config);
     let data_to_send = static_image::serialize(static_image_data);
-    send_tcp_data(net_port_config, net_port, data_to_send);
+    send_tcp_data(net_port_config, net_port, resync_requested, data_to_send);
 }
 
 /// Prepares the conditional image data and sends it to the remote tcp socket
 fn prepare_conditional_image_data_on_display(
     net_port_config: &NetworkDeviceConfig,
     net_port: &mut (NodeHandler<()>, Endpoint),
+    resync_requested: &Arc<AtomicBool>,
 ) {
     let conditional_image_data =
         conditional_image::get_preparation_data(&net_port_config.display_config);
     let data_to_send = conditional_image::serialize_preparation_data(conditional_image_data);
-    send_tcp_data(net_port_config, net_port, data_to_send);
+    send_tcp_data(net_port_config, net_port, resync_requested, data_to_send);
 }
 
 /// Sends the data to the remote tcp socket
 fn send_tcp_data(
     net_port_config: &NetworkDeviceConfig,
     net_port: &mut (NodeHandler<()>, Endpoint),
+    resync_requested: &Arc<AtomicBool>,
     data_to_send: Vec<u8>,
 ) {
     // Log data to send
@@ -235,7 +355,8 @@ fn send_tcp_data(
         SendStatus::ResourceNotFound => {
             warn!(" Not found --> Reconnecting");
             // Connection was lost, try to reconnect
-            *net_port = match open(net_port_config) {
+            net_port.0.stop();
+            *net_port = match open(net_port_config, resync_requested) {
                 Some((handler, endpoint)) => (handler, endpoint),
                 None => {
                     return;
@@ -245,7 +366,8 @@ fn send_tcp_data(
         SendStatus::ResourceNotAvailable => {
             warn!(" Not available --> Reconnecting");
             // Connection failed, try to reconnect
-            *net_port = match open(net_port_config) {
+            net_port.0.stop();
+            *net_port = match open(net_port_config, resync_requested) {
                 Some((handler, endpoint)) => (handler, endpoint),
                 None => {
                     return;
@@ -279,41 +401,42 @@ fn serialize_render_data(
 }
 
 /// Waits for the remaining time of the update interval
-/// To keep the PUSH_RATE at a constant rate, we need to sleep for the remaining time - the time it took to read the sensor values
+/// To keep the push rate constant, we need to sleep for the remaining time - the time it took to read the sensor values
 /// and send them to the network tcp port
-fn wait(start_time: Instant) {
+fn wait(start_time: Instant, push_rate: Duration) {
     let processing_duration = Instant::now().duration_since(start_time);
     debug!("Processing duration: {:?}", processing_duration);
-    let time_to_wait = PUSH_RATE
+    let time_to_wait = push_rate
         .checked_sub(processing_duration)
         .unwrap_or_else(|| {
             error!("Warning: Processing duration is longer than the update interval");
-            PUSH_RATE
+            push_rate
         });
     thread::sleep(time_to_wait);
 }
 
-/// Verifies that the specified address is reachable
-pub fn verify_network_address(address: &str) -> bool {
-    let ip = resolve_hostname(address);
+/// Verifies that the specified address is reachable.
+/// If the address does not contain a port, the fallback port is used.
+pub fn verify_network_address(address: &str, fallback_port: u16) -> bool {
+    let endpoint = resolve_endpoint(address, fallback_port);
 
-    if ip.is_none() {
-        error!("Could not resolve hostname {}", address);
-        return false;
+    match endpoint {
+        Some(endpoint) => check_endpoint(endpoint),
+        None => {
+            error!("Could not resolve address {}", address);
+            false
+        }
     }
-
-    // Pings the specified address
-    check_ip(&ip.unwrap())
 }
 
-/// Pings the specified ip address
-pub fn check_ip(ip: &str) -> bool {
-    info!("Testing IP '{ip}' on TCP port '{NETWORK_PORT}'");
+/// Tries to connect to the specified socket address
+pub fn check_endpoint(address: SocketAddr) -> bool {
+    info!("Testing TCP connection to '{address}'");
 
     let (handler, _) = message_io::node::split::<()>();
     let endpoint = handler
         .network()
-        .connect_sync(Transport::FramedTcp, format!("{ip}:{NETWORK_PORT}"));
+        .connect_sync(Transport::FramedTcp, address);
 
     endpoint.is_ok()
 }
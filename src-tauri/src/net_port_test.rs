@@ -0,0 +1,77 @@
+use assertor::{assert_that, EqualityAssertion};
+
+use crate::net_port;
+
+const FALLBACK_PORT: u16 = 10489;
+
+#[test]
+fn test_parse_address_without_port() {
+    // GIVEN are addresses without a port
+    let hostname = "some-device.local";
+    let ipv4 = "192.168.0.156";
+    let ipv6 = "fe80::1";
+
+    // WHEN parsing the addresses
+    let hostname = net_port::parse_address(hostname, FALLBACK_PORT);
+    let ipv4 = net_port::parse_address(ipv4, FALLBACK_PORT);
+    let ipv6 = net_port::parse_address(ipv6, FALLBACK_PORT);
+
+    // THEN the fallback port should be used
+    assert_that!(hostname).is_equal_to(Some(("some-device.local".to_string(), FALLBACK_PORT)));
+    assert_that!(ipv4).is_equal_to(Some(("192.168.0.156".to_string(), FALLBACK_PORT)));
+    assert_that!(ipv6).is_equal_to(Some(("fe80::1".to_string(), FALLBACK_PORT)));
+}
+
+#[test]
+fn test_parse_address_with_port() {
+    // GIVEN are addresses with a port
+    let hostname = "some-device.local:8080";
+    let ipv4 = "192.168.0.156:8080";
+    let ipv6 = "[fe80::1]:8080";
+
+    // WHEN parsing the addresses
+    let hostname = net_port::parse_address(hostname, FALLBACK_PORT);
+    let ipv4 = net_port::parse_address(ipv4, FALLBACK_PORT);
+    let ipv6 = net_port::parse_address(ipv6, FALLBACK_PORT);
+
+    // THEN the port of the address should be used
+    assert_that!(hostname).is_equal_to(Some(("some-device.local".to_string(), 8080)));
+    assert_that!(ipv4).is_equal_to(Some(("192.168.0.156".to_string(), 8080)));
+    assert_that!(ipv6).is_equal_to(Some(("fe80::1".to_string(), 8080)));
+}
+
+#[test]
+fn test_parse_malformed_address() {
+    // GIVEN are malformed addresses
+    let malformed_addresses = [
+        "",
+        ":8080",
+        "some-device.local:",
+        "some-device.local:99999",
+        "[fe80::1",
+        "[fe80::1]8080",
+        "[not-an-ip]:8080",
+        "some-device.local:0",
+        "[::1]:0",
+    ];
+
+    for address in malformed_addresses {
+        // WHEN parsing the address
+        let parsed_address = net_port::parse_address(address, FALLBACK_PORT);
+
+        // THEN the address should be rejected
+        assert_that!(parsed_address).is_equal_to(None);
+    }
+}
+
+#[test]
+fn test_resolve_endpoint() {
+    // GIVEN is an ip v6 address with a port
+    let address = "[::1]:8080";
+
+    // WHEN resolving the endpoint
+    let endpoint = net_port::resolve_endpoint(address, FALLBACK_PORT);
+
+    // THEN the socket address should be returned
+    assert_that!(endpoint).is_equal_to(Some("[::1]:8080".parse().unwrap()));
+}
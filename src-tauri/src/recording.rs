@@ -0,0 +1,334 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::Local;
+use log::{info, warn};
+use sensor_core::{SensorType, SensorValue};
+use serde::{Deserialize, Serialize};
+
+use crate::history_store;
+use crate::sensor_history;
+use crate::sensor_history::HistorySnapshot;
+use crate::sensor_sampler::{SensorDemand, SensorSampler, SAMPLE_RATE};
+use crate::utils::LockResultExt;
+
+/// Consumer id of the recording at the sensor sampler
+const CONSUMER_ID: &str = "recording";
+
+/// Default interval in which the sensor values are recorded
+pub const DEFAULT_RECORDING_INTERVAL_MS: u64 = 1000;
+
+/// Lower bound of the recording interval, providers are not read more often anyway
+pub const MIN_RECORDING_INTERVAL_MS: u64 = 250;
+
+/// Percentile reported in the summary of a recording
+const PERCENTILE: f64 = 0.95;
+
+/// Options of a recording session
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordingConfig {
+    pub file_path: String,
+    #[serde(default)]
+    pub format: RecordingFormat,
+    /// Interval in milliseconds in which the sensor values are recorded
+    #[serde(default = "default_recording_interval_ms")]
+    pub interval_ms: u64,
+    /// Ids of the recorded sensors, all sensors are recorded if empty
+    #[serde(default)]
+    pub sensor_ids: Vec<String>,
+}
+
+/// File format of a recording
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingFormat {
+    /// A row per recorded snapshot, with a column per sensor
+    #[default]
+    Csv,
+    /// A json object per recorded snapshot and line
+    JsonLines,
+}
+
+/// Statistics of a single numeric sensor over a recording
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SensorSummary {
+    pub sensor_id: String,
+    pub label: String,
+    pub unit: String,
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+    pub p95: f64,
+}
+
+/// Summary of a finished recording, written next to the recording as json file
+#[derive(Serialize, Debug, Clone)]
+pub struct RecordingSummary {
+    pub file_path: String,
+    /// Milliseconds since the unix epoch
+    pub started_at: u64,
+    pub stopped_at: u64,
+    pub snapshot_count: u64,
+    pub sensors: Vec<SensorSummary>,
+}
+
+/// A running recording, with its background thread
+struct ActiveRecording {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<io::Result<RecordingSummary>>,
+}
+
+/// Records the sampled sensor values to a file, at most one recording runs at a time.
+#[derive(Default)]
+pub struct Recorder {
+    active_recording: Mutex<Option<ActiveRecording>>,
+}
+
+impl RecordingConfig {
+    /// Creates the config of a csv recording of all sensors in the default interval
+    pub fn new(file_path: &str) -> RecordingConfig {
+        RecordingConfig {
+            file_path: file_path.to_string(),
+            format: RecordingFormat::Csv,
+            interval_ms: DEFAULT_RECORDING_INTERVAL_MS,
+            sensor_ids: vec![],
+        }
+    }
+
+    /// Returns the recording interval, bounded to the minimum recording interval
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms.max(MIN_RECORDING_INTERVAL_MS))
+    }
+}
+
+fn default_recording_interval_ms() -> u64 {
+    DEFAULT_RECORDING_INTERVAL_MS
+}
+
+impl Recorder {
+    /// Starts recording the sensor values in a new thread.
+    /// Fails if a recording is already running or the file can not be created.
+    pub fn start(
+        &self,
+        sensor_sampler: &Arc<SensorSampler>,
+        recording_config: RecordingConfig,
+    ) -> Result<(), String> {
+        let mut active_recording = self.active_recording.lock().ignore_poison();
+        if active_recording.is_some() {
+            return Err("A recording is already running".to_string());
+        }
+
+        let file_path = &recording_config.file_path;
+        let file = File::create(file_path)
+            .map_err(|err| format!("Could not create '{file_path}': {err}"))?;
+
+        let sensor_demand = if recording_config.sensor_ids.is_empty() {
+            SensorDemand::All
+        } else {
+            SensorDemand::Only(recording_config.sensor_ids.iter().cloned().collect())
+        };
+        sensor_sampler.register_consumer(CONSUMER_ID, sensor_demand);
+        info!("Recording sensor values to {file_path}");
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let sensor_sampler = sensor_sampler.clone();
+        let thread = thread::spawn(move || {
+            let summary = record(&sensor_sampler, &recording_config, file, &thread_stop);
+            sensor_sampler.unregister_consumer(CONSUMER_ID);
+            summary
+        });
+
+        *active_recording = Some(ActiveRecording { stop, thread });
+
+        Ok(())
+    }
+
+    /// Stops the running recording and returns its summary.
+    /// The summary is also written next to the recording, with the extension `.summary.json`.
+    pub fn stop(&self) -> Result<RecordingSummary, String> {
+        let active_recording = self
+            .active_recording
+            .lock()
+            .ignore_poison()
+            .take()
+            .ok_or_else(|| "No recording is running".to_string())?;
+
+        active_recording.stop.store(true, Ordering::Relaxed);
+        active_recording.thread.thread().unpark();
+        let summary = active_recording
+            .thread
+            .join()
+            .map_err(|_| "The recording failed unexpectedly".to_string())?
+            .map_err(|err| format!("Could not write the recording: {err}"))?;
+
+        let summary_path = format!("{}.summary.json", summary.file_path);
+        let summary_json = serde_json::to_string_pretty(&summary).unwrap();
+        if let Err(err) = fs::write(&summary_path, summary_json) {
+            warn!("Could not write recording summary {summary_path}: {err}");
+        }
+        info!(
+            "Recorded {} snapshots to {}",
+            summary.snapshot_count, summary.file_path
+        );
+
+        Ok(summary)
+    }
+
+    /// Returns whether a recording is running
+    pub fn is_recording(&self) -> bool {
+        self.active_recording.lock().ignore_poison().is_some()
+    }
+}
+
+/// Writes the latest sensor values in the recording interval, until the recording is stopped.
+/// Returns the summary of all recorded numeric sensors.
+fn record(
+    sensor_sampler: &SensorSampler,
+    recording_config: &RecordingConfig,
+    file: File,
+    stop: &AtomicBool,
+) -> io::Result<RecordingSummary> {
+    let mut writer = BufWriter::new(file);
+    let started_at = sensor_history::to_timestamp(SystemTime::now());
+    let mut snapshot_count = 0;
+    // Csv columns are fixed by the first recorded snapshot, unless the sensors are specified
+    let mut csv_columns: Option<Vec<String>> = None;
+    // Numeric values of each recorded sensor, with its latest value
+    let mut recorded_values: BTreeMap<String, (SensorValue, Vec<f64>)> = BTreeMap::new();
+
+    // Wait for the next sample, so that the providers of the recorded sensors were read
+    sensor_sampler.wait_for_next_sample(SAMPLE_RATE * 2);
+
+    while !stop.load(Ordering::Relaxed) {
+        let next_recording_at = Instant::now() + recording_config.interval();
+
+        let sensor_values: Vec<SensorValue> = sensor_sampler
+            .latest()
+            .into_iter()
+            .filter(|sensor_value| {
+                recording_config.sensor_ids.is_empty()
+                    || recording_config.sensor_ids.contains(&sensor_value.id)
+            })
+            .collect();
+
+        if !sensor_values.is_empty() {
+            let snapshot = HistorySnapshot {
+                timestamp: sensor_history::to_timestamp(SystemTime::now()),
+                sensor_values,
+            };
+
+            match recording_config.format {
+                RecordingFormat::Csv => {
+                    if csv_columns.is_none() {
+                        let columns = get_csv_columns(recording_config, &snapshot);
+                        history_store::write_csv_header(&mut writer, &columns)?;
+                        csv_columns = Some(columns);
+                    }
+                    let columns = csv_columns.as_ref().unwrap();
+                    history_store::write_csv_row(&mut writer, columns, &snapshot)?;
+                }
+                RecordingFormat::JsonLines => {
+                    serde_json::to_writer(&mut writer, &snapshot)?;
+                    writeln!(writer)?;
+                }
+            }
+            writer.flush()?;
+            snapshot_count += 1;
+
+            for sensor_value in snapshot.sensor_values {
+                if !matches!(sensor_value.sensor_type, SensorType::Number) {
+                    continue;
+                }
+                if let Ok(value) = sensor_value.value.trim().parse::<f64>() {
+                    let (latest_value, values) = recorded_values
+                        .entry(sensor_value.id.clone())
+                        .or_insert_with(|| (sensor_value.clone(), vec![]));
+                    *latest_value = sensor_value;
+                    values.push(value);
+                }
+            }
+        }
+
+        // Sleep until the next recording, stopping the recording wakes the thread early
+        while !stop.load(Ordering::Relaxed) {
+            let now = Instant::now();
+            if now >= next_recording_at {
+                break;
+            }
+            thread::park_timeout(next_recording_at - now);
+        }
+    }
+
+    Ok(RecordingSummary {
+        file_path: recording_config.file_path.clone(),
+        started_at,
+        stopped_at: sensor_history::to_timestamp(SystemTime::now()),
+        snapshot_count,
+        sensors: recorded_values
+            .values()
+            .filter_map(|(sensor_value, values)| summarize(sensor_value, values))
+            .collect(),
+    })
+}
+
+/// Returns the path of a new recording in the documents directory, named after the current time
+pub fn get_default_recording_path() -> String {
+    let directory = dirs::document_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(std::env::temp_dir);
+    let file_name = format!(
+        "sensor-bridge-recording-{}.csv",
+        Local::now().format("%Y%m%d-%H%M%S")
+    );
+
+    directory.join(file_name).to_string_lossy().to_string()
+}
+
+/// Returns the csv columns, the specified sensors or all sensors of the first snapshot sorted by id
+fn get_csv_columns(recording_config: &RecordingConfig, snapshot: &HistorySnapshot) -> Vec<String> {
+    if !recording_config.sensor_ids.is_empty() {
+        return recording_config.sensor_ids.clone();
+    }
+
+    let mut sensor_ids: Vec<String> = snapshot
+        .sensor_values
+        .iter()
+        .map(|sensor_value| sensor_value.id.clone())
+        .collect();
+    sensor_ids.sort();
+    sensor_ids
+}
+
+/// Returns min, average, max and 95th percentile of the recorded values of the sensor.
+/// Returns None if no value was recorded.
+pub fn summarize(sensor_value: &SensorValue, values: &[f64]) -> Option<SensorSummary> {
+    if values.is_empty() {
+        return None;
+    }
+
+    let mut sorted_values = values.to_vec();
+    sorted_values.sort_by(f64::total_cmp);
+
+    // Nearest rank percentile
+    let percentile_rank = (PERCENTILE * sorted_values.len() as f64).ceil() as usize;
+    let p95 = sorted_values[percentile_rank.clamp(1, sorted_values.len()) - 1];
+
+    Some(SensorSummary {
+        sensor_id: sensor_value.id.clone(),
+        label: sensor_value.label.clone(),
+        unit: sensor_value.unit.clone(),
+        min: sorted_values[0],
+        avg: sorted_values.iter().sum::<f64>() / sorted_values.len() as f64,
+        max: sorted_values[sorted_values.len() - 1],
+        p95,
+    })
+}
@@ -0,0 +1,54 @@
+use assertor::{assert_that, EqualityAssertion};
+use sensor_core::{SensorType, SensorValue};
+
+use crate::recording;
+use crate::recording::SensorSummary;
+
+fn create_sensor_value() -> SensorValue {
+    SensorValue {
+        id: "cpu_temp".to_string(),
+        value: "0".to_string(),
+        unit: "°C".to_string(),
+        label: "CPU temperature".to_string(),
+        sensor_type: SensorType::Number,
+    }
+}
+
+#[test]
+fn test_summarize() {
+    // GIVEN are the recorded values 1 to 20 in random order
+    let mut values: Vec<f64> = (1..=20).map(f64::from).collect();
+    values.reverse();
+    values.swap(3, 11);
+
+    // WHEN summarizing the values
+    let summary = recording::summarize(&create_sensor_value(), &values);
+
+    // THEN the 95th percentile should be the nearest rank
+    assert_that!(summary).is_equal_to(Some(SensorSummary {
+        sensor_id: "cpu_temp".to_string(),
+        label: "CPU temperature".to_string(),
+        unit: "°C".to_string(),
+        min: 1.0,
+        avg: 10.5,
+        max: 20.0,
+        p95: 19.0,
+    }));
+}
+
+#[test]
+fn test_summarize_single_and_no_value() {
+    // GIVEN are a single recorded value and no recorded value
+    let single_value = [42.0];
+    let no_value: [f64; 0] = [];
+
+    // WHEN summarizing the values
+    let single_value = recording::summarize(&create_sensor_value(), &single_value);
+    let no_value = recording::summarize(&create_sensor_value(), &no_value);
+
+    // THEN all statistics should equal the single value, and nothing should be summarized without values
+    let single_value = single_value.unwrap();
+    assert_that!(single_value.min).is_equal_to(42.0);
+    assert_that!(single_value.p95).is_equal_to(42.0);
+    assert_that!(no_value).is_equal_to(None);
+}
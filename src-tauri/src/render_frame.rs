@@ -0,0 +1,178 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use sensor_core::{DisplayConfig, SensorValue};
+use serde::{Deserialize, Serialize};
+
+/// Leading magic number of frame messages, "SBFR".
+/// Read as transport type of a sensor-core transport message it is out of range,
+/// so that a display can tell frame messages apart from transport messages.
+pub const FRAME_MAGIC: u32 = 0x5342_4652;
+
+/// Types of frame messages of the delta frame protocol
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum FrameType {
+    /// The layout with the values of all referenced sensors, sent after connect and on a resync
+    Layout,
+    /// Only the sensor values that changed since the previous frame
+    Delta,
+    /// Sent by the display to receive a layout frame with the next tick, e.g. after it lost its state
+    RequestResync,
+}
+
+/// Envelope of a frame, like the transport message of sensor-core
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FrameMessage {
+    pub magic: u32,
+    pub frame_type: FrameType,
+    pub data: Vec<u8>,
+}
+
+/// The layout and the values of all referenced sensors, the display replaces its state with it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LayoutFrame {
+    pub display_config: DisplayConfig,
+    pub sensor_values: Vec<SensorValue>,
+}
+
+/// The changes since the previous frame, the display applies them to its state
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeltaFrame {
+    /// Sensor values that changed or appeared since the previous frame
+    pub changed_values: Vec<SensorValue>,
+    /// Ids of sensors, that are no longer available
+    pub removed_sensor_ids: Vec<String>,
+}
+
+/// A frame to send to the display
+#[derive(Debug, Clone)]
+pub enum RenderFrame {
+    Layout(LayoutFrame),
+    Delta(DeltaFrame),
+}
+
+/// Decides per tick whether the layout or only the changed sensor values are sent to a display.
+/// The layout is sent with the first frame, on request and after the resync interval.
+pub struct FrameEncoder {
+    resync_interval: Duration,
+    /// Sensor values known by the display, by sensor id
+    sent_values: HashMap<String, SensorValue>,
+    last_resync: Option<Instant>,
+}
+
+impl FrameEncoder {
+    pub fn new(resync_interval: Duration) -> FrameEncoder {
+        FrameEncoder {
+            resync_interval,
+            sent_values: HashMap::new(),
+            last_resync: None,
+        }
+    }
+
+    /// Sends the layout with the next frame, e.g. after a reconnect or a resync request of the display
+    pub fn request_resync(&mut self) {
+        self.last_resync = None;
+    }
+
+    /// Returns the frame for the current sensor values
+    pub fn encode(
+        &mut self,
+        display_config: &DisplayConfig,
+        sensor_values: Vec<SensorValue>,
+        now: Instant,
+    ) -> RenderFrame {
+        let is_resync_due = match self.last_resync {
+            Some(last_resync) => now.duration_since(last_resync) >= self.resync_interval,
+            None => true,
+        };
+
+        if is_resync_due {
+            self.last_resync = Some(now);
+            self.sent_values = sensor_values
+                .iter()
+                .map(|sensor_value| (sensor_value.id.clone(), sensor_value.clone()))
+                .collect();
+
+            return RenderFrame::Layout(LayoutFrame {
+                display_config: display_config.clone(),
+                sensor_values,
+            });
+        }
+
+        let sensor_ids: HashSet<&String> = sensor_values
+            .iter()
+            .map(|sensor_value| &sensor_value.id)
+            .collect();
+        let mut removed_sensor_ids: Vec<String> = self
+            .sent_values
+            .keys()
+            .filter(|id| !sensor_ids.contains(id))
+            .cloned()
+            .collect();
+        removed_sensor_ids.sort();
+        for id in removed_sensor_ids.iter() {
+            self.sent_values.remove(id);
+        }
+
+        let changed_values: Vec<SensorValue> = sensor_values
+            .into_iter()
+            .filter(|sensor_value| {
+                self.sent_values
+                    .get(&sensor_value.id)
+                    .is_none_or(|sent_value| !is_same_value(sent_value, sensor_value))
+            })
+            .collect();
+        for sensor_value in changed_values.iter() {
+            self.sent_values
+                .insert(sensor_value.id.clone(), sensor_value.clone());
+        }
+
+        RenderFrame::Delta(DeltaFrame {
+            changed_values,
+            removed_sensor_ids,
+        })
+    }
+}
+
+/// Returns true if the display shows both sensor values the same way
+fn is_same_value(a: &SensorValue, b: &SensorValue) -> bool {
+    a.value == b.value && a.unit == b.unit && a.label == b.label
+}
+
+/// Serializes the frame to a frame message
+pub fn serialize(frame: &RenderFrame) -> Vec<u8> {
+    let (frame_type, data) = match frame {
+        RenderFrame::Layout(layout_frame) => {
+            (FrameType::Layout, bincode::serialize(layout_frame).unwrap())
+        }
+        RenderFrame::Delta(delta_frame) => {
+            (FrameType::Delta, bincode::serialize(delta_frame).unwrap())
+        }
+    };
+
+    serialize_message(frame_type, data)
+}
+
+/// Serializes the data to a frame message of the specified type
+pub fn serialize_message(frame_type: FrameType, data: Vec<u8>) -> Vec<u8> {
+    let frame_message = FrameMessage {
+        magic: FRAME_MAGIC,
+        frame_type,
+        data,
+    };
+
+    bincode::serialize(&frame_message).unwrap()
+}
+
+/// Deserializes a frame message, returns None if the data is no frame message
+pub fn deserialize_message(data: &[u8]) -> Option<FrameMessage> {
+    bincode::deserialize::<FrameMessage>(data)
+        .ok()
+        .filter(|frame_message| frame_message.magic == FRAME_MAGIC)
+}
+
+/// Returns true if the data received from the display is a resync request
+pub fn is_resync_request(data: &[u8]) -> bool {
+    deserialize_message(data)
+        .is_some_and(|frame_message| frame_message.frame_type == FrameType::RequestResync)
+}
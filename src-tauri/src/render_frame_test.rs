@@ -0,0 +1,228 @@
+use std::time::{Duration, Instant};
+
+use assertor::{assert_that, BooleanAssertion, EqualityAssertion};
+use sensor_core::DisplayConfig;
+
+use crate::render_frame;
+use crate::render_frame::{FrameEncoder, FrameType, RenderFrame};
+use crate::test_utils::create_sensor_value;
+use crate::text;
+
+const RESYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Returns the ids of the sensor values of a layout frame, or None for a delta frame
+fn get_layout_ids(frame: &RenderFrame) -> Option<Vec<String>> {
+    match frame {
+        RenderFrame::Layout(layout_frame) => Some(
+            layout_frame
+                .sensor_values
+                .iter()
+                .map(|sensor_value| sensor_value.id.clone())
+                .collect(),
+        ),
+        RenderFrame::Delta(_) => None,
+    }
+}
+
+/// Returns the changed values and removed ids of a delta frame, or None for a layout frame
+fn get_delta(frame: &RenderFrame) -> Option<(Vec<(String, String)>, Vec<String>)> {
+    match frame {
+        RenderFrame::Layout(_) => None,
+        RenderFrame::Delta(delta_frame) => Some((
+            delta_frame
+                .changed_values
+                .iter()
+                .map(|sensor_value| (sensor_value.id.clone(), sensor_value.value.clone()))
+                .collect(),
+            delta_frame.removed_sensor_ids.clone(),
+        )),
+    }
+}
+
+#[test]
+fn test_encode_delta_frames() {
+    // GIVEN is a frame encoder
+    let mut frame_encoder = FrameEncoder::new(RESYNC_INTERVAL);
+    let display_config = DisplayConfig::default();
+    let start = Instant::now();
+
+    // WHEN encoding a tick, a tick with a changed value and a tick with a removed and an added sensor
+    let first = frame_encoder.encode(
+        &display_config,
+        vec![
+            create_sensor_value("cpu_load", "10", "%"),
+            create_sensor_value("hostname", "desktop", "%"),
+        ],
+        start,
+    );
+    let second = frame_encoder.encode(
+        &display_config,
+        vec![
+            create_sensor_value("cpu_load", "20", "%"),
+            create_sensor_value("hostname", "desktop", "%"),
+        ],
+        start + Duration::from_secs(1),
+    );
+    let third = frame_encoder.encode(
+        &display_config,
+        vec![
+            create_sensor_value("cpu_load", "20", "%"),
+            create_sensor_value("gpu_load", "5", "%"),
+        ],
+        start + Duration::from_secs(2),
+    );
+
+    // THEN the first frame should contain the layout with all values,
+    // and the following frames only the changes
+    assert_that!(get_layout_ids(&first))
+        .is_equal_to(Some(vec!["cpu_load".to_string(), "hostname".to_string()]));
+    assert_that!(get_delta(&second)).is_equal_to(Some((
+        vec![("cpu_load".to_string(), "20".to_string())],
+        vec![],
+    )));
+    assert_that!(get_delta(&third)).is_equal_to(Some((
+        vec![("gpu_load".to_string(), "5".to_string())],
+        vec!["hostname".to_string()],
+    )));
+}
+
+#[test]
+fn test_encode_resync() {
+    // GIVEN is a frame encoder that already sent the layout
+    let mut frame_encoder = FrameEncoder::new(RESYNC_INTERVAL);
+    let display_config = DisplayConfig::default();
+    let sensor_values = vec![create_sensor_value("cpu_load", "10", "%")];
+    let start = Instant::now();
+    frame_encoder.encode(&display_config, sensor_values.clone(), start);
+
+    // WHEN encoding before and after the resync interval, and after a requested resync
+    let before_interval = frame_encoder.encode(
+        &display_config,
+        sensor_values.clone(),
+        start + Duration::from_secs(59),
+    );
+    let after_interval = frame_encoder.encode(
+        &display_config,
+        sensor_values.clone(),
+        start + RESYNC_INTERVAL,
+    );
+    frame_encoder.request_resync();
+    let after_request = frame_encoder.encode(
+        &display_config,
+        sensor_values,
+        start + Duration::from_secs(61),
+    );
+
+    // THEN the layout should be sent again after the interval and on request
+    assert_that!(get_delta(&before_interval)).is_equal_to(Some((vec![], vec![])));
+    assert_that!(get_layout_ids(&after_interval)).is_equal_to(Some(vec!["cpu_load".to_string()]));
+    assert_that!(get_layout_ids(&after_request)).is_equal_to(Some(vec!["cpu_load".to_string()]));
+}
+
+#[test]
+fn test_frame_messages() {
+    // GIVEN are a resync request of a display and a serialized delta frame
+    let resync_request = render_frame::serialize_message(FrameType::RequestResync, vec![]);
+    let mut frame_encoder = FrameEncoder::new(RESYNC_INTERVAL);
+    let display_config = DisplayConfig::default();
+    let start = Instant::now();
+    frame_encoder.encode(&display_config, vec![], start);
+    let delta_frame = render_frame::serialize(&frame_encoder.encode(
+        &display_config,
+        vec![],
+        start + Duration::from_secs(1),
+    ));
+
+    // WHEN checking the messages
+    let is_resync_request = render_frame::is_resync_request(&resync_request);
+    let is_delta_frame_resync_request = render_frame::is_resync_request(&delta_frame);
+    let is_garbage_resync_request = render_frame::is_resync_request(&[1, 2, 3]);
+    let delta_frame_type = render_frame::deserialize_message(&delta_frame)
+        .map(|frame_message| frame_message.frame_type);
+
+    // THEN only the resync request should be detected and the frame type should be preserved
+    assert_that!(is_resync_request).is_true();
+    assert_that!(is_delta_frame_resync_request).is_false();
+    assert_that!(is_garbage_resync_request).is_false();
+    assert_that!(delta_frame_type).is_equal_to(Some(FrameType::Delta));
+}
+
+#[test]
+fn test_auto_scaled_text_across_unit_boundary() {
+    // GIVEN is a layout with a text element of a frequency sensor, whose units are auto scaled
+    let display_config: DisplayConfig = serde_json::from_value(serde_json::json!({
+        "resolution_width": 480,
+        "resolution_height": 320,
+        "elements": [{
+            "id": "text",
+            "name": "Frequency",
+            "element_type": "text",
+            "x": 0,
+            "y": 0,
+            "text_config": {
+                "sensor_id": "cpu_frequency",
+                "value_modifier": "none",
+                "format": "{value} {unit}",
+                "font_family": "Arial",
+                "font_size": 20,
+                "font_color": "#ffffffff",
+                "width": 100,
+                "height": 20,
+                "alignment": "left"
+            },
+            "image_config": null,
+            "graph_config": null,
+            "conditional_image_config": null
+        }]
+    }))
+    .unwrap();
+    let mut frame_encoder = FrameEncoder::new(RESYNC_INTERVAL);
+    let start = Instant::now();
+    let mut encode_tick = |second: u64, value: &str| {
+        let mut tick_display_config = display_config.clone();
+        let mut sensor_values = vec![create_sensor_value("cpu_frequency", value, "Hz")];
+        text::auto_scale_units(
+            &mut tick_display_config,
+            std::slice::from_mut(&mut sensor_values),
+        );
+        frame_encoder.encode(
+            &tick_display_config,
+            sensor_values,
+            start + Duration::from_secs(second),
+        )
+    };
+
+    // WHEN the value rises from a readable value in hertz above 1 GHz, and drops again
+    let readable = encode_tick(0, "800");
+    let scaled = encode_tick(1, "3400000000");
+    let dropped = encode_tick(2, "900");
+
+    // THEN the layout should point to the auto scaled copy from the start,
+    // and the copy should only change its value, without being removed
+    let text_sensor_id = match &readable {
+        RenderFrame::Layout(layout_frame) => layout_frame.display_config.elements[0]
+            .text_config
+            .as_ref()
+            .map(|text_config| text_config.sensor_id.clone()),
+        RenderFrame::Delta(_) => None,
+    };
+    assert_that!(text_sensor_id).is_equal_to(Some("cpu_frequency@auto_scaled".to_string()));
+    assert_that!(get_layout_ids(&readable)).is_equal_to(Some(vec![
+        "cpu_frequency".to_string(),
+        "cpu_frequency@auto_scaled".to_string(),
+    ]));
+    assert_that!(get_delta(&scaled)).is_equal_to(Some((
+        vec![
+            ("cpu_frequency".to_string(), "3400000000".to_string()),
+            ("cpu_frequency@auto_scaled".to_string(), "3.4".to_string()),
+        ],
+        vec![],
+    )));
+    assert_that!(get_delta(&dropped)).is_equal_to(Some((
+        vec![
+            ("cpu_frequency".to_string(), "900".to_string()),
+            ("cpu_frequency@auto_scaled".to_string(), "900".to_string()),
+        ],
+        vec![],
+    )));
+}
@@ -1,89 +1,278 @@
+use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
-use std::thread;
+use std::time::{Duration, Instant};
 
-use log::debug;
-use sensor_core::SensorValue;
+use sensor_core::{DisplayConfig, ElementType, SensorValue};
+use serde::{Deserialize, Serialize};
 use super_shell::RootShell;
 
+use crate::alerts::AlertSensors;
+use crate::linux_amdgpu::AmdGpuSensors;
 use crate::linux_dmidecode_sensors::DmiDecodeSensors;
-use crate::system_stat_sensor;
-use crate::utils::LockResultExt;
-use crate::{
-    linux_amdgpu, linux_lm_sensors, linux_system_sensors, misc_sensor, SENSOR_VALUE_HISTORY_SIZE,
-};
+use crate::linux_intel_gpu::IntelGpuSensors;
+use crate::linux_lm_sensors::LmSensors;
+use crate::linux_system_sensors::LinuxSystemSensor;
+use crate::misc_sensor::SystemSensor;
+use crate::system_stat_sensor::SystemStatSensor;
 
-pub trait SensorProvider {
+/// A source of sensor values
+pub trait SensorProvider: Send + Sync {
+    /// Returns the unique name of the provider
     fn get_name(&self) -> String;
+
+    /// Returns the interval in which the provider should be read
+    fn get_preferred_interval(&self) -> Duration;
+
+    /// Reads the current sensor values
+    fn read(&self) -> Vec<SensorValue>;
+
+    /// Reads all sensor values the provider is able to produce.
+    /// This is used to find out which sensors a provider produces, defaults to a regular read.
+    fn discover(&self) -> Vec<SensorValue> {
+        self.read()
+    }
+}
+
+/// Information about a registered sensor provider, as listed in the UI
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SensorProviderInfo {
+    pub name: String,
+    pub enabled: bool,
+    pub interval_ms: u64,
+    pub sensor_count: usize,
+}
+
+/// A registered sensor provider with its last read sensor values
+struct RegisteredSensorProvider {
+    provider: Arc<dyn SensorProvider>,
+    enabled: bool,
+    last_read: Option<Instant>,
+    sensor_values: Vec<SensorValue>,
 }
 
-pub fn read_all_sensor_values(
-    sensor_value_history: &Arc<Mutex<Vec<Vec<SensorValue>>>>,
-    static_sensor_values: &Arc<Vec<SensorValue>>,
-) -> Vec<SensorValue> {
-    let static_sensor_values = static_sensor_values.iter().cloned().collect();
-
-    let collected_sensor_values = thread::spawn(move || {
-        // Measurement that it took to read all sensors
-        let start = std::time::Instant::now();
-
-        let mut sensor_values: Vec<SensorValue> =
-            [static_sensor_values, read_dynamic_sensor_values()].concat();
-
-        // Sort sensors by label
-        sensor_values.sort_by(|a, b| a.label.cmp(&b.label));
-
-        debug!(
-            "Reading all sensors took {:?}",
-            std::time::Instant::now().duration_since(start)
-        );
-
-        sensor_values
-    })
-    .join()
-    .unwrap();
-
-    // Insert the collected sensor values at the beginning of the history
-    // and remove the last element if the history is too long
-    let mut sensor_value_history = sensor_value_history.lock().ignore_poison();
-    sensor_value_history.insert(0, collected_sensor_values.clone());
-    while sensor_value_history.len() > SENSOR_VALUE_HISTORY_SIZE {
-        sensor_value_history.pop();
+impl RegisteredSensorProvider {
+    /// Checks if the provider produces at least one of the specified sensors.
+    /// A provider that was never read may produce any sensor.
+    fn provides_any(&self, sensor_ids: &HashSet<String>) -> bool {
+        self.last_read.is_none()
+            || self
+                .sensor_values
+                .iter()
+                .any(|sensor_value| sensor_ids.contains(&sensor_value.id))
     }
 
-    collected_sensor_values
+    /// Checks if the provider produces the specified sensor
+    fn provides(&self, sensor_id: &str) -> bool {
+        self.sensor_values
+            .iter()
+            .any(|sensor_value| sensor_value.id == sensor_id)
+    }
 }
 
-/// Reads the dynamic sensor values
-/// This is done every update interval
-fn read_dynamic_sensor_values() -> Vec<SensorValue> {
-    // Store reference to CpuSensor {}.get_sensor_values in a vector
-    let sensor_requests = vec![
-        system_stat_sensor::get_sensor_values,
-        misc_sensor::get_sensor_values,
-        linux_lm_sensors::get_sensor_values,
-        linux_amdgpu::get_sensor_values,
-        linux_system_sensors::get_sensor_values,
-    ];
-
-    let mut sensor_values = vec![];
-
-    // Iterate over the vector and call each function using par_iter
-    sensor_requests
-        .into_iter()
-        .flat_map(|f| f())
-        .collect::<Vec<SensorValue>>()
-        .iter()
-        .for_each(|sensor_value| {
-            sensor_values.push(sensor_value.clone());
+/// Registry of all sensor providers.
+/// Keeps the last read values of each provider, so that every provider can be read in its own interval.
+pub struct SensorProviderRegistry {
+    providers: Vec<RegisteredSensorProvider>,
+}
+
+impl SensorProviderRegistry {
+    /// Creates the registry with all available sensor providers.
+    /// Providers whose name is contained in the disabled provider names are not read.
+    pub fn new(
+        root_shell: &Arc<Mutex<Option<RootShell>>>,
+        disabled_provider_names: &[String],
+    ) -> SensorProviderRegistry {
+        let providers: Vec<Arc<dyn SensorProvider>> = vec![
+            Arc::new(DmiDecodeSensors::new(root_shell.clone())),
+            Arc::new(SystemStatSensor::default()),
+            Arc::new(SystemSensor {}),
+            Arc::new(LinuxSystemSensor {}),
+            Arc::new(LmSensors {}),
+            Arc::new(AmdGpuSensors::default()),
+            Arc::new(IntelGpuSensors::default()),
+            Arc::new(AlertSensors {}),
+        ];
+
+        SensorProviderRegistry::with_providers(providers, disabled_provider_names)
+    }
+
+    /// Creates the registry with the specified sensor providers.
+    /// Providers whose name is contained in the disabled provider names are not read.
+    pub fn with_providers(
+        providers: Vec<Arc<dyn SensorProvider>>,
+        disabled_provider_names: &[String],
+    ) -> SensorProviderRegistry {
+        SensorProviderRegistry {
+            providers: providers
+                .into_iter()
+                .map(|provider| RegisteredSensorProvider {
+                    enabled: !disabled_provider_names.contains(&provider.get_name()),
+                    provider,
+                    last_read: None,
+                    sensor_values: vec![],
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the information of all registered providers
+    pub fn get_provider_infos(&self) -> Vec<SensorProviderInfo> {
+        self.providers
+            .iter()
+            .map(|registered| SensorProviderInfo {
+                name: registered.provider.get_name(),
+                enabled: registered.enabled,
+                interval_ms: registered.provider.get_preferred_interval().as_millis() as u64,
+                sensor_count: registered.sensor_values.len(),
+            })
+            .collect()
+    }
+
+    /// Enables or disables the provider with the specified name.
+    /// Returns false if no provider with the specified name exists.
+    pub fn set_enabled(&mut self, provider_name: &str, enabled: bool) -> bool {
+        let registered = self
+            .providers
+            .iter_mut()
+            .find(|registered| registered.provider.get_name() == provider_name);
+
+        match registered {
+            Some(registered) => {
+                registered.enabled = enabled;
+                if !enabled {
+                    registered.last_read = None;
+                    registered.sensor_values.clear();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the index and provider of all enabled providers.
+    pub fn get_enabled_providers(&self) -> Vec<(usize, Arc<dyn SensorProvider>)> {
+        self.providers
+            .iter()
+            .enumerate()
+            .filter(|(_, registered)| registered.enabled)
+            .map(|(index, registered)| (index, registered.provider.clone()))
+            .collect()
+    }
+
+    /// Returns the index and provider of all enabled providers, that were not read yet.
+    pub fn get_unread_providers(&self) -> Vec<(usize, Arc<dyn SensorProvider>)> {
+        self.providers
+            .iter()
+            .enumerate()
+            .filter(|(_, registered)| registered.enabled && registered.last_read.is_none())
+            .map(|(index, registered)| (index, registered.provider.clone()))
+            .collect()
+    }
+
+    /// Returns the index and provider of all enabled providers, that produce a required sensor
+    /// and whose interval elapsed. If required sensor ids is None, all sensors are required.
+    /// The tolerance compensates the jitter of the caller's tick.
+    pub fn get_due_providers(
+        &self,
+        required_sensor_ids: &Option<HashSet<String>>,
+        now: Instant,
+        tolerance: Duration,
+    ) -> Vec<(usize, Arc<dyn SensorProvider>)> {
+        self.get_required_providers(required_sensor_ids)
+            .into_iter()
+            .filter(|index| {
+                let registered = &self.providers[*index];
+                registered.last_read.is_none_or(|last_read| {
+                    now.duration_since(last_read) + tolerance
+                        >= registered.provider.get_preferred_interval()
+                })
+            })
+            .map(|index| (index, self.providers[index].provider.clone()))
+            .collect()
+    }
+
+    /// Stores the sensor values that were read from the provider with the specified index
+    pub fn update(&mut self, index: usize, sensor_values: Vec<SensorValue>, read_at: Instant) {
+        let registered = &mut self.providers[index];
+
+        // The provider might have been disabled while it was read
+        if registered.enabled {
+            registered.last_read = Some(read_at);
+            registered.sensor_values = sensor_values;
+        }
+    }
+
+    /// Returns the ids of all sensors, that were read from the providers so far
+    pub fn get_known_sensor_ids(&self) -> Vec<String> {
+        self.providers
+            .iter()
+            .flat_map(|registered| &registered.sensor_values)
+            .map(|sensor_value| sensor_value.id.clone())
+            .collect()
+    }
+
+    /// Returns the last read sensor values of all enabled providers, that produce a required sensor.
+    /// If required sensor ids is None, all sensors are required.
+    pub fn get_sensor_values(
+        &self,
+        required_sensor_ids: &Option<HashSet<String>>,
+    ) -> Vec<SensorValue> {
+        self.get_required_providers(required_sensor_ids)
+            .into_iter()
+            .flat_map(|index| self.providers[index].sensor_values.clone())
+            .collect()
+    }
+
+    /// Returns the indices of all enabled providers, that produce at least one required sensor.
+    /// All enabled providers are required, if a required sensor is not produced by any provider,
+    /// because its provider was not read yet or the sensor appeared since the last read.
+    fn get_required_providers(&self, required_sensor_ids: &Option<HashSet<String>>) -> Vec<usize> {
+        let enabled_providers = self
+            .providers
+            .iter()
+            .enumerate()
+            .filter(|(_, registered)| registered.enabled);
+
+        let required_sensor_ids = match required_sensor_ids {
+            Some(required_sensor_ids) => required_sensor_ids,
+            None => return enabled_providers.map(|(index, _)| index).collect(),
+        };
+
+        let is_unknown_sensor_required = required_sensor_ids.iter().any(|sensor_id| {
+            !self
+                .providers
+                .iter()
+                .any(|registered| registered.provides(sensor_id))
         });
 
-    sensor_values
+        enabled_providers
+            .filter(|(_, registered)| {
+                is_unknown_sensor_required || registered.provides_any(required_sensor_ids)
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
 }
 
-/// Reads the static sensor values
-/// This is done only once at startup
-pub fn read_static_sensor_values(
-    root_shell_mutex: &Arc<Mutex<Option<RootShell>>>,
-) -> Vec<SensorValue> {
-    DmiDecodeSensors::new(root_shell_mutex.clone()).get_sensor_values()
+/// Returns the ids of all sensors, that are referenced by the elements of the display config
+pub fn get_referenced_sensor_ids(display_config: &DisplayConfig) -> HashSet<String> {
+    display_config
+        .elements
+        .iter()
+        .filter_map(|element| match element.element_type {
+            ElementType::Text => element
+                .text_config
+                .as_ref()
+                .map(|text_config| text_config.sensor_id.clone()),
+            ElementType::Graph => element
+                .graph_config
+                .as_ref()
+                .map(|graph_config| graph_config.sensor_id.clone()),
+            ElementType::ConditionalImage => element
+                .conditional_image_config
+                .as_ref()
+                .map(|conditional_image_config| conditional_image_config.sensor_id.clone()),
+            ElementType::StaticImage => None,
+        })
+        .collect()
 }
@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use sensor_core::SensorValue;
+use serde::{Deserialize, Serialize};
+
+/// A single sampled value of a sensor
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryEntry {
+    /// Milliseconds since the unix epoch
+    pub timestamp: u64,
+    pub value: String,
+}
+
+/// The sampled values of all sensors at the same time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistorySnapshot {
+    /// Milliseconds since the unix epoch
+    pub timestamp: u64,
+    pub sensor_values: Vec<SensorValue>,
+}
+
+/// The sampled values of a single sensor, oldest first
+struct SensorSeries {
+    /// The latest sampled value, which provides the label, unit and type of all entries
+    sensor_value: SensorValue,
+    entries: VecDeque<HistoryEntry>,
+}
+
+/// History of the sampled sensor values, as timestamped ring buffer per sensor.
+/// Entries older than the retention, relative to the latest sample, are dropped.
+/// Time windows are relative to the latest sample as well, so that they do not depend on the sample rate.
+pub struct SensorHistory {
+    retention: Duration,
+    series: HashMap<String, SensorSeries>,
+    /// Timestamp of the latest sample in milliseconds since the unix epoch
+    latest_timestamp: Option<u64>,
+}
+
+impl SensorHistory {
+    pub fn new(retention: Duration) -> SensorHistory {
+        SensorHistory {
+            retention,
+            series: HashMap::new(),
+            latest_timestamp: None,
+        }
+    }
+
+    /// Appends the sensor values, sampled at the specified time, to the history.
+    /// Removes all entries that are older than the retention.
+    pub fn push(&mut self, sampled_at: SystemTime, sensor_values: &[SensorValue]) {
+        let timestamp = to_timestamp(sampled_at);
+
+        for sensor_value in sensor_values {
+            let entry = HistoryEntry {
+                timestamp,
+                value: sensor_value.value.clone(),
+            };
+            match self.series.get_mut(&sensor_value.id) {
+                Some(series) => {
+                    series.sensor_value = sensor_value.clone();
+                    series.entries.push_back(entry);
+                }
+                None => {
+                    let series = SensorSeries {
+                        sensor_value: sensor_value.clone(),
+                        entries: VecDeque::from([entry]),
+                    };
+                    self.series.insert(sensor_value.id.clone(), series);
+                }
+            }
+        }
+
+        self.latest_timestamp = Some(timestamp);
+
+        // Drop expired entries, sensors without any entry are removed
+        let retention_start = self.get_window_start(Some(self.retention));
+        self.series.retain(|_, series| {
+            while series
+                .entries
+                .front()
+                .is_some_and(|entry| entry.timestamp < retention_start)
+            {
+                series.entries.pop_front();
+            }
+            !series.entries.is_empty()
+        });
+    }
+
+    /// Returns the entries of the sensor within the time window, newest first.
+    /// Returns the complete history of the sensor if no window is specified.
+    pub fn get_entries(&self, sensor_id: &str, window: Option<Duration>) -> Vec<HistoryEntry> {
+        let window_start = self.get_window_start(window);
+
+        match self.series.get(sensor_id) {
+            Some(series) => series
+                .entries
+                .iter()
+                .rev()
+                .take_while(|entry| entry.timestamp >= window_start)
+                .cloned()
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Returns the snapshots of all sensors within the time window, newest first.
+    /// Returns the complete history if no window is specified.
+    pub fn get_snapshots(&self, window: Option<Duration>) -> Vec<HistorySnapshot> {
+        let window_start = self.get_window_start(window);
+
+        let mut snapshots: BTreeMap<u64, Vec<SensorValue>> = BTreeMap::new();
+        for series in self.series.values() {
+            for entry in series.entries.iter().rev() {
+                if entry.timestamp < window_start {
+                    break;
+                }
+                snapshots
+                    .entry(entry.timestamp)
+                    .or_default()
+                    .push(SensorValue {
+                        value: entry.value.clone(),
+                        ..series.sensor_value.clone()
+                    });
+            }
+        }
+
+        snapshots
+            .into_iter()
+            .rev()
+            .map(|(timestamp, mut sensor_values)| {
+                sensor_values.sort_by(|a, b| a.label.cmp(&b.label));
+                HistorySnapshot {
+                    timestamp,
+                    sensor_values,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the sensor values of all snapshots within the time window, newest snapshot first.
+    /// This is the history format expected by the renderers of sensor-core.
+    pub fn get_sensor_values(&self, window: Option<Duration>) -> Vec<Vec<SensorValue>> {
+        self.get_snapshots(window)
+            .into_iter()
+            .map(|snapshot| snapshot.sensor_values)
+            .collect()
+    }
+
+    /// Returns the sensor values of the specified sensors, each within its own time window, newest snapshot first.
+    /// Only the entries of these sensors are copied, a sensor without window covers its complete history.
+    /// This is the history format expected by the renderers of sensor-core.
+    pub fn get_sensor_values_within(
+        &self,
+        sensor_windows: &HashMap<String, Option<Duration>>,
+    ) -> Vec<Vec<SensorValue>> {
+        let mut snapshots: BTreeMap<u64, Vec<SensorValue>> = BTreeMap::new();
+        for (sensor_id, window) in sensor_windows {
+            let series = match self.series.get(sensor_id) {
+                Some(series) => series,
+                None => continue,
+            };
+
+            for entry in self.get_entries(sensor_id, *window) {
+                snapshots
+                    .entry(entry.timestamp)
+                    .or_default()
+                    .push(SensorValue {
+                        value: entry.value,
+                        ..series.sensor_value.clone()
+                    });
+            }
+        }
+
+        snapshots
+            .into_values()
+            .rev()
+            .map(|mut sensor_values| {
+                sensor_values.sort_by(|a, b| a.label.cmp(&b.label));
+                sensor_values
+            })
+            .collect()
+    }
+
+    /// Returns the sensor values of the latest snapshot, or None if nothing was sampled yet
+    pub fn get_latest_snapshot(&self) -> Option<Vec<SensorValue>> {
+        self.latest_timestamp?;
+        let latest_snapshot = self
+            .get_sensor_values(Some(Duration::ZERO))
+            .into_iter()
+            .next();
+        Some(latest_snapshot.unwrap_or_default())
+    }
+
+    /// Returns the oldest timestamp within the time window, relative to the latest sample
+    fn get_window_start(&self, window: Option<Duration>) -> u64 {
+        match (self.latest_timestamp, window) {
+            (Some(latest_timestamp), Some(window)) => {
+                latest_timestamp.saturating_sub(window.as_millis() as u64)
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// Converts milliseconds since the unix epoch to the time
+pub fn from_timestamp(timestamp: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(timestamp)
+}
+
+/// Converts the time to milliseconds since the unix epoch
+pub fn to_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
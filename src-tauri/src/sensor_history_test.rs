@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use assertor::{assert_that, EqualityAssertion};
+
+use crate::sensor_history::{HistoryEntry, SensorHistory};
+use crate::test_utils::create_sensor_value;
+
+fn at_second(second: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(second)
+}
+
+fn create_entry(second: u64, value: &str) -> HistoryEntry {
+    HistoryEntry {
+        timestamp: second * 1000,
+        value: value.to_string(),
+    }
+}
+
+#[test]
+fn test_get_entries_within_window() {
+    // GIVEN is a history with a sample per second
+    let mut history = SensorHistory::new(Duration::from_secs(60));
+    for second in 1..=5 {
+        history.push(
+            at_second(second),
+            &[create_sensor_value("cpu", &second.to_string(), "%")],
+        );
+    }
+
+    // WHEN querying the last 2 seconds and the complete history
+    let window_entries = history.get_entries("cpu", Some(Duration::from_secs(2)));
+    let all_entries = history.get_entries("cpu", None);
+
+    // THEN the entries should be returned newest first
+    assert_that!(window_entries).is_equal_to(vec![
+        create_entry(5, "5"),
+        create_entry(4, "4"),
+        create_entry(3, "3"),
+    ]);
+    assert_that!(all_entries.len()).is_equal_to(5);
+}
+
+#[test]
+fn test_push_drops_expired_entries() {
+    // GIVEN is a history with a retention of 10 seconds
+    let mut history = SensorHistory::new(Duration::from_secs(10));
+
+    // WHEN a sensor disappears and samples are pushed for longer than the retention
+    history.push(at_second(0), &[create_sensor_value("gpu", "1", "%")]);
+    for second in 0..=30 {
+        history.push(
+            at_second(second),
+            &[create_sensor_value("cpu", &second.to_string(), "%")],
+        );
+    }
+
+    // THEN only the entries within the retention should be kept
+    let entries = history.get_entries("cpu", None);
+    assert_that!(entries.len()).is_equal_to(11);
+    assert_that!(entries.last().cloned()).is_equal_to(Some(create_entry(20, "20")));
+    assert_that!(history.get_entries("gpu", None).len()).is_equal_to(0);
+}
+
+#[test]
+fn test_get_sensor_values_groups_by_timestamp() {
+    // GIVEN is a history, where a sensor was added later
+    let mut history = SensorHistory::new(Duration::from_secs(60));
+    history.push(at_second(1), &[create_sensor_value("cpu", "10", "%")]);
+    history.push(
+        at_second(2),
+        &[
+            create_sensor_value("cpu", "20", "%"),
+            create_sensor_value("gpu", "30", "%"),
+        ],
+    );
+
+    // WHEN reading the snapshots
+    let sensor_values = history.get_sensor_values(None);
+
+    // THEN each sample should be a snapshot, newest first, sorted by label
+    assert_that!(sensor_values.len()).is_equal_to(2);
+    let newest: Vec<String> = sensor_values[0].iter().map(|v| v.value.clone()).collect();
+    let oldest: Vec<String> = sensor_values[1].iter().map(|v| v.value.clone()).collect();
+    assert_that!(newest).is_equal_to(vec!["20".to_string(), "30".to_string()]);
+    assert_that!(oldest).is_equal_to(vec!["10".to_string()]);
+}
+
+#[test]
+fn test_get_latest_snapshot() {
+    // GIVEN is an empty history
+    let mut history = SensorHistory::new(Duration::from_secs(60));
+    let empty_snapshot = history.get_latest_snapshot();
+
+    // WHEN pushing two samples
+    history.push(at_second(1), &[create_sensor_value("cpu", "10", "%")]);
+    history.push(at_second(2), &[create_sensor_value("cpu", "20", "%")]);
+
+    // THEN only the values of the latest sample should be returned
+    let latest_snapshot = history.get_latest_snapshot().unwrap();
+    assert_that!(empty_snapshot.is_none()).is_equal_to(true);
+    assert_that!(latest_snapshot.len()).is_equal_to(1);
+    assert_that!(latest_snapshot[0].value.clone()).is_equal_to("20".to_string());
+}
+
+#[test]
+fn test_get_sensor_values_within_sensor_windows() {
+    // GIVEN is a history of three sensors with a sample per second
+    let mut history = SensorHistory::new(Duration::from_secs(60));
+    for second in 1..=5 {
+        history.push(
+            at_second(second),
+            &[
+                create_sensor_value("cpu", &format!("c{second}"), "%"),
+                create_sensor_value("gpu", &format!("g{second}"), "%"),
+                create_sensor_value("ram", &format!("r{second}"), "%"),
+            ],
+        );
+    }
+
+    // WHEN reading the complete history of cpu and the last second of gpu
+    let sensor_windows = HashMap::from([
+        ("cpu".to_string(), None),
+        ("gpu".to_string(), Some(Duration::from_secs(1))),
+    ]);
+    let sensor_values = history.get_sensor_values_within(&sensor_windows);
+
+    // THEN only the entries of these sensors within their windows should be returned, newest first
+    let values: Vec<Vec<String>> = sensor_values
+        .iter()
+        .map(|snapshot| snapshot.iter().map(|v| v.value.clone()).collect())
+        .collect();
+    assert_that!(values).is_equal_to(vec![
+        vec!["c5".to_string(), "g5".to_string()],
+        vec!["c4".to_string(), "g4".to_string()],
+        vec!["c3".to_string()],
+        vec!["c2".to_string()],
+        vec!["c1".to_string()],
+    ]);
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+
+use sensor_core::{SensorType, SensorValue};
+
+use crate::config::SensorOverrideConfig;
+use crate::{units, utils};
+
+/// Applies the label, unit conversion, scale, offset and precision overrides to the sensor values.
+/// Hidden sensors are kept, so that derived sensors can still use them, see `remove_hidden`.
+pub fn apply(
+    sensor_overrides: &HashMap<String, SensorOverrideConfig>,
+    sensor_values: &mut [SensorValue],
+) {
+    if sensor_overrides.is_empty() {
+        return;
+    }
+
+    for sensor_value in sensor_values.iter_mut() {
+        if let Some(sensor_override) = sensor_overrides.get(&sensor_value.id) {
+            apply_override(sensor_override, sensor_value);
+        }
+    }
+}
+
+/// Removes all sensors, that are hidden by their override
+pub fn remove_hidden(
+    sensor_overrides: &HashMap<String, SensorOverrideConfig>,
+    sensor_values: &mut Vec<SensorValue>,
+) {
+    sensor_values.retain(|sensor_value| {
+        sensor_overrides
+            .get(&sensor_value.id)
+            .is_none_or(|sensor_override| !sensor_override.hidden)
+    });
+}
+
+/// Applies the override to a single sensor value.
+/// The value of text sensors and values, that are not a number, are left unchanged.
+fn apply_override(sensor_override: &SensorOverrideConfig, sensor_value: &mut SensorValue) {
+    if let Some(label) = &sensor_override.label {
+        sensor_value.label = label.clone();
+    }
+
+    let changes_value = sensor_override.unit.is_some()
+        || sensor_override.scale.is_some()
+        || sensor_override.offset.is_some()
+        || sensor_override.precision.is_some();
+    if !changes_value {
+        return;
+    }
+
+    let value = if matches!(sensor_value.sensor_type, SensorType::Number) {
+        sensor_value.value.trim().parse::<f64>().ok()
+    } else {
+        None
+    };
+    let mut value = match value {
+        Some(value) => value,
+        None => {
+            if let Some(unit) = &sensor_override.unit {
+                sensor_value.unit = unit.clone();
+            }
+            return;
+        }
+    };
+
+    if let Some(unit) = &sensor_override.unit {
+        if let Some(converted_value) = units::convert(value, &sensor_value.unit, unit) {
+            value = converted_value;
+        }
+        sensor_value.unit = unit.clone();
+    }
+    value = value * sensor_override.scale.unwrap_or(1.0) + sensor_override.offset.unwrap_or(0.0);
+
+    sensor_value.value = match sensor_override.precision {
+        Some(precision) => format!("{value:.precision$}"),
+        None => utils::format_number(value),
+    };
+}
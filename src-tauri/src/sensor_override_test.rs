@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use assertor::{assert_that, EqualityAssertion};
+use sensor_core::SensorValue;
+
+use crate::config::SensorOverrideConfig;
+use crate::sensor_override;
+use crate::test_utils::{create_sensor_value, create_text_sensor_value};
+
+/// Applies the override to the sensor value and returns value, unit and label
+fn apply(
+    sensor_override: SensorOverrideConfig,
+    sensor_value: SensorValue,
+) -> (String, String, String) {
+    let sensor_overrides = HashMap::from([(sensor_value.id.clone(), sensor_override)]);
+    let mut sensor_values = vec![sensor_value];
+
+    sensor_override::apply(&sensor_overrides, &mut sensor_values);
+
+    let sensor_value = sensor_values.remove(0);
+    (sensor_value.value, sensor_value.unit, sensor_value.label)
+}
+
+#[test]
+fn test_apply_unit_conversion() {
+    // GIVEN are sensors in base units
+    let temperature = create_sensor_value("cpu_temp", "20", "°C");
+    let network = create_sensor_value("net_rx", "1250000", "B/s");
+    let frequency = create_sensor_value("cpu0-frequency", "1512000000", "Hz");
+
+    // WHEN converting them to other units
+    let fahrenheit = apply(
+        SensorOverrideConfig {
+            unit: Some("°F".to_string()),
+            ..Default::default()
+        },
+        temperature,
+    );
+    let megabits = apply(
+        SensorOverrideConfig {
+            unit: Some("Mbit/s".to_string()),
+            ..Default::default()
+        },
+        network,
+    );
+    let gigahertz = apply(
+        SensorOverrideConfig {
+            unit: Some("GHz".to_string()),
+            precision: Some(1),
+            ..Default::default()
+        },
+        frequency,
+    );
+
+    // THEN the values should be converted and formatted with the precision
+    assert_that!(fahrenheit.0).is_equal_to("68".to_string());
+    assert_that!(fahrenheit.1).is_equal_to("°F".to_string());
+    assert_that!(megabits.0).is_equal_to("10".to_string());
+    assert_that!(gigahertz.0).is_equal_to("1.5".to_string());
+    assert_that!(gigahertz.1).is_equal_to("GHz".to_string());
+}
+
+#[test]
+fn test_apply_label_scale_and_offset() {
+    // GIVEN is a fan sensor with an unreadable id
+    let fan = create_sensor_value("lm_nct6798-isa-0290-fan2", "1200", "RPM");
+
+    // WHEN relabeling it and scaling it to a custom unit
+    let (value, unit, label) = apply(
+        SensorOverrideConfig {
+            label: Some("Pump".to_string()),
+            unit: Some("kRPM".to_string()),
+            scale: Some(0.001),
+            offset: Some(0.5),
+            ..Default::default()
+        },
+        fan,
+    );
+
+    // THEN the unknown unit should only be replaced and the value should be scaled and offset
+    assert_that!(value).is_equal_to("1.7".to_string());
+    assert_that!(unit).is_equal_to("kRPM".to_string());
+    assert_that!(label).is_equal_to("Pump".to_string());
+}
+
+#[test]
+fn test_apply_label_keeps_value() {
+    // GIVEN are a number and a text sensor
+    let load = create_sensor_value("cpu_load", "12.345", "%");
+    let os = create_text_sensor_value("os", "Linux");
+
+    // WHEN only relabeling them, or scaling the text sensor
+    let load = apply(
+        SensorOverrideConfig {
+            label: Some("CPU".to_string()),
+            ..Default::default()
+        },
+        load,
+    );
+    let os = apply(
+        SensorOverrideConfig {
+            scale: Some(2.0),
+            ..Default::default()
+        },
+        os,
+    );
+
+    // THEN the values should be left unchanged
+    assert_that!(load.0).is_equal_to("12.345".to_string());
+    assert_that!(os.0).is_equal_to("Linux".to_string());
+}
+
+#[test]
+fn test_remove_hidden() {
+    // GIVEN are two sensors, one of them hidden
+    let sensor_overrides = HashMap::from([(
+        "fan1".to_string(),
+        SensorOverrideConfig {
+            hidden: true,
+            ..Default::default()
+        },
+    )]);
+    let mut sensor_values = vec![
+        create_sensor_value("fan1", "0", "RPM"),
+        create_sensor_value("fan2", "800", "RPM"),
+    ];
+
+    // WHEN removing the hidden sensors
+    sensor_override::remove_hidden(&sensor_overrides, &mut sensor_values);
+
+    // THEN only the visible sensor should remain
+    assert_that!(sensor_values.len()).is_equal_to(1);
+    assert_that!(sensor_values[0].id.clone()).is_equal_to("fan2".to_string());
+}
@@ -0,0 +1,444 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use log::{debug, warn};
+use sensor_core::SensorValue;
+
+use crate::config::SensorOverrideConfig;
+use crate::derived_sensor;
+use crate::derived_sensor::DerivedSensor;
+use crate::sensor::{SensorProvider, SensorProviderInfo, SensorProviderRegistry};
+use crate::sensor_history;
+use crate::sensor_history::{HistorySnapshot, SensorHistory};
+use crate::sensor_override;
+use crate::sensor_transform::SensorTransforms;
+use crate::utils::LockResultExt;
+
+/// Interval in which a snapshot of the sensor values is added to the history
+pub const SAMPLE_RATE: Duration = Duration::from_millis(1000);
+
+/// Interval in which the sampler checks, which providers are due to be read
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+/// Duration for which sampled sensor values are kept in the history
+pub const SENSOR_VALUE_HISTORY_RETENTION: Duration = Duration::from_secs(1000);
+
+/// The sensors a consumer of the sampled sensor values requires
+pub enum SensorDemand {
+    All,
+    Only(HashSet<String>),
+}
+
+/// A registered consumer of the sampled sensor values
+struct Consumer {
+    sensor_demand: SensorDemand,
+    /// Consumers that poll, like the lcd preview, are dropped if they stop renewing their lease
+    expires_at: Option<Instant>,
+}
+
+/// Background sampler that owns the sensor value history.
+/// Each provider is read in its own preferred interval, regardless of how many consumers exist.
+/// Network devices, the lcd preview and the UI only read the latest snapshot or the history.
+/// Providers are only read if at least one registered consumer requires one of their sensors.
+pub struct SensorSampler {
+    sensor_provider_registry: Mutex<SensorProviderRegistry>,
+    latest_sensor_values: Mutex<Vec<SensorValue>>,
+    sensor_value_history: Mutex<SensorHistory>,
+    /// Number of snapshots added to the history, to detect new samples
+    sample_count: AtomicU64,
+    /// Notified whenever a snapshot was added to the history
+    new_sample: Condvar,
+    consumers: Mutex<HashMap<String, Consumer>>,
+    /// Sensors computed from the provided sensor values
+    derived_sensors: Mutex<Vec<DerivedSensor>>,
+    /// Smoothed, peak-hold and session min/max variants of sensors
+    sensor_transforms: Mutex<SensorTransforms>,
+    /// User overrides of the label, unit and value of sensors, by sensor id
+    sensor_overrides: Mutex<HashMap<String, SensorOverrideConfig>>,
+}
+
+impl SensorSampler {
+    pub fn new(sensor_provider_registry: SensorProviderRegistry) -> SensorSampler {
+        SensorSampler {
+            sensor_provider_registry: Mutex::new(sensor_provider_registry),
+            latest_sensor_values: Mutex::new(vec![]),
+            sensor_value_history: Mutex::new(SensorHistory::new(SENSOR_VALUE_HISTORY_RETENTION)),
+            sample_count: AtomicU64::new(0),
+            new_sample: Condvar::new(),
+            consumers: Mutex::new(HashMap::new()),
+            derived_sensors: Mutex::new(vec![]),
+            sensor_transforms: Mutex::new(SensorTransforms::default()),
+            sensor_overrides: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Starts the sampling thread.
+    /// All providers are discovered and the first sample is taken synchronously,
+    /// so that consumers always find a snapshot.
+    /// The thread runs until the application exits.
+    pub fn start(self: &Arc<Self>) -> thread::JoinHandle<()> {
+        self.discover();
+        self.sample();
+
+        let sensor_sampler = self.clone();
+        thread::spawn(move || {
+            let mut last_sample_time = Instant::now();
+            loop {
+                let start_time = Instant::now();
+
+                sensor_sampler.tick();
+                if start_time.duration_since(last_sample_time) + TICK_RATE / 2 >= SAMPLE_RATE {
+                    sensor_sampler.sample();
+                    last_sample_time = start_time;
+                }
+
+                // Keep a constant tick rate, by subtracting the time it took to read the sensors
+                let processing_duration = Instant::now().duration_since(start_time);
+                debug!("Sampling duration: {:?}", processing_duration);
+                let time_to_wait =
+                    TICK_RATE
+                        .checked_sub(processing_duration)
+                        .unwrap_or_else(|| {
+                            warn!("Sampling duration is longer than the tick interval");
+                            Duration::ZERO
+                        });
+                thread::sleep(time_to_wait);
+            }
+        })
+    }
+
+    /// Reads all enabled providers, regardless of the consumers and their intervals.
+    /// Returns all available sensor values, without adding them to the history.
+    /// This is used for one-time reads, like the command line.
+    pub fn discover(&self) -> Vec<SensorValue> {
+        let providers = self
+            .sensor_provider_registry
+            .lock()
+            .ignore_poison()
+            .get_enabled_providers();
+
+        self.discover_providers(providers)
+    }
+
+    /// Returns all available sensor values, as last read from each enabled provider.
+    /// Only providers that were not read yet are read. The others are only read in their interval
+    /// while their sensors are required, thus the values of the other sensors may be outdated.
+    /// This is used to list all sensors in the designer.
+    pub fn list_sensors(&self) -> Vec<SensorValue> {
+        let providers = self
+            .sensor_provider_registry
+            .lock()
+            .ignore_poison()
+            .get_unread_providers();
+
+        self.discover_providers(providers)
+    }
+
+    /// Reads all sensors of the specified providers.
+    /// Returns the last read sensor values of all enabled providers.
+    fn discover_providers(
+        &self,
+        providers: Vec<(usize, Arc<dyn SensorProvider>)>,
+    ) -> Vec<SensorValue> {
+        // Read the providers without holding the lock, as reading may take a while
+        let read_at = Instant::now();
+        let provider_sensor_values: Vec<(usize, Vec<SensorValue>)> = providers
+            .into_iter()
+            .map(|(index, provider)| (index, provider.discover()))
+            .collect();
+
+        let mut sensor_provider_registry = self.sensor_provider_registry.lock().ignore_poison();
+        for (index, sensor_values) in provider_sensor_values {
+            sensor_provider_registry.update(index, sensor_values, read_at);
+        }
+        let mut sensor_values = sensor_provider_registry.get_sensor_values(&None);
+        drop(sensor_provider_registry);
+
+        self.process_sensor_values(&mut sensor_values);
+        sort_sensor_values(&mut sensor_values);
+        self.update_latest_sensor_values();
+
+        sensor_values
+    }
+
+    /// Reads all required providers whose interval elapsed and updates the latest snapshot.
+    /// This is called by the sampling thread with every tick.
+    pub fn tick(&self) {
+        let required_sensor_ids = self.get_required_sensor_ids();
+        let due_providers = self
+            .sensor_provider_registry
+            .lock()
+            .ignore_poison()
+            .get_due_providers(&required_sensor_ids, Instant::now(), TICK_RATE / 2);
+
+        if due_providers.is_empty() {
+            return;
+        }
+
+        // Read the providers without holding the lock, as reading may take a while
+        for (index, provider) in due_providers {
+            let read_at = Instant::now();
+            let sensor_values = provider.read();
+            self.sensor_provider_registry.lock().ignore_poison().update(
+                index,
+                sensor_values,
+                read_at,
+            );
+        }
+
+        self.update_latest_sensor_values();
+    }
+
+    /// Combines the last read values of all required providers to the latest snapshot.
+    fn update_latest_sensor_values(&self) {
+        let required_sensor_ids = self.get_required_sensor_ids();
+        let mut sensor_values = self
+            .sensor_provider_registry
+            .lock()
+            .ignore_poison()
+            .get_sensor_values(&required_sensor_ids);
+
+        self.process_sensor_values(&mut sensor_values);
+        sort_sensor_values(&mut sensor_values);
+
+        *self.latest_sensor_values.lock().ignore_poison() = sensor_values;
+    }
+
+    /// Applies the user overrides to the provided sensor values and adds the computed sensors.
+    /// Derived sensors are computed before the transformed sensors, so that they can be smoothed.
+    /// Hidden sensors are removed last, so that computed sensors can still use them.
+    fn process_sensor_values(&self, sensor_values: &mut Vec<SensorValue>) {
+        let sensor_overrides = self.sensor_overrides.lock().ignore_poison().clone();
+        sensor_override::apply(&sensor_overrides, sensor_values);
+
+        let derived_sensor_values =
+            derived_sensor::evaluate(&self.derived_sensors.lock().ignore_poison(), sensor_values);
+        sensor_values.extend(derived_sensor_values);
+
+        let sensor_value_history = self.sensor_value_history.lock().ignore_poison();
+        let transformed_sensor_values = self.sensor_transforms.lock().ignore_poison().update(
+            sensor_values,
+            &sensor_value_history,
+            Instant::now(),
+        );
+        drop(sensor_value_history);
+        sensor_values.extend(transformed_sensor_values);
+
+        sensor_override::remove_hidden(&sensor_overrides, sensor_values);
+    }
+
+    /// Appends the latest snapshot with the current time to the history.
+    /// Values older than the retention are removed from the history.
+    fn sample(&self) {
+        let sensor_values = self.latest();
+
+        let mut sensor_value_history = self.sensor_value_history.lock().ignore_poison();
+        sensor_value_history.push(SystemTime::now(), &sensor_values);
+
+        self.sample_count.fetch_add(1, Ordering::Relaxed);
+        self.new_sample.notify_all();
+    }
+
+    /// Adds previously stored snapshots to the history, oldest snapshot first.
+    /// This must happen before the sampler starts, as the history only accepts newer samples.
+    pub fn restore_history(&self, snapshots: Vec<HistorySnapshot>) {
+        let mut sensor_value_history = self.sensor_value_history.lock().ignore_poison();
+        for snapshot in snapshots {
+            sensor_value_history.push(
+                sensor_history::from_timestamp(snapshot.timestamp),
+                &snapshot.sensor_values,
+            );
+        }
+    }
+
+    /// Blocks until the next snapshot was added to the history, at most for the specified timeout.
+    /// Returns the new snapshot, or None if the timeout elapsed.
+    pub fn wait_for_next_sample(&self, timeout: Duration) -> Option<Vec<SensorValue>> {
+        let sensor_value_history = self.sensor_value_history.lock().ignore_poison();
+        let sample_count = self.sample_count.load(Ordering::Relaxed);
+
+        let (sensor_value_history, wait_result) = self
+            .new_sample
+            .wait_timeout_while(sensor_value_history, timeout, |_| {
+                self.sample_count.load(Ordering::Relaxed) == sample_count
+            })
+            .ignore_poison();
+
+        if wait_result.timed_out() {
+            return None;
+        }
+
+        sensor_value_history.get_latest_snapshot()
+    }
+
+    /// Returns the information of all sensor providers
+    pub fn get_provider_infos(&self) -> Vec<SensorProviderInfo> {
+        self.sensor_provider_registry
+            .lock()
+            .ignore_poison()
+            .get_provider_infos()
+    }
+
+    /// Enables or disables the sensor provider with the specified name.
+    /// Returns false if no provider with the specified name exists.
+    pub fn set_provider_enabled(&self, provider_name: &str, enabled: bool) -> bool {
+        let updated = self
+            .sensor_provider_registry
+            .lock()
+            .ignore_poison()
+            .set_enabled(provider_name, enabled);
+
+        if updated {
+            self.update_latest_sensor_values();
+        }
+
+        updated
+    }
+
+    /// Sets the derived sensors, which are computed with every update of the latest snapshot
+    pub fn set_derived_sensors(&self, derived_sensors: Vec<DerivedSensor>) {
+        *self.derived_sensors.lock().ignore_poison() = derived_sensors;
+        self.update_latest_sensor_values();
+    }
+
+    /// Sets the sensor transforms, their state starts over
+    pub fn set_sensor_transforms(&self, sensor_transforms: SensorTransforms) {
+        *self.sensor_transforms.lock().ignore_poison() = sensor_transforms;
+        self.update_latest_sensor_values();
+    }
+
+    /// Sets the user overrides of the sensors, by sensor id
+    pub fn set_sensor_overrides(&self, sensor_overrides: HashMap<String, SensorOverrideConfig>) {
+        *self.sensor_overrides.lock().ignore_poison() = sensor_overrides;
+        self.update_latest_sensor_values();
+    }
+
+    /// Resets the session min and max of all transformed sensors
+    pub fn reset_sensor_sessions(&self) {
+        self.sensor_transforms
+            .lock()
+            .ignore_poison()
+            .reset_session();
+        self.update_latest_sensor_values();
+    }
+
+    /// Returns the union of the sensor ids required by all consumers.
+    /// Required transformed sensors are replaced by their source sensor.
+    /// Required derived sensors add the sensors their expression depends on, and are removed afterwards,
+    /// as no provider produces them and they would otherwise require all providers.
+    /// Returns None if at least one consumer requires all sensors.
+    /// Consumers with an expired lease are removed.
+    fn get_required_sensor_ids(&self) -> Option<HashSet<String>> {
+        let mut required_sensor_ids = HashSet::new();
+        let mut consumers = self.consumers.lock().ignore_poison();
+
+        let now = Instant::now();
+        consumers.retain(|_, consumer| {
+            consumer
+                .expires_at
+                .is_none_or(|expires_at| expires_at > now)
+        });
+
+        for consumer in consumers.values() {
+            match &consumer.sensor_demand {
+                SensorDemand::All => return None,
+                SensorDemand::Only(sensor_ids) => {
+                    required_sensor_ids.extend(sensor_ids.iter().cloned())
+                }
+            }
+        }
+
+        drop(consumers);
+
+        let sensor_transforms = self.sensor_transforms.lock().ignore_poison();
+        let source_sensor_ids = sensor_transforms.get_source_sensor_ids(&required_sensor_ids);
+        let transformed_sensor_ids = sensor_transforms.get_sensor_ids();
+        drop(sensor_transforms);
+        required_sensor_ids.retain(|sensor_id| !transformed_sensor_ids.contains(sensor_id));
+        required_sensor_ids.extend(source_sensor_ids);
+
+        let derived_sensors = self.derived_sensors.lock().ignore_poison();
+        let required_derived_sensors: Vec<&DerivedSensor> = derived_sensors
+            .iter()
+            .filter(|derived_sensor| required_sensor_ids.contains(&derived_sensor.get_sensor_id()))
+            .collect();
+        if !required_derived_sensors.is_empty() {
+            let known_sensor_ids = self
+                .sensor_provider_registry
+                .lock()
+                .ignore_poison()
+                .get_known_sensor_ids();
+            for derived_sensor in required_derived_sensors {
+                required_sensor_ids.remove(&derived_sensor.get_sensor_id());
+                required_sensor_ids.extend(derived_sensor.get_dependencies(&known_sensor_ids));
+            }
+        }
+
+        Some(required_sensor_ids)
+    }
+
+    /// Registers a consumer and the sensors it requires, until it is unregistered.
+    /// An already registered consumer with the same id is replaced.
+    pub fn register_consumer(&self, consumer_id: &str, sensor_demand: SensorDemand) {
+        let consumer = Consumer {
+            sensor_demand,
+            expires_at: None,
+        };
+        self.consumers
+            .lock()
+            .ignore_poison()
+            .insert(consumer_id.to_string(), consumer);
+    }
+
+    /// Registers a consumer and the sensors it requires, for the duration of the lease.
+    /// Polling consumers renew their lease with every poll.
+    /// An already registered consumer with the same id is replaced.
+    /// Returns true if the consumer renewed an active lease, thus its sensors are already sampled.
+    pub fn register_consumer_with_lease(
+        &self,
+        consumer_id: &str,
+        sensor_demand: SensorDemand,
+        lease: Duration,
+    ) -> bool {
+        let now = Instant::now();
+        let consumer = Consumer {
+            sensor_demand,
+            expires_at: Some(now + lease),
+        };
+        let previous_consumer = self
+            .consumers
+            .lock()
+            .ignore_poison()
+            .insert(consumer_id.to_string(), consumer);
+
+        previous_consumer.is_some_and(|previous_consumer| {
+            previous_consumer
+                .expires_at
+                .is_none_or(|expires_at| expires_at > now)
+        })
+    }
+
+    /// Removes the consumer, its sensors are no longer sampled if no other consumer requires them.
+    pub fn unregister_consumer(&self, consumer_id: &str) {
+        self.consumers.lock().ignore_poison().remove(consumer_id);
+    }
+
+    /// Returns the latest sampled sensor values.
+    /// Returns an empty vector if nothing was sampled yet.
+    pub fn latest(&self) -> Vec<SensorValue> {
+        self.latest_sensor_values.lock().ignore_poison().clone()
+    }
+
+    /// Returns the sensor value history, which can be queried for time windows.
+    pub fn history(&self) -> MutexGuard<'_, SensorHistory> {
+        self.sensor_value_history.lock().ignore_poison()
+    }
+}
+
+/// Sorts the sensor values by label
+fn sort_sensor_values(sensor_values: &mut [SensorValue]) {
+    sensor_values.sort_by(|a, b| a.label.cmp(&b.label));
+}
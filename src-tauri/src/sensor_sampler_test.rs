@@ -0,0 +1,195 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use assertor::{assert_that, BooleanAssertion, EqualityAssertion};
+use sensor_core::{SensorType, SensorValue};
+
+use crate::config::{DerivedSensorConfig, SensorTransform, SensorTransformConfig};
+use crate::derived_sensor;
+use crate::sensor::{SensorProvider, SensorProviderRegistry};
+use crate::sensor_sampler::{SensorDemand, SensorSampler};
+use crate::sensor_transform::SensorTransforms;
+
+const LEASE: Duration = Duration::from_secs(60);
+
+/// A provider with a single sensor named after the provider, that is due with every tick
+struct CountingProvider {
+    name: &'static str,
+    read_count: Arc<AtomicUsize>,
+}
+
+impl SensorProvider for CountingProvider {
+    fn get_name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn get_preferred_interval(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn read(&self) -> Vec<SensorValue> {
+        self.read_count.fetch_add(1, Ordering::Relaxed);
+        vec![SensorValue {
+            id: format!("{}_value", self.name),
+            value: "1".to_string(),
+            unit: "".to_string(),
+            label: self.name.to_string(),
+            sensor_type: SensorType::Number,
+        }]
+    }
+}
+
+/// Creates a sampler with the providers `a` and `b`, and returns their read counters
+fn create_sampler() -> (SensorSampler, Arc<AtomicUsize>, Arc<AtomicUsize>) {
+    let read_count_a = Arc::new(AtomicUsize::new(0));
+    let read_count_b = Arc::new(AtomicUsize::new(0));
+    let registry = SensorProviderRegistry::with_providers(
+        vec![
+            Arc::new(CountingProvider {
+                name: "a",
+                read_count: read_count_a.clone(),
+            }),
+            Arc::new(CountingProvider {
+                name: "b",
+                read_count: read_count_b.clone(),
+            }),
+        ],
+        &[],
+    );
+
+    (SensorSampler::new(registry), read_count_a, read_count_b)
+}
+
+fn get_ids(sensor_values: Vec<SensorValue>) -> Vec<String> {
+    sensor_values
+        .into_iter()
+        .map(|sensor_value| sensor_value.id)
+        .collect()
+}
+
+fn only(ids: &[&str]) -> SensorDemand {
+    SensorDemand::Only(
+        ids.iter()
+            .map(|id| id.to_string())
+            .collect::<HashSet<String>>(),
+    )
+}
+
+#[test]
+fn test_tick_reads_only_required_providers() {
+    // GIVEN is a sampler whose providers were discovered, and a consumer of a sensor of provider a
+    let (sensor_sampler, read_count_a, read_count_b) = create_sampler();
+    sensor_sampler.discover();
+    sensor_sampler.register_consumer("display", only(&["a_value"]));
+
+    // WHEN ticking
+    sensor_sampler.tick();
+
+    // THEN only provider a should be read again, and only its sensor should be sampled
+    assert_that!(read_count_a.load(Ordering::Relaxed)).is_equal_to(2);
+    assert_that!(read_count_b.load(Ordering::Relaxed)).is_equal_to(1);
+    assert_that!(get_ids(sensor_sampler.latest())).is_equal_to(vec!["a_value".to_string()]);
+}
+
+#[test]
+fn test_expired_lease_is_not_sampled() {
+    // GIVEN is a sampler whose providers were discovered, and a consumer whose lease expired
+    let (sensor_sampler, read_count_a, read_count_b) = create_sampler();
+    sensor_sampler.discover();
+    sensor_sampler.register_consumer_with_lease("preview", only(&["a_value"]), Duration::ZERO);
+
+    // WHEN ticking
+    sensor_sampler.tick();
+
+    // THEN no provider should be read
+    assert_that!(read_count_a.load(Ordering::Relaxed)).is_equal_to(1);
+    assert_that!(read_count_b.load(Ordering::Relaxed)).is_equal_to(1);
+}
+
+#[test]
+fn test_register_consumer_with_lease_reports_renewal() {
+    // GIVEN is a sampler
+    let (sensor_sampler, _, _) = create_sampler();
+
+    // WHEN registering a consumer, renewing its lease, and registering it again after its lease expired
+    let first = sensor_sampler.register_consumer_with_lease("metrics", SensorDemand::All, LEASE);
+    let renewed =
+        sensor_sampler.register_consumer_with_lease("metrics", SensorDemand::All, Duration::ZERO);
+    let after_expiry =
+        sensor_sampler.register_consumer_with_lease("metrics", SensorDemand::All, LEASE);
+
+    // THEN only the renewal of the active lease should be reported
+    assert_that!(first).is_false();
+    assert_that!(renewed).is_true();
+    assert_that!(after_expiry).is_false();
+}
+
+#[test]
+fn test_list_sensors_reads_only_unread_providers() {
+    // GIVEN is a sampler whose providers were discovered
+    let (sensor_sampler, read_count_a, read_count_b) = create_sampler();
+    sensor_sampler.discover();
+
+    // WHEN listing the sensors, and listing them again after provider b was disabled and enabled
+    let listed = get_ids(sensor_sampler.list_sensors());
+    let read_count_after_listing = read_count_b.load(Ordering::Relaxed);
+    sensor_sampler.set_provider_enabled("b", false);
+    sensor_sampler.set_provider_enabled("b", true);
+    let listed_after_enabling = get_ids(sensor_sampler.list_sensors());
+
+    // THEN the cached sensor values should be listed, and only the re-enabled provider should be read
+    assert_that!(listed).is_equal_to(vec!["a_value".to_string(), "b_value".to_string()]);
+    assert_that!(read_count_after_listing).is_equal_to(1);
+    assert_that!(listed_after_enabling)
+        .is_equal_to(vec!["a_value".to_string(), "b_value".to_string()]);
+    assert_that!(read_count_a.load(Ordering::Relaxed)).is_equal_to(1);
+    assert_that!(read_count_b.load(Ordering::Relaxed)).is_equal_to(2);
+}
+
+#[test]
+fn test_tick_reads_only_dependencies_of_derived_sensors() {
+    // GIVEN is a sampler whose providers were discovered, and a consumer of a derived sensor of provider a
+    let (sensor_sampler, read_count_a, read_count_b) = create_sampler();
+    sensor_sampler.discover();
+    sensor_sampler.set_derived_sensors(derived_sensor::create_all(&[DerivedSensorConfig {
+        id: "double_a".to_string(),
+        label: "Double a".to_string(),
+        unit: "".to_string(),
+        expression: "{a_value} * 2".to_string(),
+    }]));
+    sensor_sampler.register_consumer("display", only(&["derived_double_a"]));
+
+    // WHEN ticking
+    sensor_sampler.tick();
+
+    // THEN only provider a should be read again, and the derived sensor should be computed from it
+    assert_that!(read_count_a.load(Ordering::Relaxed)).is_equal_to(2);
+    assert_that!(read_count_b.load(Ordering::Relaxed)).is_equal_to(1);
+    assert_that!(get_ids(sensor_sampler.latest()))
+        .is_equal_to(vec!["derived_double_a".to_string(), "a_value".to_string()]);
+}
+
+#[test]
+fn test_tick_reads_only_sources_of_transformed_sensors() {
+    // GIVEN is a sampler whose providers were discovered, and a consumer of a transformed sensor of provider a
+    let (sensor_sampler, read_count_a, read_count_b) = create_sampler();
+    sensor_sampler.discover();
+    sensor_sampler.set_sensor_transforms(SensorTransforms::new(&[SensorTransformConfig {
+        sensor_id: "a_value".to_string(),
+        transform: SensorTransform::SessionMax,
+    }]));
+    sensor_sampler.register_consumer("display", only(&["transform_a_value_session_max"]));
+
+    // WHEN ticking
+    sensor_sampler.tick();
+
+    // THEN only provider a should be read again, and the transformed sensor should be computed from it
+    assert_that!(read_count_a.load(Ordering::Relaxed)).is_equal_to(2);
+    assert_that!(read_count_b.load(Ordering::Relaxed)).is_equal_to(1);
+    assert_that!(get_ids(sensor_sampler.latest())).is_equal_to(vec![
+        "a_value".to_string(),
+        "transform_a_value_session_max".to_string(),
+    ]);
+}
@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use assertor::{assert_that, BooleanAssertion, EqualityAssertion};
+use sensor_core::SensorValue;
+
+use crate::config::AppConfig;
+use crate::sensor::{SensorProvider, SensorProviderRegistry};
+use crate::test_utils::create_sensor_value;
+
+const TOLERANCE: Duration = Duration::from_millis(125);
+
+/// A provider with a single sensor, named after the provider
+struct TestProvider {
+    name: &'static str,
+    interval: Duration,
+}
+
+impl SensorProvider for TestProvider {
+    fn get_name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn get_preferred_interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn read(&self) -> Vec<SensorValue> {
+        vec![create_sensor_value(
+            &format!("{}_value", self.name),
+            "1",
+            "",
+        )]
+    }
+}
+
+/// Creates a registry with a provider `fast` read every second and a provider `slow` read hourly
+fn create_registry(disabled_provider_names: &[String]) -> SensorProviderRegistry {
+    SensorProviderRegistry::with_providers(
+        vec![
+            Arc::new(TestProvider {
+                name: "fast",
+                interval: Duration::from_secs(1),
+            }),
+            Arc::new(TestProvider {
+                name: "slow",
+                interval: Duration::from_secs(60 * 60),
+            }),
+        ],
+        disabled_provider_names,
+    )
+}
+
+/// Reads all due providers and stores their sensor values
+fn read_due_providers(registry: &mut SensorProviderRegistry, now: Instant) -> Vec<String> {
+    let due_providers = registry.get_due_providers(&None, now, TOLERANCE);
+    for (index, provider) in due_providers.iter() {
+        registry.update(*index, provider.read(), now);
+    }
+
+    due_providers
+        .iter()
+        .map(|(_, provider)| provider.get_name())
+        .collect()
+}
+
+fn get_ids(sensor_values: Vec<SensorValue>) -> Vec<String> {
+    sensor_values
+        .into_iter()
+        .map(|sensor_value| sensor_value.id)
+        .collect()
+}
+
+fn sensor_ids(ids: &[&str]) -> Option<HashSet<String>> {
+    Some(ids.iter().map(|id| id.to_string()).collect())
+}
+
+#[test]
+fn test_get_due_providers() {
+    // GIVEN is a registry with providers of different intervals
+    let mut registry = create_registry(&[]);
+    let start = Instant::now();
+
+    // WHEN reading the due providers at start, within the tolerance of a second, and after an hour
+    let at_start = read_due_providers(&mut registry, start);
+    let after_half_a_second = read_due_providers(&mut registry, start + Duration::from_millis(500));
+    let after_a_second = read_due_providers(&mut registry, start + Duration::from_millis(900));
+    let after_an_hour = read_due_providers(&mut registry, start + Duration::from_secs(60 * 60));
+
+    // THEN providers should be due when they were never read or their interval elapsed
+    assert_that!(at_start).is_equal_to(vec!["fast".to_string(), "slow".to_string()]);
+    assert_that!(after_half_a_second).is_equal_to(Vec::<String>::new());
+    assert_that!(after_a_second).is_equal_to(vec!["fast".to_string()]);
+    assert_that!(after_an_hour).is_equal_to(vec!["fast".to_string(), "slow".to_string()]);
+}
+
+#[test]
+fn test_required_provider_filtering() {
+    // GIVEN is a registry whose providers were read
+    let mut registry = create_registry(&[]);
+    let start = Instant::now();
+    read_due_providers(&mut registry, start);
+    let later = start + Duration::from_secs(60 * 60);
+
+    // WHEN requiring a sensor of one provider, no sensor, and an unknown sensor
+    let fast_sensor_required =
+        registry.get_due_providers(&sensor_ids(&["fast_value"]), later, TOLERANCE);
+    let fast_sensor_values = registry.get_sensor_values(&sensor_ids(&["fast_value"]));
+    let nothing_required = registry.get_due_providers(&sensor_ids(&[]), later, TOLERANCE);
+    let unknown_sensor_required =
+        registry.get_due_providers(&sensor_ids(&["unknown_value"]), later, TOLERANCE);
+
+    // THEN only providers of required sensors should be read,
+    // and all providers if a required sensor is unknown, as it may appear with the next read
+    assert_that!(fast_sensor_required.len()).is_equal_to(1);
+    assert_that!(get_ids(fast_sensor_values)).is_equal_to(vec!["fast_value".to_string()]);
+    assert_that!(nothing_required.len()).is_equal_to(0);
+    assert_that!(unknown_sensor_required.len()).is_equal_to(2);
+}
+
+#[test]
+fn test_enable_and_disable_provider() {
+    // GIVEN is a registry whose providers were read
+    let mut registry = create_registry(&[]);
+    let start = Instant::now();
+    read_due_providers(&mut registry, start);
+
+    // WHEN disabling a provider, an unknown provider, and enabling the provider again
+    let disabled = registry.set_enabled("slow", false);
+    let unknown_disabled = registry.set_enabled("unknown", false);
+    let sensor_values_while_disabled = get_ids(registry.get_sensor_values(&None));
+    let enabled_providers_while_disabled = registry.get_enabled_providers().len();
+    let enabled = registry.set_enabled("slow", true);
+    let unread_providers_after_enabling: Vec<String> = registry
+        .get_unread_providers()
+        .iter()
+        .map(|(_, provider)| provider.get_name())
+        .collect();
+
+    // THEN the sensors of the disabled provider should be removed,
+    // and the provider should be read again once it is enabled
+    assert_that!(disabled).is_true();
+    assert_that!(unknown_disabled).is_false();
+    assert_that!(sensor_values_while_disabled).is_equal_to(vec!["fast_value".to_string()]);
+    assert_that!(enabled_providers_while_disabled).is_equal_to(1);
+    assert_that!(enabled).is_true();
+    assert_that!(unread_providers_after_enabling).is_equal_to(vec!["slow".to_string()]);
+}
+
+#[test]
+fn test_disabled_provider_is_persisted() {
+    // GIVEN is an app config in which a provider was disabled twice and another one disabled and enabled again
+    let mut app_config = AppConfig::default();
+    app_config.set_sensor_provider_enabled("slow", false);
+    app_config.set_sensor_provider_enabled("slow", false);
+    app_config.set_sensor_provider_enabled("fast", false);
+    app_config.set_sensor_provider_enabled("fast", true);
+
+    // WHEN saving and loading the config, and creating the registry from it
+    let json = serde_json::to_string(&app_config).unwrap();
+    let app_config: AppConfig = serde_json::from_str(&json).unwrap();
+    let registry = create_registry(&app_config.disabled_sensor_providers);
+
+    // THEN only the disabled provider should be stored once and be disabled in the registry
+    let provider_states: Vec<(String, bool)> = registry
+        .get_provider_infos()
+        .into_iter()
+        .map(|provider_info| (provider_info.name, provider_info.enabled))
+        .collect();
+    assert_that!(app_config.disabled_sensor_providers).is_equal_to(vec!["slow".to_string()]);
+    assert_that!(provider_states).is_equal_to(vec![
+        ("fast".to_string(), true),
+        ("slow".to_string(), false),
+    ]);
+}
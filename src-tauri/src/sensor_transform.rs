@@ -0,0 +1,208 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use log::warn;
+use sensor_core::{SensorType, SensorValue};
+
+use crate::config::{SensorTransform, SensorTransformConfig};
+use crate::sensor_history::SensorHistory;
+use crate::utils;
+
+/// A configured transform with the state it keeps between updates
+struct ActiveTransform {
+    config: SensorTransformConfig,
+    value: Option<f64>,
+    updated_at: Option<Instant>,
+}
+
+/// Computes the transformed sensors, e.g. smoothed values or the session maximum of a sensor.
+/// Each transform is exposed as an additional sensor next to its source sensor.
+#[derive(Default)]
+pub struct SensorTransforms {
+    transforms: Vec<ActiveTransform>,
+}
+
+/// Prefix of the ids of all transformed sensors, to not collide with the ids of provided sensors
+const TRANSFORM_SENSOR_ID_PREFIX: &str = "transform_";
+
+/// Returns the id of the sensor value of the transform, the source sensor id with the kind and parameter,
+/// e.g. `transform_cpu_load_total_avg_10000`
+pub fn get_sensor_id(config: &SensorTransformConfig) -> String {
+    let suffix = match config.transform {
+        SensorTransform::Ema { time_constant_ms } => format!("ema_{time_constant_ms}"),
+        SensorTransform::Average { window_ms } => format!("avg_{window_ms}"),
+        SensorTransform::PeakHold { decay_per_second } => {
+            format!("peak_{}", utils::format_number(decay_per_second))
+        }
+        SensorTransform::SessionMin => "session_min".to_string(),
+        SensorTransform::SessionMax => "session_max".to_string(),
+    };
+    format!("{TRANSFORM_SENSOR_ID_PREFIX}{}_{suffix}", config.sensor_id)
+}
+
+/// Returns the label of the sensor value of the transform, the source label with the kind and parameter
+fn get_label(config: &SensorTransformConfig, source_label: &str) -> String {
+    let suffix = match config.transform {
+        SensorTransform::Ema { time_constant_ms } => {
+            format!(
+                "EMA {}s",
+                utils::format_number(time_constant_ms as f64 / 1000.0)
+            )
+        }
+        SensorTransform::Average { window_ms } => {
+            format!("avg {}s", utils::format_number(window_ms as f64 / 1000.0))
+        }
+        SensorTransform::PeakHold { .. } => "peak".to_string(),
+        SensorTransform::SessionMin => "session min".to_string(),
+        SensorTransform::SessionMax => "session max".to_string(),
+    };
+    format!("{source_label} ({suffix})")
+}
+
+/// Verifies that the transforms produce distinct sensor ids.
+/// Returns an error naming the first duplicate, e.g. two session max transforms of the same sensor.
+pub fn verify_config(configs: &[SensorTransformConfig]) -> Result<(), String> {
+    let mut sensor_ids = HashSet::new();
+    for config in configs {
+        let sensor_id = get_sensor_id(config);
+        if !sensor_ids.insert(sensor_id.clone()) {
+            return Err(format!("Duplicate sensor transform '{sensor_id}'"));
+        }
+    }
+    Ok(())
+}
+
+impl SensorTransforms {
+    /// Creates the transforms of the configs.
+    /// Duplicates are logged and skipped on startup, only the first transform of a sensor id is kept.
+    pub fn new(configs: &[SensorTransformConfig]) -> SensorTransforms {
+        let mut sensor_ids = HashSet::new();
+        SensorTransforms {
+            transforms: configs
+                .iter()
+                .filter(|config| {
+                    let sensor_id = get_sensor_id(config);
+                    let is_unique = sensor_ids.insert(sensor_id.clone());
+                    if !is_unique {
+                        warn!("Duplicate sensor transform '{sensor_id}' is skipped");
+                    }
+                    is_unique
+                })
+                .map(|config| ActiveTransform {
+                    config: config.clone(),
+                    value: None,
+                    updated_at: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the ids of all transformed sensors
+    pub fn get_sensor_ids(&self) -> HashSet<String> {
+        self.transforms
+            .iter()
+            .map(|transform| get_sensor_id(&transform.config))
+            .collect()
+    }
+
+    /// Returns the ids of the source sensors of all required transformed sensors
+    pub fn get_source_sensor_ids(&self, required_sensor_ids: &HashSet<String>) -> HashSet<String> {
+        self.transforms
+            .iter()
+            .filter(|transform| required_sensor_ids.contains(&get_sensor_id(&transform.config)))
+            .map(|transform| transform.config.sensor_id.clone())
+            .collect()
+    }
+
+    /// Updates all transforms with the current value of their source sensor.
+    /// Windowed averages are computed from the sensor history.
+    /// Returns the transformed sensor values, transforms without a numeric source value are skipped.
+    pub fn update(
+        &mut self,
+        sensor_values: &[SensorValue],
+        sensor_history: &SensorHistory,
+        now: Instant,
+    ) -> Vec<SensorValue> {
+        let mut transformed_sensor_values = vec![];
+
+        for transform in &mut self.transforms {
+            let source = match sensor_values.iter().find(|sensor_value| {
+                sensor_value.id == transform.config.sensor_id
+                    && matches!(sensor_value.sensor_type, SensorType::Number)
+            }) {
+                Some(source) => source,
+                None => continue,
+            };
+            let value: f64 = match source.value.trim().parse() {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+
+            let elapsed = transform
+                .updated_at
+                .map(|updated_at| now.duration_since(updated_at).as_secs_f64());
+            let transformed_value = match (&transform.config.transform, transform.value, elapsed) {
+                (SensorTransform::Ema { time_constant_ms }, Some(previous), Some(elapsed)) => {
+                    let time_constant = *time_constant_ms as f64 / 1000.0;
+                    if time_constant > 0.0 {
+                        let alpha = 1.0 - (-elapsed / time_constant).exp();
+                        previous + alpha * (value - previous)
+                    } else {
+                        value
+                    }
+                }
+                (SensorTransform::Average { window_ms }, _, _) => {
+                    let window = Duration::from_millis(*window_ms);
+                    average(&transform.config.sensor_id, sensor_history, window).unwrap_or(value)
+                }
+                (SensorTransform::PeakHold { decay_per_second }, Some(previous), Some(elapsed)) => {
+                    value.max(previous - decay_per_second * elapsed)
+                }
+                (SensorTransform::SessionMin, Some(previous), _) => value.min(previous),
+                (SensorTransform::SessionMax, Some(previous), _) => value.max(previous),
+                _ => value,
+            };
+
+            transform.value = Some(transformed_value);
+            transform.updated_at = Some(now);
+            transformed_sensor_values.push(SensorValue {
+                id: get_sensor_id(&transform.config),
+                value: utils::format_number(transformed_value),
+                unit: source.unit.clone(),
+                label: get_label(&transform.config, &source.label),
+                sensor_type: SensorType::Number,
+            });
+        }
+
+        transformed_sensor_values
+    }
+
+    /// Resets the session min and max, they start over with the next value
+    pub fn reset_session(&mut self) {
+        for transform in &mut self.transforms {
+            if matches!(
+                transform.config.transform,
+                SensorTransform::SessionMin | SensorTransform::SessionMax
+            ) {
+                transform.value = None;
+                transform.updated_at = None;
+            }
+        }
+    }
+}
+
+/// Returns the average of the sampled numeric values of the sensor within the window.
+/// Returns None if the history contains no value of the sensor within the window.
+fn average(sensor_id: &str, sensor_history: &SensorHistory, window: Duration) -> Option<f64> {
+    let values: Vec<f64> = sensor_history
+        .get_entries(sensor_id, Some(window))
+        .iter()
+        .filter_map(|entry| entry.value.trim().parse().ok())
+        .collect();
+
+    if values.is_empty() {
+        return None;
+    }
+
+    Some(values.iter().sum::<f64>() / values.len() as f64)
+}
@@ -0,0 +1,207 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use assertor::{assert_that, BooleanAssertion, EqualityAssertion};
+use sensor_core::{SensorType, SensorValue};
+
+use crate::config::{SensorTransform, SensorTransformConfig};
+use crate::sensor_history::SensorHistory;
+use crate::sensor_transform;
+use crate::sensor_transform::SensorTransforms;
+
+fn create_sensor_values(value: f64) -> Vec<SensorValue> {
+    vec![SensorValue {
+        id: "gpu_temp".to_string(),
+        value: value.to_string(),
+        unit: "°C".to_string(),
+        label: "GPU temperature".to_string(),
+        sensor_type: SensorType::Number,
+    }]
+}
+
+fn create_sensor_transforms(transforms: Vec<SensorTransform>) -> SensorTransforms {
+    let configs: Vec<SensorTransformConfig> = transforms
+        .into_iter()
+        .map(|transform| SensorTransformConfig {
+            sensor_id: "gpu_temp".to_string(),
+            transform,
+        })
+        .collect();
+    SensorTransforms::new(&configs)
+}
+
+/// Updates the transforms with the value at the specified second and returns the transformed values
+fn update_at(
+    sensor_transforms: &mut SensorTransforms,
+    sensor_history: &SensorHistory,
+    start: Instant,
+    second: u64,
+    value: f64,
+) -> Vec<(String, String)> {
+    sensor_transforms
+        .update(
+            &create_sensor_values(value),
+            sensor_history,
+            start + Duration::from_secs(second),
+        )
+        .into_iter()
+        .map(|sensor_value| (sensor_value.id, sensor_value.value))
+        .collect()
+}
+
+#[test]
+fn test_session_min_max() {
+    // GIVEN are session min and max transforms
+    let mut sensor_transforms = create_sensor_transforms(vec![
+        SensorTransform::SessionMin,
+        SensorTransform::SessionMax,
+    ]);
+    let sensor_history = SensorHistory::new(Duration::from_secs(60));
+    let start = Instant::now();
+
+    // WHEN the value rises and falls, and the session is reset
+    update_at(&mut sensor_transforms, &sensor_history, start, 0, 50.0);
+    update_at(&mut sensor_transforms, &sensor_history, start, 1, 80.0);
+    let before_reset = update_at(&mut sensor_transforms, &sensor_history, start, 2, 60.0);
+    sensor_transforms.reset_session();
+    let after_reset = update_at(&mut sensor_transforms, &sensor_history, start, 3, 70.0);
+
+    // THEN the extremes since the start or the reset should be reported
+    assert_that!(before_reset).is_equal_to(vec![
+        (
+            "transform_gpu_temp_session_min".to_string(),
+            "50".to_string(),
+        ),
+        (
+            "transform_gpu_temp_session_max".to_string(),
+            "80".to_string(),
+        ),
+    ]);
+    assert_that!(after_reset).is_equal_to(vec![
+        (
+            "transform_gpu_temp_session_min".to_string(),
+            "70".to_string(),
+        ),
+        (
+            "transform_gpu_temp_session_max".to_string(),
+            "70".to_string(),
+        ),
+    ]);
+}
+
+#[test]
+fn test_peak_hold_and_ema() {
+    // GIVEN are a peak-hold, that decays by 5 per second, and an ema with a time constant of 1 second
+    let mut sensor_transforms = create_sensor_transforms(vec![
+        SensorTransform::PeakHold {
+            decay_per_second: 5.0,
+        },
+        SensorTransform::Ema {
+            time_constant_ms: 1000,
+        },
+    ]);
+    let sensor_history = SensorHistory::new(Duration::from_secs(60));
+    let start = Instant::now();
+
+    // WHEN the value drops from a peak
+    update_at(&mut sensor_transforms, &sensor_history, start, 0, 90.0);
+    let after_drop = update_at(&mut sensor_transforms, &sensor_history, start, 2, 50.0);
+
+    // THEN the peak should decay over time and the ema should follow the drop by 1 - e^-2
+    assert_that!(after_drop).is_equal_to(vec![
+        ("transform_gpu_temp_peak_5".to_string(), "80".to_string()),
+        (
+            "transform_gpu_temp_ema_1000".to_string(),
+            "55.41".to_string(),
+        ),
+    ]);
+}
+
+#[test]
+fn test_average() {
+    // GIVEN is a history of the sensor and a windowed average over 10 seconds
+    let mut sensor_transforms =
+        create_sensor_transforms(vec![SensorTransform::Average { window_ms: 10_000 }]);
+    let mut sensor_history = SensorHistory::new(Duration::from_secs(60));
+    let history_start = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+    for (second, value) in [(0, 100.0), (20, 40.0), (25, 50.0), (30, 60.0)] {
+        sensor_history.push(
+            history_start + Duration::from_secs(second),
+            &create_sensor_values(value),
+        );
+    }
+
+    // WHEN updating the transform
+    let average = update_at(
+        &mut sensor_transforms,
+        &sensor_history,
+        Instant::now(),
+        0,
+        70.0,
+    );
+
+    // THEN only the sampled values within the window should be averaged
+    assert_that!(average).is_equal_to(vec![(
+        "transform_gpu_temp_avg_10000".to_string(),
+        "50".to_string(),
+    )]);
+}
+
+#[test]
+fn test_transforms_of_the_same_kind_have_distinct_ids() {
+    // GIVEN are two averages with different windows
+    let mut sensor_transforms = create_sensor_transforms(vec![
+        SensorTransform::Average { window_ms: 1_000 },
+        SensorTransform::Average { window_ms: 60_000 },
+    ]);
+    let sensor_history = SensorHistory::new(Duration::from_secs(60));
+
+    // WHEN updating the transforms
+    let sensor_ids: Vec<String> = update_at(
+        &mut sensor_transforms,
+        &sensor_history,
+        Instant::now(),
+        0,
+        50.0,
+    )
+    .into_iter()
+    .map(|(sensor_id, _)| sensor_id)
+    .collect();
+
+    // THEN each transform should be reported with its own id
+    assert_that!(sensor_ids).is_equal_to(vec![
+        "transform_gpu_temp_avg_1000".to_string(),
+        "transform_gpu_temp_avg_60000".to_string(),
+    ]);
+}
+
+#[test]
+fn test_duplicate_transforms() {
+    // GIVEN are two identical session max transforms of a sensor
+    let configs = vec![
+        SensorTransformConfig {
+            sensor_id: "gpu_temp".to_string(),
+            transform: SensorTransform::SessionMax,
+        };
+        2
+    ];
+
+    // WHEN verifying and creating the transforms
+    let verify_result = sensor_transform::verify_config(&configs);
+    let mut sensor_transforms = SensorTransforms::new(&configs);
+    let sensor_history = SensorHistory::new(Duration::from_secs(60));
+    let transformed = update_at(
+        &mut sensor_transforms,
+        &sensor_history,
+        Instant::now(),
+        0,
+        50.0,
+    );
+
+    // THEN the duplicate should be rejected and only the first transform should be kept
+    assert_that!(verify_result.is_err()).is_true();
+    assert_that!(transformed).is_equal_to(vec![(
+        "transform_gpu_temp_session_max".to_string(),
+        "50".to_string(),
+    )]);
+    assert_that!(sensor_transform::verify_config(&configs[..1]).is_ok()).is_true();
+}
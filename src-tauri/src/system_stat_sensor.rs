@@ -3,58 +3,114 @@ use std::collections::HashMap;
 use std::fs::File;
 #[cfg(target_os = "linux")]
 use std::io::{BufRead, BufReader};
-use std::thread;
-use std::time::Duration;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use rayon::prelude::*;
 use sensor_core::{SensorType, SensorValue};
 use systemstat::platform::PlatformImpl;
 use systemstat::IpAddr::{V4, V6};
-use systemstat::{Platform, System};
+use systemstat::{CPULoad, DelayedMeasurement, Platform, System};
 
-use crate::{sensor, utils};
+use crate::utils::LockResultExt;
+use crate::{sensor, units};
 
-pub struct SystemStatSensor {}
+/// Time since the previous read, after which loads and rates are measured anew,
+/// instead of averaging them over the whole gap, e.g. after a suspend or while the sensors were not required
+const MAX_READ_GAP: Duration = Duration::from_secs(10);
+
+/// Reads cpu, memory, network and disk sensors using systemstat.
+/// Load and rate sensors are measured over the time elapsed since the previous read,
+/// thus the first read reports no load and no rates.
+#[derive(Default)]
+pub struct SystemStatSensor {
+    previous_read: Mutex<PreviousRead>,
+}
+
+/// Measurements and counters of the previous read, that are required to calculate loads and rates
+#[derive(Default)]
+struct PreviousRead {
+    read_at: Option<Instant>,
+    cpu_load: Option<CpuLoadMeasurement>,
+    network_counters: Option<CounterSnapshot>,
+    #[cfg(target_os = "linux")]
+    disk_counters: Option<CounterSnapshot>,
+}
+
+/// A running cpu load measurement, that is completed by the next read
+struct CpuLoadMeasurement {
+    aggregate: DelayedMeasurement<CPULoad>,
+    per_cpu: DelayedMeasurement<Vec<CPULoad>>,
+}
+
+/// Counters per device, e.g. transmitted bytes per network interface, at the time they were read
+struct CounterSnapshot {
+    read_at: Instant,
+    counters: HashMap<String, (u64, u64)>,
+}
 
 impl sensor::SensorProvider for SystemStatSensor {
     fn get_name(&self) -> String {
         "SystemStat".to_string()
     }
-}
 
-pub fn get_sensor_values() -> Vec<SensorValue> {
-    let system_stat = System::new();
+    fn get_preferred_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn read(&self) -> Vec<SensorValue> {
+        let system_stat = System::new();
+        let mut previous_read = self.previous_read.lock().ignore_poison();
+        let now = Instant::now();
+        if get_elapsed_since(previous_read.read_at, now).is_none() {
+            *previous_read = PreviousRead::default();
+        }
+        previous_read.read_at = Some(now);
 
-    let sensors_requests = vec![
-        // Blocks for 250ms
-        get_total_delayed_sensors,
-        // Blocks for 250ms
+        let mut sensor_values = vec![];
+        sensor_values.extend(get_cpu_load_sensors(
+            &system_stat,
+            &mut previous_read.cpu_load,
+        ));
         #[cfg(target_os = "linux")]
-        get_disk_rw_sensors,
-        get_cpu_temp_sensors,
-        get_memory_sensors,
-        get_uptime_sensor,
-        get_network_sensors,
-    ];
-
-    // Limit the number of threads to 3
-    // Because otherwise the CPU load will be too high on some high core cpu systems
-    // But we need at most 3 to read sensors because two of them are blocking for 250ms
-    let pool = rayon::ThreadPoolBuilder::new()
-        .num_threads(3)
-        .build()
-        .unwrap();
-
-    pool.install(|| {
-        sensors_requests
-            .par_iter()
-            .flat_map(|f| f(&system_stat))
-            .collect()
-    })
+        sensor_values.extend(get_disk_rw_sensors(
+            &system_stat,
+            &mut previous_read.disk_counters,
+        ));
+        sensor_values.extend(get_cpu_temp_sensors(&system_stat));
+        sensor_values.extend(get_memory_sensors(&system_stat));
+        sensor_values.extend(get_uptime_sensor(&system_stat));
+        sensor_values.extend(get_network_sensors(
+            &system_stat,
+            &mut previous_read.network_counters,
+        ));
+        sensor_values
+    }
 }
 
-fn get_network_sensors(system_stat: &PlatformImpl) -> Vec<SensorValue> {
+/// Returns the time elapsed since the previous read,
+/// or None if there was no previous read or it is older than the maximum gap
+pub fn get_elapsed_since(previous_read_at: Option<Instant>, now: Instant) -> Option<Duration> {
+    let elapsed = now.duration_since(previous_read_at?);
+    (elapsed <= MAX_READ_GAP).then_some(elapsed)
+}
+
+/// Calculates the rate per second of a counter, based on its value of the previous snapshot.
+/// Returns 0 if there is no previous value, no time elapsed or the counter was reset.
+pub fn get_rate_per_second(previous_counter: Option<u64>, counter: u64, elapsed: Duration) -> f64 {
+    match previous_counter {
+        Some(previous_counter) if !elapsed.is_zero() => {
+            counter.saturating_sub(previous_counter) as f64 / elapsed.as_secs_f64()
+        }
+        _ => 0.0,
+    }
+}
+
+fn get_network_sensors(
+    system_stat: &PlatformImpl,
+    previous_counters: &mut Option<CounterSnapshot>,
+) -> Vec<SensorValue> {
     let network = system_stat.networks().unwrap();
+    let read_at = Instant::now();
 
     let mut sensor_values: Vec<SensorValue> = vec![];
 
@@ -69,7 +125,7 @@ fn get_network_sensors(system_stat: &PlatformImpl) -> Vec<SensorValue> {
 
         let net_data = net_data.unwrap();
 
-        // Read RX and TX in megabytes
+        // Read RX and TX in bytes
         let rx = net_data.rx_bytes.0;
         let tx = net_data.tx_bytes.0;
 
@@ -101,88 +157,102 @@ fn get_network_sensors(system_stat: &PlatformImpl) -> Vec<SensorValue> {
         }
     }
 
-    // Wait 250ms
-    thread::sleep(Duration::from_millis(250));
-
-    // Read RX and TX again
-    for (iface_name, _iface_data) in network {
-        let net_data = system_stat.network_stats(&iface_name);
-        if net_data.is_err() {
-            continue;
-        }
-
-        let net_data = net_data.unwrap();
-
-        // Read RX and TX in megabytes
-        let rx = net_data.rx_bytes.0;
-        let tx = net_data.tx_bytes.0;
-
-        // Get previous RX and TX from hashmap
-        let (prev_rx, prev_tx) = network_bytes.get(&iface_name).unwrap();
-
-        // Calculate RX and TX in bytes
-        // Cause we only waited 250ms, we need to multiply by 4 to get the correct value
-        let rx_delta = (rx - prev_rx) * 4;
-        let tx_delta = (tx - prev_tx) * 4;
+    // Calculate the RX and TX rates over the time elapsed since the previous read
+    for (iface_name, (rx, tx)) in &network_bytes {
+        let (elapsed, previous) = match previous_counters {
+            Some(snapshot) => (
+                read_at.duration_since(snapshot.read_at),
+                snapshot.counters.get(iface_name),
+            ),
+            None => (Duration::ZERO, None),
+        };
 
-        let (dl_rate, dl_rate_format) = utils::pretty_bytes(rx_delta as f64);
-        let (ul_rate, ul_rate_format) = utils::pretty_bytes(tx_delta as f64);
+        let rx_rate = get_rate_per_second(previous.map(|(prev_rx, _)| *prev_rx), *rx, elapsed);
+        let tx_rate = get_rate_per_second(previous.map(|(_, prev_tx)| *prev_tx), *tx, elapsed);
 
         // Add RX and TX to the vector
         sensor_values.push(SensorValue {
             id: format!("network_rx_{iface_name}"),
-            value: format!("{:.2}", dl_rate),
-            unit: format!("{dl_rate_format}/s"),
+            value: format!("{:.0}", rx_rate),
+            unit: units::BYTES_PER_SECOND.to_string(),
             label: format!("{iface_name} download rate"),
             sensor_type: SensorType::Number,
         });
 
         sensor_values.push(SensorValue {
             id: format!("network_tx_{iface_name}"),
-            value: format!("{:.2}", ul_rate),
-            unit: format!("{ul_rate_format}/s"),
+            value: format!("{:.0}", tx_rate),
+            unit: units::BYTES_PER_SECOND.to_string(),
             label: format!("{iface_name} upload rate"),
             sensor_type: SensorType::Number,
         });
     }
 
+    *previous_counters = Some(CounterSnapshot {
+        read_at,
+        counters: network_bytes,
+    });
+
     sensor_values
 }
 
-fn get_total_delayed_sensors(system_stat: &PlatformImpl) -> Vec<SensorValue> {
-    let cpu_load = system_stat.cpu_load_aggregate().unwrap();
-    let per_cpu_load = system_stat.cpu_load().unwrap();
-
-    thread::sleep(Duration::from_millis(250));
+/// Returns the load of the cpu in percent.
+/// A measurement without elapsed time has no load, instead of an undefined one.
+fn get_load_percentage(cpu_load: &CPULoad) -> f32 {
+    let load = cpu_load.user * 100.0
+        + cpu_load.nice * 100.0
+        + cpu_load.system * 100.0
+        + cpu_load.interrupt * 100.0;
+
+    if load.is_finite() {
+        load
+    } else {
+        0.0
+    }
+}
 
-    let cpu = cpu_load.done().unwrap();
-    let cpus = per_cpu_load.done().unwrap();
+fn get_cpu_load_sensors(
+    system_stat: &PlatformImpl,
+    previous_measurement: &mut Option<CpuLoadMeasurement>,
+) -> Vec<SensorValue> {
+    // Start the measurement for the next read
+    let measurement = match (system_stat.cpu_load_aggregate(), system_stat.cpu_load()) {
+        (Ok(aggregate), Ok(per_cpu)) => CpuLoadMeasurement { aggregate, per_cpu },
+        _ => return vec![],
+    };
 
-    let total_cpu_load =
-        cpu.user * 100.0 + cpu.nice * 100.0 + cpu.system * 100.0 + cpu.interrupt * 100.0;
+    // Complete the measurement of the previous read,
+    // the first read completes the measurement it just started
+    let previous = previous_measurement.replace(measurement);
+    let completed_measurement = previous
+        .as_ref()
+        .unwrap_or_else(|| previous_measurement.as_ref().unwrap());
+    let (cpu, cpus) = match (
+        completed_measurement.aggregate.done(),
+        completed_measurement.per_cpu.done(),
+    ) {
+        (Ok(cpu), Ok(cpus)) => (cpu, cpus),
+        _ => return vec![],
+    };
 
     // Collect CPU load for each CPU
     let mut sensor_values: Vec<SensorValue> = cpus
-        .into_iter()
+        .iter()
         .enumerate()
-        .map(|(i, cpu)| {
-            let total_cpu_load =
-                cpu.user * 100.0 + cpu.nice * 100.0 + cpu.system * 100.0 + cpu.interrupt * 100.0;
-            SensorValue {
-                id: format!("cpu_load_{}", i),
-                value: format!("{:.2}", total_cpu_load),
-                label: format!("CPU {} load", i),
-                unit: "%".to_string(),
-                sensor_type: SensorType::Number,
-            }
+        .map(|(i, cpu)| SensorValue {
+            id: format!("cpu_load_{}", i),
+            value: format!("{:.2}", get_load_percentage(cpu)),
+            label: format!("CPU {} load", i),
+            unit: units::PERCENT.to_string(),
+            sensor_type: SensorType::Number,
         })
         .collect();
 
     // Add total CPU load to the vector
     sensor_values.push(SensorValue {
         id: "cpu_load_total".to_string(),
-        value: format!("{:.2}", total_cpu_load),
-        unit: "%".to_string(),
+        value: format!("{:.2}", get_load_percentage(&cpu)),
+        unit: units::PERCENT.to_string(),
         label: "Total CPU load".to_string(),
         sensor_type: SensorType::Number,
     });
@@ -201,7 +271,7 @@ fn get_cpu_temp_sensors(system_stat: &PlatformImpl) -> Vec<SensorValue> {
         id: "cpu_temp_package".to_string(),
         value: format!("{:.2}", cpu_temp.unwrap()),
         label: "CPU package temperature".to_string(),
-        unit: "°C".to_string(),
+        unit: units::CELSIUS.to_string(),
         sensor_type: SensorType::Number,
     }]
 }
@@ -230,7 +300,7 @@ fn get_uptime_sensor(system_stat: &PlatformImpl) -> Vec<SensorValue> {
     }]
 }
 
-// Reads in mega bytes
+// Reads in bytes
 fn get_memory_sensors(system_stat: &PlatformImpl) -> Vec<SensorValue> {
     let mem = system_stat.memory();
 
@@ -244,46 +314,45 @@ fn get_memory_sensors(system_stat: &PlatformImpl) -> Vec<SensorValue> {
     let mem_free = mem.free.as_u64();
     let mem_used = mem_total - mem_free;
 
-    let (mem_total, mem_total_unit) = utils::pretty_bytes(mem_total as f64);
-    let (mem_used, mem_used_unit) = utils::pretty_bytes(mem_used as f64);
-    let (mem_free, mem_free_unit) = utils::pretty_bytes(mem_free as f64);
-
-    // Collect sensors and use pretty_bytes to convert the values
     vec![
         SensorValue {
             id: "memory_total".to_string(),
-            value: format!("{:.2}", mem_total),
+            value: mem_total.to_string(),
             label: "Total memory".to_string(),
-            unit: mem_total_unit,
+            unit: units::BYTES.to_string(),
             sensor_type: SensorType::Number,
         },
         SensorValue {
             id: "memory_used".to_string(),
-            value: format!("{:.2}", mem_used),
+            value: mem_used.to_string(),
             label: "Used memory".to_string(),
-            unit: mem_used_unit,
+            unit: units::BYTES.to_string(),
             sensor_type: SensorType::Number,
         },
         SensorValue {
             id: "memory_free".to_string(),
-            value: format!("{:.2}", mem_free),
+            value: mem_free.to_string(),
             label: "Free memory".to_string(),
-            unit: mem_free_unit,
+            unit: units::BYTES.to_string(),
             sensor_type: SensorType::Number,
         },
         SensorValue {
             id: "memory_used_percentage".to_string(),
-            value: format!("{:.2}", mem_used / mem_total * 100.0),
+            value: format!("{:.2}", mem_used as f64 / mem_total as f64 * 100.0),
             label: "Used memory percentage".to_string(),
-            unit: "%".to_string(),
+            unit: units::PERCENT.to_string(),
             sensor_type: SensorType::Number,
         },
     ]
 }
 
 #[cfg(target_os = "linux")]
-fn get_disk_rw_sensors(system_stat: &PlatformImpl) -> Vec<SensorValue> {
+fn get_disk_rw_sensors(
+    system_stat: &PlatformImpl,
+    previous_counters: &mut Option<CounterSnapshot>,
+) -> Vec<SensorValue> {
     let disks = system_stat.block_device_statistics();
+    let read_at = Instant::now();
 
     if disks.is_err() {
         return vec![];
@@ -291,53 +360,68 @@ fn get_disk_rw_sensors(system_stat: &PlatformImpl) -> Vec<SensorValue> {
 
     let disks = disks.unwrap();
 
-    // Snapshot read_ios and write_ios per disk
-    let mut read_ios: HashMap<String, usize> = HashMap::new();
-    let mut write_ios: HashMap<String, usize> = HashMap::new();
-    disks.iter().for_each(|disk| {
-        read_ios.insert(disk.1.name.clone(), disk.1.read_sectors);
-        write_ios.insert(disk.1.name.clone(), disk.1.write_sectors);
-    });
-
-    thread::sleep(Duration::from_millis(250));
-
-    let disks = system_stat.block_device_statistics().unwrap();
-
-    // Calculate read and write per second per disk
-    disks
-        .into_iter()
-        .flat_map(|disk| {
-            let sector_size = get_sector_size(&disk.1.name);
-
-            // Calculate read and write per second per disk based on the difference between
-            // the current and previous sector count
-            // To compensate the 250ms sleep not 1 second, multiply by 4
-            let read =
-                (disk.1.read_sectors - read_ios.get(&disk.1.name).unwrap()) * sector_size * 4;
-            let write =
-                (disk.1.write_sectors - write_ios.get(&disk.1.name).unwrap()) * sector_size * 4;
+    // Snapshot read and written sectors per disk
+    let sector_counters: HashMap<String, (u64, u64)> = disks
+        .values()
+        .map(|disk| {
+            (
+                disk.name.clone(),
+                (disk.read_sectors as u64, disk.write_sectors as u64),
+            )
+        })
+        .collect();
 
-            let (read, read_unit) = utils::pretty_bytes(read as f64);
-            let (write, write_unit) = utils::pretty_bytes(write as f64);
+    let elapsed = previous_counters
+        .as_ref()
+        .map(|snapshot| read_at.duration_since(snapshot.read_at))
+        .unwrap_or_default();
+
+    // Calculate read and write per second per disk based on the difference between
+    // the current and previous sector count, over the time elapsed since the previous read
+    let sensor_values = sector_counters
+        .iter()
+        .flat_map(|(name, (read_sectors, write_sectors))| {
+            let sector_size = get_sector_size(name) as f64;
+            let previous = previous_counters
+                .as_ref()
+                .and_then(|snapshot| snapshot.counters.get(name));
+
+            let read = get_rate_per_second(
+                previous.map(|(prev_read, _)| *prev_read),
+                *read_sectors,
+                elapsed,
+            ) * sector_size;
+            let write = get_rate_per_second(
+                previous.map(|(_, prev_write)| *prev_write),
+                *write_sectors,
+                elapsed,
+            ) * sector_size;
 
             vec![
                 SensorValue {
-                    id: format!("disk_read_{}", disk.1.name),
-                    value: format!("{:.2}", read),
-                    label: format!("Disk {} read", disk.1.name),
-                    unit: format!("{}/s", read_unit),
+                    id: format!("disk_read_{}", name),
+                    value: format!("{:.0}", read),
+                    label: format!("Disk {} read", name),
+                    unit: units::BYTES_PER_SECOND.to_string(),
                     sensor_type: SensorType::Number,
                 },
                 SensorValue {
-                    id: format!("disk_write_{}", disk.1.name),
-                    value: format!("{:.2}", write),
-                    label: format!("Disk {} write", disk.1.name),
-                    unit: format!("{}/s", write_unit),
+                    id: format!("disk_write_{}", name),
+                    value: format!("{:.0}", write),
+                    label: format!("Disk {} write", name),
+                    unit: units::BYTES_PER_SECOND.to_string(),
                     sensor_type: SensorType::Number,
                 },
             ]
         })
-        .collect()
+        .collect();
+
+    *previous_counters = Some(CounterSnapshot {
+        read_at,
+        counters: sector_counters,
+    });
+
+    sensor_values
 }
 
 /// Returns the sector size of the given device
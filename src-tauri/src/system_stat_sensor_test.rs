@@ -0,0 +1,66 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use assertor::{assert_that, BooleanAssertion, EqualityAssertion};
+use sensor_core::SensorValue;
+
+use crate::sensor::SensorProvider;
+use crate::system_stat_sensor;
+use crate::system_stat_sensor::SystemStatSensor;
+
+/// Returns the numeric value of the sensor
+fn get_value(sensor_values: &[SensorValue], sensor_id: &str) -> Option<f64> {
+    sensor_values
+        .iter()
+        .find(|sensor_value| sensor_value.id == sensor_id)
+        .and_then(|sensor_value| sensor_value.value.parse().ok())
+}
+
+#[test]
+fn test_get_rate_per_second() {
+    // GIVEN are byte counters of a network interface, read two seconds apart
+
+    // WHEN calculating the rate of the counter, without previous value, without elapsed time and after a reset
+    let rate = system_stat_sensor::get_rate_per_second(Some(1000), 5096, Duration::from_secs(2));
+    let first_read = system_stat_sensor::get_rate_per_second(None, 5096, Duration::from_secs(2));
+    let no_time_elapsed = system_stat_sensor::get_rate_per_second(Some(1000), 5096, Duration::ZERO);
+    let reset = system_stat_sensor::get_rate_per_second(Some(5096), 1000, Duration::from_secs(2));
+
+    // THEN the rate should be in bytes per second, and 0 if it can not be calculated
+    assert_that!(rate).is_equal_to(2048.0);
+    assert_that!(first_read).is_equal_to(0.0);
+    assert_that!(no_time_elapsed).is_equal_to(0.0);
+    assert_that!(reset).is_equal_to(0.0);
+}
+
+#[test]
+fn test_get_elapsed_since() {
+    // GIVEN is the time of a read
+    let now = Instant::now() + Duration::from_secs(60);
+
+    // WHEN getting the time elapsed since a recent, a stale and no previous read
+    let recent = system_stat_sensor::get_elapsed_since(Some(now - Duration::from_secs(1)), now);
+    let stale = system_stat_sensor::get_elapsed_since(Some(now - Duration::from_secs(60)), now);
+    let first_read = system_stat_sensor::get_elapsed_since(None, now);
+
+    // THEN only the recent read should be used to measure loads and rates
+    assert_that!(recent).is_equal_to(Some(Duration::from_secs(1)));
+    assert_that!(stale).is_equal_to(None);
+    assert_that!(first_read).is_equal_to(None);
+}
+
+#[test]
+fn test_cpu_load_is_measured_over_elapsed_time() {
+    // GIVEN is a sensor, whose first read starts the cpu load measurement
+    let sensor = SystemStatSensor::default();
+    let first_load = get_value(&sensor.read(), "cpu_load_total");
+
+    // WHEN reading the sensor again after some time
+    thread::sleep(Duration::from_millis(200));
+    let second_load = get_value(&sensor.read(), "cpu_load_total");
+
+    // THEN both reads should report a defined load in percent
+    for load in [first_load, second_load] {
+        assert_that!(load.is_some_and(|load| (0.0..=100.0).contains(&load))).is_true();
+    }
+}
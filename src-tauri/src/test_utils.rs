@@ -0,0 +1,32 @@
+use std::fs;
+use std::path::Path;
+
+use sensor_core::{SensorType, SensorValue};
+
+/// Creates a numeric sensor value, labeled with its id
+pub fn create_sensor_value(id: &str, value: &str, unit: &str) -> SensorValue {
+    SensorValue {
+        id: id.to_string(),
+        value: value.to_string(),
+        unit: unit.to_string(),
+        label: id.to_string(),
+        sensor_type: SensorType::Number,
+    }
+}
+
+/// Creates a text sensor value without unit, labeled with its id
+pub fn create_text_sensor_value(id: &str, value: &str) -> SensorValue {
+    SensorValue {
+        sensor_type: SensorType::Text,
+        ..create_sensor_value(id, value, "")
+    }
+}
+
+/// Writes the files relative to the directory, creating missing directories
+pub fn write_files(directory: &Path, files: &[(&str, &str)]) {
+    for (file_path, content) in files {
+        let path = directory.join(file_path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+}
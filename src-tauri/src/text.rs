@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::{BufWriter, Cursor};
 
 use sensor_core::{
@@ -6,7 +6,10 @@ use sensor_core::{
     TransportType,
 };
 
-use crate::fonts;
+use crate::{fonts, units};
+
+/// Suffix of the ids of auto scaled sensor copies, which no sensor provider uses
+const AUTO_SCALED_ID_SUFFIX: &str = "@auto_scaled";
 
 /// Creates the PrepareTextData struct which contains the font data for each text element.
 pub fn get_preparation_data(display_config: &DisplayConfig) -> PrepareTextData {
@@ -76,3 +79,82 @@ pub fn build_fonts_data(display_config: &DisplayConfig) -> HashMap<String, Vec<u
         })
         .collect()
 }
+
+/// Shows the sensors of text elements in readable units, e.g. 3.4 GHz instead of 3400000000 Hz.
+/// Text elements are always pointed to auto scaled copies of their sensors, so that graphs and conditional images
+/// still get the values in their base unit, and the layout does not change when a value crosses a unit boundary.
+/// The copy keeps the value and unit of its sensor, if the value is readable in its unit.
+/// The unit is chosen by the latest value and used for all snapshots,
+/// so that the min, max and average of a text element are calculated in the same unit.
+pub fn auto_scale_units(
+    display_config: &mut DisplayConfig,
+    sensor_value_history: &mut [Vec<SensorValue>],
+) {
+    let mut text_sensor_ids: HashSet<String> = HashSet::new();
+    for element in display_config.elements.iter_mut() {
+        if element.element_type != ElementType::Text {
+            continue;
+        }
+        if let Some(text_config) = element.text_config.as_mut() {
+            text_sensor_ids.insert(text_config.sensor_id.clone());
+            text_config.sensor_id = format!("{}{AUTO_SCALED_ID_SUFFIX}", text_config.sensor_id);
+        }
+    }
+
+    // Display unit and factor of each text sensor, whose latest value is not readable in its unit
+    let display_units: HashMap<String, (&'static str, f64)> = sensor_value_history
+        .first()
+        .into_iter()
+        .flatten()
+        .filter(|sensor_value| text_sensor_ids.contains(&sensor_value.id))
+        .filter_map(|sensor_value| {
+            let value = sensor_value.value.parse::<f64>().ok()?;
+            let display_unit = units::get_display_unit(value, &sensor_value.unit)?;
+            Some((sensor_value.id.clone(), display_unit))
+        })
+        .collect();
+
+    for sensor_values in sensor_value_history.iter_mut() {
+        let auto_scaled_sensor_values: Vec<SensorValue> = sensor_values
+            .iter()
+            .filter(|sensor_value| text_sensor_ids.contains(&sensor_value.id))
+            .map(|sensor_value| {
+                let id = format!("{}{AUTO_SCALED_ID_SUFFIX}", sensor_value.id);
+                let scaled_value = display_units
+                    .get(&sensor_value.id)
+                    .zip(sensor_value.value.parse::<f64>().ok());
+                match scaled_value {
+                    Some(((display_unit, factor), value)) => SensorValue {
+                        id,
+                        value: units::format_display_value(value / factor),
+                        unit: display_unit.to_string(),
+                        ..sensor_value.clone()
+                    },
+                    None => SensorValue {
+                        id,
+                        ..sensor_value.clone()
+                    },
+                }
+            })
+            .collect();
+        sensor_values.extend(auto_scaled_sensor_values);
+    }
+}
+
+/// Returns the sensor value in a readable unit, e.g. 3.4 GHz instead of 3400000000 Hz.
+/// Returns the sensor value unchanged if it is readable in its unit.
+pub fn auto_scale_unit(sensor_value: SensorValue) -> SensorValue {
+    let value = match sensor_value.value.parse::<f64>() {
+        Ok(value) => value,
+        Err(_) => return sensor_value,
+    };
+
+    match units::get_display_unit(value, &sensor_value.unit) {
+        Some((display_unit, factor)) => SensorValue {
+            value: units::format_display_value(value / factor),
+            unit: display_unit.to_string(),
+            ..sensor_value
+        },
+        None => sensor_value,
+    }
+}
@@ -0,0 +1,109 @@
+const KIB: f64 = 1024.0;
+
+/// Base units in which the sensor providers report their values.
+/// Scaling to a readable unit is left to the display, so that values stay comparable over time.
+pub const CELSIUS: &str = "°C";
+pub const PERCENT: &str = "%";
+pub const BYTES: &str = "B";
+pub const BYTES_PER_SECOND: &str = "B/s";
+pub const HERTZ: &str = "Hz";
+pub const RPM: &str = "RPM";
+pub const VOLT: &str = "V";
+pub const WATT: &str = "W";
+pub const JOULE: &str = "J";
+
+/// Unit that is converted from celsius with an offset, thus not part of the units table
+const FAHRENHEIT: &str = "°F";
+
+/// Known units of sensor values, with their base unit and the factor to convert a value to the base unit
+const UNITS: &[(&str, &str, f64)] = &[
+    (CELSIUS, CELSIUS, 1.0),
+    ("C", CELSIUS, 1.0),
+    (PERCENT, PERCENT, 1.0),
+    (BYTES, BYTES, 1.0),
+    ("KB", BYTES, KIB),
+    ("MB", BYTES, KIB * KIB),
+    ("GB", BYTES, KIB * KIB * KIB),
+    ("TB", BYTES, KIB * KIB * KIB * KIB),
+    (BYTES_PER_SECOND, BYTES_PER_SECOND, 1.0),
+    ("KB/s", BYTES_PER_SECOND, KIB),
+    ("MB/s", BYTES_PER_SECOND, KIB * KIB),
+    ("GB/s", BYTES_PER_SECOND, KIB * KIB * KIB),
+    ("TB/s", BYTES_PER_SECOND, KIB * KIB * KIB * KIB),
+    ("bit/s", BYTES_PER_SECOND, 1.0 / 8.0),
+    ("Kbit/s", BYTES_PER_SECOND, 1e3 / 8.0),
+    ("Mbit/s", BYTES_PER_SECOND, 1e6 / 8.0),
+    ("Gbit/s", BYTES_PER_SECOND, 1e9 / 8.0),
+    (HERTZ, HERTZ, 1.0),
+    ("kHz", HERTZ, 1e3),
+    ("MHz", HERTZ, 1e6),
+    ("Mhz", HERTZ, 1e6),
+    ("GHz", HERTZ, 1e9),
+    (RPM, RPM, 1.0),
+    (VOLT, VOLT, 1.0),
+    ("mV", VOLT, 0.001),
+    ("A", "A", 1.0),
+    (WATT, WATT, 1.0),
+    (JOULE, JOULE, 1.0),
+    ("s", "s", 1.0),
+];
+
+/// Units a value of a base unit is scaled to for display, with the factor to divide the value by, largest unit first
+const DISPLAY_UNITS: &[(&str, &str, f64)] = &[
+    ("TB", BYTES, KIB * KIB * KIB * KIB),
+    ("GB", BYTES, KIB * KIB * KIB),
+    ("MB", BYTES, KIB * KIB),
+    ("KB", BYTES, KIB),
+    ("TB/s", BYTES_PER_SECOND, KIB * KIB * KIB * KIB),
+    ("GB/s", BYTES_PER_SECOND, KIB * KIB * KIB),
+    ("MB/s", BYTES_PER_SECOND, KIB * KIB),
+    ("KB/s", BYTES_PER_SECOND, KIB),
+    ("GHz", HERTZ, 1e9),
+    ("MHz", HERTZ, 1e6),
+    ("kHz", HERTZ, 1e3),
+];
+
+/// Converts the value to the base unit of its unit, e.g. 2 MB/s to 2097152 B/s.
+/// Returns None if the unit is unknown.
+pub fn to_base_unit(value: f64, unit: &str) -> Option<(f64, &'static str)> {
+    let unit = unit.trim();
+
+    UNITS
+        .iter()
+        .find(|(known_unit, _, _)| *known_unit == unit)
+        .map(|(_, base_unit, factor)| (value * factor, *base_unit))
+}
+
+/// Converts the value from its unit to the target unit, e.g. 1500 MHz to 1.5 GHz or 20 °C to 68 °F.
+/// Returns None if the units are unknown or do not share the same base unit.
+pub fn convert(value: f64, unit: &str, target_unit: &str) -> Option<f64> {
+    let (base_value, base_unit) = to_base_unit(value, unit)?;
+
+    if base_unit == CELSIUS && target_unit.trim() == FAHRENHEIT {
+        return Some(base_value * 1.8 + 32.0);
+    }
+
+    match to_base_unit(1.0, target_unit)? {
+        (factor, target_base_unit) if target_base_unit == base_unit => Some(base_value / factor),
+        _ => None,
+    }
+}
+
+/// Returns the largest unit in which the value of a base unit is at least 1, with the factor to divide the value by,
+/// e.g. GHz and 1e9 for 3400000000 Hz.
+/// Returns None if the value is readable in its unit, or the unit is not scaled for display.
+pub fn get_display_unit(value: f64, unit: &str) -> Option<(&'static str, f64)> {
+    DISPLAY_UNITS
+        .iter()
+        .find(|(_, base_unit, factor)| *base_unit == unit.trim() && value.abs() >= *factor)
+        .map(|(display_unit, _, factor)| (*display_unit, *factor))
+}
+
+/// Formats a scaled value with at most two decimal places, e.g. 3.4 instead of 3.40
+pub fn format_display_value(value: f64) -> String {
+    let formatted = format!("{value:.2}");
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
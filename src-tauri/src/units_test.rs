@@ -0,0 +1,96 @@
+use assertor::{assert_that, EqualityAssertion};
+use sensor_core::{SensorType, SensorValue};
+
+use crate::{text, units};
+
+fn create_sensor_value(value: &str, unit: &str) -> SensorValue {
+    SensorValue {
+        id: "cpu0-frequency".to_string(),
+        value: value.to_string(),
+        unit: unit.to_string(),
+        label: "CPU 0 Frequency".to_string(),
+        sensor_type: SensorType::Number,
+    }
+}
+
+#[test]
+fn test_to_base_unit() {
+    // GIVEN are values in scaled and legacy units
+
+    // WHEN converting them to their base unit
+    let megabytes = units::to_base_unit(2.0, "MB");
+    let megabits = units::to_base_unit(8.0, "Mbit/s");
+    let megahertz = units::to_base_unit(3400.0, " MHz ");
+    let celsius = units::to_base_unit(45.0, "C");
+    let unknown = units::to_base_unit(1.0, "furlong");
+
+    // THEN the values should be in bytes, bytes per second, hertz and celsius
+    assert_that!(megabytes).is_equal_to(Some((2097152.0, "B")));
+    assert_that!(megabits).is_equal_to(Some((1e6, "B/s")));
+    assert_that!(megahertz).is_equal_to(Some((3.4e9, "Hz")));
+    assert_that!(celsius).is_equal_to(Some((45.0, "°C")));
+    assert_that!(unknown).is_equal_to(None);
+}
+
+#[test]
+fn test_convert() {
+    // GIVEN are values and the units to convert them to
+
+    // WHEN converting them
+    let gigahertz = units::convert(1500.0, "MHz", "GHz");
+    let fahrenheit = units::convert(20.0, "°C", "°F");
+    let incompatible = units::convert(1.0, "MB", "GHz");
+
+    // THEN values should only be converted between units of the same base unit
+    assert_that!(gigahertz).is_equal_to(Some(1.5));
+    assert_that!(fahrenheit).is_equal_to(Some(68.0));
+    assert_that!(incompatible).is_equal_to(None);
+}
+
+#[test]
+fn test_get_display_unit() {
+    // GIVEN are values in their base unit
+
+    // WHEN getting their display units
+    let frequency = units::get_display_unit(3400000000.0, "Hz");
+    let memory = units::get_display_unit(16.0 * 1024.0 * 1024.0 * 1024.0, "B");
+    let rate = units::get_display_unit(1536.0, "B/s");
+    let small = units::get_display_unit(512.0, "B");
+    let temperature = units::get_display_unit(45000.0, "°C");
+
+    // THEN the largest unit in which the value is at least 1 should be used,
+    // and values that are readable or not scalable should be kept
+    assert_that!(frequency).is_equal_to(Some(("GHz", 1e9)));
+    assert_that!(memory).is_equal_to(Some(("GB", 1024.0 * 1024.0 * 1024.0)));
+    assert_that!(rate).is_equal_to(Some(("KB/s", 1024.0)));
+    assert_that!(small).is_equal_to(None);
+    assert_that!(temperature).is_equal_to(None);
+}
+
+#[test]
+fn test_format_display_value() {
+    // GIVEN are scaled values
+
+    // WHEN formatting them
+    let formatted = [3.4, 2.0, 1.23456, 0.001, 100.0].map(units::format_display_value);
+
+    // THEN at most two decimal places should be shown, without trailing zeros
+    assert_that!(formatted).is_equal_to(["3.4", "2", "1.23", "0", "100"].map(String::from));
+}
+
+#[test]
+fn test_auto_scale_unit() {
+    // GIVEN are a frequency in hertz, a readable frequency and a text value
+
+    // WHEN scaling them to readable units
+    let frequency = text::auto_scale_unit(create_sensor_value("3400000000", "Hz"));
+    let readable = text::auto_scale_unit(create_sensor_value("800", "Hz"));
+    let text = text::auto_scale_unit(create_sensor_value("unknown", "Hz"));
+
+    // THEN only values that are not readable in their unit should be scaled
+    assert_that!((frequency.value, frequency.unit))
+        .is_equal_to(("3.4".to_string(), "GHz".to_string()));
+    assert_that!((readable.value, readable.unit))
+        .is_equal_to(("800".to_string(), "Hz".to_string()));
+    assert_that!((text.value, text.unit)).is_equal_to(("unknown".to_string(), "Hz".to_string()));
+}
@@ -20,6 +20,15 @@ pub fn pretty_bytes(value: f64) -> (f64, String) {
     (value, units[unit].to_string())
 }
 
+/// Formats the number with at most two decimal places, without trailing zeros
+pub fn format_number(value: f64) -> String {
+    let formatted = format!("{:.2}", value);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
 /// Convert an rgb image to a png buffer
 pub fn rgb_to_jpeg_bytes(image: ImageBuffer<Rgba<u8>, Vec<u8>>) -> Vec<u8> {
     let mut buf = Vec::new();
@@ -7,6 +7,10 @@ const { open, save } = window.__TAURI__.dialog;
 const sensorSelectionDialog = document.getElementById("sensor-selection-dialog");
 const sensorSelectionTable = document.getElementById("sensor-selection-table");
 const txtSensorSelectionTableFilterInput = document.getElementById("sensor-selection-table-filter-input");
+const deviceDiscoveryDialog = document.getElementById("device-discovery-dialog");
+const deviceDiscoveryTableBody = document.querySelector("#device-discovery-table tbody");
+const sensorProviderDialog = document.getElementById("sensor-provider-dialog");
+const sensorProviderTableBody = document.querySelector("#sensor-provider-table tbody");
 
 // Network port selection
 const cmbNetworkPorts = document.getElementById("main-network-ports-select");
@@ -14,9 +18,11 @@ const lcdBasePanel = document.getElementById("lcd-panel");
 
 // Main buttons
 const btnAddNetworkDevice = document.getElementById("btn-add-network-device");
+const btnDiscoverNetworkDevices = document.getElementById("btn-discover-network-devices");
 const btnSaveNetworkDevice = document.getElementById("lcd-btn-save-network-device");
 const btnToggleLivePreview = document.getElementById("btn-lcd-toggle-live-preview");
 const btnRemoveNetworkDevice = document.getElementById("lcd-btn-remove-network-device");
+const btnSensorProviders = document.getElementById("btn-sensor-providers");
 const btnExportConfig = document.getElementById("btn-export-config");
 const btnImportConfig = document.getElementById("btn-import-config");
 const panelKillSwitch = document.getElementById("kill-switch-input");
@@ -25,6 +31,10 @@ const btnActivateSync = document.getElementById("main-chk-transfer-active");
 // LCD designer
 const txtDeviceName = document.getElementById("lcd-txt-device-name");
 const txtDeviceNetworkAddress = document.getElementById("lcd-txt-device-network-address");
+const txtDeviceNetworkPort = document.getElementById("lcd-txt-device-network-port");
+const txtDevicePushRate = document.getElementById("lcd-txt-device-push-rate");
+const chkDeviceDeltaFrames = document.getElementById("lcd-chk-device-delta-frames");
+const chkDeviceAutoScaleUnits = document.getElementById("lcd-chk-device-auto-scale-units");
 const txtDisplayResolutionWidth = document.getElementById("lcd-txt-resolution-width");
 const txtDisplayResolutionHeight = document.getElementById("lcd-txt-resolution-height");
 const designerPane = document.getElementById("lcd-designer-pane");
@@ -83,6 +93,7 @@ const cmbGraphSensorIdSelection = document.getElementById("lcd-cmb-number-sensor
 const btnGraphSensorIdSelectionDialog = document.getElementById("lcd-graph-config-btn-select-sensor-id");
 const txtGraphMinValue = document.getElementById("lcd-txt-element-graph-min-value");
 const txtGraphMaxValue = document.getElementById("lcd-txt-element-graph-max-value");
+const txtGraphWindow = document.getElementById("lcd-txt-element-graph-window");
 const txtGraphWidth = document.getElementById("lcd-graph-width");
 const txtGraphHeight = document.getElementById("lcd-graph-height");
 const cmbGraphType = document.getElementById("lcd-graph-type");
@@ -115,6 +126,9 @@ let currentNetworkDeviceId = null;
 const DESIGNER_ID_PREFIX = "designer-";
 const LIST_ID_PREFIX = "list-";
 
+// Smallest plausible threshold per base unit, smaller thresholds were most likely set for the former units like MB or MHz
+const BASE_UNIT_MIN_THRESHOLDS = {"B": 1024, "B/s": 1024, "Hz": 1000000};
+
 // Element types
 const ELEMENT_TYPE_TEXT = "text";
 const ELEMENT_TYPE_STATIC_IMAGE = "static-image";
@@ -148,6 +162,7 @@ const ATTR_STATIC_IMAGE_HEIGHT = "data-static-image-height";
 const ATTR_GRAPH_SENSOR_ID = "data-graph-sensor-id";
 const ATTR_GRAPH_MIN_VALUE = "data-graph-min-value";
 const ATTR_GRAPH_MAX_VALUE = "data-graph-max-value";
+const ATTR_GRAPH_WINDOW = "data-graph-window";
 const ATTR_GRAPH_WIDTH = "data-graph-width";
 const ATTR_GRAPH_HEIGHT = "data-graph-height";
 const ATTR_GRAPH_TYPE = "data-graph-type";
@@ -192,7 +207,9 @@ window.addEventListener("DOMContentLoaded", () => {
 
     // Register button click events
     btnAddNetworkDevice.addEventListener("click", createNetworkPort);
+    btnDiscoverNetworkDevices.addEventListener("click", discoverNetworkDevices);
     btnRemoveNetworkDevice.addEventListener("click", removeNetworkDevice);
+    btnSensorProviders.addEventListener("click", showSensorProviderDialog);
     btnExportConfig.addEventListener("click", exportConfig);
     btnImportConfig.addEventListener("click", importConfig);
     btnSaveNetworkDevice.addEventListener("click", onSave);
@@ -227,6 +244,7 @@ window.addEventListener("DOMContentLoaded", () => {
 
     // If lost focus, check network config
     txtDeviceNetworkAddress.addEventListener("focusout", verifyNetworkAddress);
+    txtDeviceNetworkPort.addEventListener("focusout", verifyNetworkAddress);
 
     // Register drag dropping
     designerPane.addEventListener('dragover', (event) => event.preventDefault());
@@ -410,7 +428,10 @@ function exportConfig() {
         (selected) => {
             // If the user selected a file, save the config to the file
             if (typeof selected === "string" && selected !== "") {
-                invoke('export_config', {filePath: selected});
+                invoke('export_config', {filePath: selected})
+                    .catch((error) => {
+                        alert("Error while exporting config. " + error);
+                    });
             } else {
                 console.log("No file selected");
             }
@@ -493,9 +514,29 @@ function changeMoveUnit() {
     }
 }
 
+// Returns the integer value of the number input, or null if it is empty or outside of its min and max
+function getIntegerInputValue(input) {
+    const value = Number(input.value);
+    if (input.value === "" || !Number.isInteger(value)) {
+        return null;
+    }
+
+    return value >= Number(input.min) && value <= Number(input.max) ? value : null;
+}
+
 function verifyNetworkAddress() {
+    // An invalid port can not be parsed by the backend
+    const port = getIntegerInputValue(txtDeviceNetworkPort);
+    if (port === null) {
+        txtDeviceNetworkAddress.classList.add("invalid");
+        return;
+    }
+
     // Call backend to verify network address
-    invoke('verify_network_address', {address: txtDeviceNetworkAddress.value}).then(
+    invoke('verify_network_address', {
+        address: txtDeviceNetworkAddress.value,
+        port: port
+    }).then(
         (isValid) => {
             if (isValid) {
                 txtDeviceNetworkAddress.classList.remove("invalid");
@@ -643,6 +684,126 @@ function createNetworkPort() {
         );
 }
 
+// Searches the local network for displays and shows them in a dialog
+function discoverNetworkDevices() {
+    btnDiscoverNetworkDevices.disabled = true;
+
+    invoke('discover_network_devices')
+        .then((discoveredDevices) => {
+            discoveredDevices = JSON.parse(discoveredDevices);
+
+            if (discoveredDevices.length === 0) {
+                alert("No displays found in the local network.");
+                return;
+            }
+
+            // Fill discovered devices into table
+            deviceDiscoveryTableBody.innerHTML = "";
+            discoveredDevices.forEach((discoveredDevice) => {
+                const row = document.createElement("tr");
+                const name = document.createElement("td");
+                const address = document.createElement("td");
+                const resolution = document.createElement("td");
+
+                row.classList.add("device-discovery-table-row");
+                row.addEventListener("click", () => {
+                    deviceDiscoveryDialog.close();
+                    createNetworkPortFromDiscovery(discoveredDevice);
+                });
+                name.innerText = discoveredDevice.name;
+                address.innerText = discoveredDevice.address + ":" + discoveredDevice.port;
+                resolution.innerText = discoveredDevice.resolution_width + "x" + discoveredDevice.resolution_height;
+
+                row.appendChild(name);
+                row.appendChild(address);
+                row.appendChild(resolution);
+
+                deviceDiscoveryTableBody.appendChild(row);
+            });
+
+            deviceDiscoveryDialog.showModal();
+        })
+        .catch((error) => {
+            alert("Error while searching for displays. " + error);
+        })
+        .finally(() => {
+            btnDiscoverNetworkDevices.disabled = false;
+        });
+}
+
+// Shows all sensor providers in a dialog, where they can be enabled or disabled
+function showSensorProviderDialog() {
+    invoke('get_sensor_providers')
+        .then((sensorProviders) => {
+            sensorProviders = JSON.parse(sensorProviders);
+
+            // Fill sensor providers into table
+            sensorProviderTableBody.innerHTML = "";
+            sensorProviders.forEach((sensorProvider) => {
+                const row = document.createElement("tr");
+                const name = document.createElement("td");
+                const interval = document.createElement("td");
+                const sensorCount = document.createElement("td");
+                const enabled = document.createElement("td");
+                const chkEnabled = document.createElement("input");
+
+                name.innerText = sensorProvider.name;
+                interval.innerText = sensorProvider.interval_ms + "ms";
+                sensorCount.innerText = sensorProvider.enabled ? sensorProvider.sensor_count : "-";
+                chkEnabled.type = "checkbox";
+                chkEnabled.checked = sensorProvider.enabled;
+                chkEnabled.addEventListener("change", () => {
+                    setSensorProviderEnabled(sensorProvider.name, chkEnabled);
+                });
+
+                enabled.appendChild(chkEnabled);
+                row.appendChild(name);
+                row.appendChild(interval);
+                row.appendChild(sensorCount);
+                row.appendChild(enabled);
+
+                sensorProviderTableBody.appendChild(row);
+            });
+
+            sensorProviderDialog.showModal();
+        })
+        .catch((error) => {
+            alert("Error while loading sensor providers. " + error);
+        });
+}
+
+// Enables or disables a sensor provider, reverts the checkbox if this failed
+function setSensorProviderEnabled(providerName, chkEnabled) {
+    invoke('set_sensor_provider_enabled', {providerName: providerName, enabled: chkEnabled.checked})
+        .then(() => {
+            // Reload sensor values, as the available sensors changed
+            loadSensorValues();
+        })
+        .catch((error) => {
+            chkEnabled.checked = !chkEnabled.checked;
+            alert("Error while changing the sensor provider. " + error);
+        });
+}
+
+// Creates a new network device from a discovered display
+function createNetworkPortFromDiscovery(discoveredDevice) {
+    invoke('create_network_device_config_from_discovery', {discoveredDevice: discoveredDevice})
+        .then((networkDeviceId) => {
+            currentNetworkDeviceId = networkDeviceId;
+
+            // Load all device configs from config
+            loadDeviceConfigs().then(() => {
+                // Select new network device
+                let liElement = document.getElementById(networkDeviceId);
+                onNetDeviceSelected(liElement);
+            });
+        })
+        .catch((error) => {
+                alert("Error while creating a new network device. " + error);
+            }
+        );
+}
+
 async function loadDeviceConfigs() {
     // Load config from backend
     return invoke('get_app_config')
@@ -700,6 +861,12 @@ function buildGraphConfigFromAttributes(listItem) {
     };
 }
 
+// Returns the time window in seconds of the graph list item, or null for the complete history
+function getGraphWindowFromAttributes(listItem) {
+    const windowSeconds = parseInt(listItem.getAttribute(ATTR_GRAPH_WINDOW));
+    return isNaN(windowSeconds) || windowSeconds <= 0 ? null : windowSeconds;
+}
+
 // Builds the graph config values from the list item attributes
 function buildConditionalImageConfigFromAttributes(listItem) {
     return {
@@ -795,6 +962,17 @@ function saveConfig() {
         return getElementConfig(listItem);
     });
 
+    // Collect the time windows of the graphs, by element id
+    const graphWindowSeconds = {};
+    lcdDesignerPlacedElementsListItemsArray
+        .filter((listItem) => listItem.getAttribute(ATTR_ELEMENT_TYPE) === ELEMENT_TYPE_GRAPH)
+        .forEach((listItem) => {
+            const windowSeconds = getGraphWindowFromAttributes(listItem);
+            if (windowSeconds !== null) {
+                graphWindowSeconds[listItem.getAttribute(ATTR_ELEMENT_ID)] = windowSeconds;
+            }
+        });
+
     // Build lcd config object, with integers
     const displayConfig = {
         resolution_width: parseInt(txtDisplayResolutionWidth.value),
@@ -802,10 +980,28 @@ function saveConfig() {
         elements: displayElements,
     }
 
+    // Verify the numeric device settings, the backend would fail to parse them otherwise
+    const pushRateMs = getIntegerInputValue(txtDevicePushRate);
+    if (pushRateMs === null) {
+        alert("Update interval must be a number between " + txtDevicePushRate.min + "ms and " + txtDevicePushRate.max + "ms.");
+        return;
+    }
+
+    const port = getIntegerInputValue(txtDeviceNetworkPort);
+    if (port === null) {
+        alert("Port must be a number between " + txtDeviceNetworkPort.min + " and " + txtDeviceNetworkPort.max + ".");
+        return;
+    }
+
     invoke('save_app_config', {
         id: currentNetworkDeviceId,
         name: txtDeviceName.value,
         address: txtDeviceNetworkAddress.value,
+        port: port,
+        pushRateMs: pushRateMs,
+        deltaFrames: chkDeviceDeltaFrames.checked,
+        autoScaleUnits: chkDeviceAutoScaleUnits.checked,
+        graphWindowSeconds: graphWindowSeconds,
         displayConfig: JSON.stringify(displayConfig),
     }).catch(
         (error) => {
@@ -835,6 +1031,10 @@ function onNetDeviceSelected(element) {
             // Set name, host and resolution
             txtDeviceName.value = portConfig.name;
             txtDeviceNetworkAddress.value = portConfig.address;
+            txtDeviceNetworkPort.value = portConfig.port;
+            txtDevicePushRate.value = portConfig.push_rate_ms;
+            chkDeviceDeltaFrames.checked = portConfig.delta_frames;
+            chkDeviceAutoScaleUnits.checked = portConfig.auto_scale_units;
 
             // Set active sync state
             btnActivateSync.checked = portConfig.active;
@@ -897,6 +1097,11 @@ function loadDisplayConfig(networkDeviceId) {
             // Add elements to designer pane and list
             // iterate elements with index
             displayConfig.elements.forEach((element, index) => {
+                // The time window of graphs is stored next to the display config
+                if (element.graph_config) {
+                    element.graph_config.window_seconds = portConfig.graph_window_seconds?.[element.id] ?? "";
+                }
+
                 // Add element to list
                 addElementToList(element.id, element.x, element.y, element.name, element.element_type, element.text_config, element.image_config, element.graph_config, element.conditional_image_config);
 
@@ -904,6 +1109,8 @@ function loadDisplayConfig(networkDeviceId) {
                 addElementToDesignerPane(index, element.id, element.element_type, element.x, element.y, element.text_config, element.image_config, element.graph_config, element.conditional_image_config);
             });
 
+            warnAboutFormerUnitThresholds(displayConfig.elements);
+
             // If there are elements, select the first one
             if (displayConfig.elements.length > 0) {
                 setSelectedElement(document.querySelector("#lcd-designer-placed-elements li"));
@@ -916,6 +1123,30 @@ function loadDisplayConfig(networkDeviceId) {
     )
 }
 
+// Warns if graphs or conditional images compare a sensor in base units against thresholds of the former units
+function warnAboutFormerUnitThresholds(elements) {
+    const affectedElementNames = elements.filter((element) => {
+        const thresholdConfig = element.graph_config ?? element.conditional_image_config;
+        if (!thresholdConfig) {
+            return false;
+        }
+
+        const sensor = sensorValues.find((sensorValue) => sensorValue.id === thresholdConfig.sensor_id);
+        const minThreshold = sensor ? BASE_UNIT_MIN_THRESHOLDS[sensor.unit] : undefined;
+        if (minThreshold === undefined) {
+            return false;
+        }
+
+        const maxSensorValue = Number(thresholdConfig.max_sensor_value);
+        return maxSensorValue > 0 && maxSensorValue < minThreshold;
+    }).map((element) => element.name);
+
+    if (affectedElementNames.length > 0) {
+        alert("The min and max values of " + affectedElementNames.join(", ") + " look like they are set for former units like MB or MHz. " +
+            "Sensor values are now reported in B, B/s and Hz, please update these values.");
+    }
+}
+
 function addElementToList(elementId, positionX, positionY, elementName, elementType, elementTextConfig, elementImageConfig, elementGraphConfig, elementConditionalImageConfig) {
     const liElement = document.createElement("li");
 
@@ -952,6 +1183,7 @@ function addElementToList(elementId, positionX, positionY, elementName, elementT
         liElement.setAttribute(ATTR_GRAPH_SENSOR_ID, elementGraphConfig.sensor_id);
         liElement.setAttribute(ATTR_GRAPH_MIN_VALUE, elementGraphConfig.min_sensor_value);
         liElement.setAttribute(ATTR_GRAPH_MAX_VALUE, elementGraphConfig.max_sensor_value);
+        liElement.setAttribute(ATTR_GRAPH_WINDOW, elementGraphConfig.window_seconds ?? "");
         liElement.setAttribute(ATTR_GRAPH_WIDTH, elementGraphConfig.width);
         liElement.setAttribute(ATTR_GRAPH_HEIGHT, elementGraphConfig.height);
         liElement.setAttribute(ATTR_GRAPH_TYPE, elementGraphConfig.graph_type);
@@ -1282,6 +1514,7 @@ function updateCurrentElement() {
         listEntryElement.setAttribute(ATTR_GRAPH_SENSOR_ID, cmbGraphSensorIdSelection.value);
         listEntryElement.setAttribute(ATTR_GRAPH_MIN_VALUE, txtGraphMinValue.value);
         listEntryElement.setAttribute(ATTR_GRAPH_MAX_VALUE, txtGraphMaxValue.value);
+        listEntryElement.setAttribute(ATTR_GRAPH_WINDOW, txtGraphWindow.value);
         listEntryElement.setAttribute(ATTR_GRAPH_WIDTH, txtGraphWidth.value);
         listEntryElement.setAttribute(ATTR_GRAPH_HEIGHT, txtGraphHeight.value);
         listEntryElement.setAttribute(ATTR_GRAPH_TYPE, cmbGraphType.value);
@@ -1316,7 +1549,8 @@ function updateCurrentElement() {
             invoke('get_text_preview_image', {
                 imageWidth: parseInt(txtDisplayResolutionWidth.value),
                 imageHeight: parseInt(txtDisplayResolutionHeight.value),
-                textConfig: buildTextConfigFromAttributes(listEntryElement)
+                textConfig: buildTextConfigFromAttributes(listEntryElement),
+                autoScaleUnits: chkDeviceAutoScaleUnits.checked
             })
                 .then(response => {
                     designerElement.src = "data:image/png;base64," + response;
@@ -1331,7 +1565,8 @@ function updateCurrentElement() {
             designerElement.style.width = txtGraphWidth.value + "px";
             designerElement.style.height = txtGraphHeight.value + "px";
             invoke('get_graph_preview_image', {
-                graphConfig: buildGraphConfigFromAttributes(listEntryElement)
+                graphConfig: buildGraphConfigFromAttributes(listEntryElement),
+                windowSeconds: getGraphWindowFromAttributes(listEntryElement)
             })
                 .then(response => {
                     designerElement.src = "data:image/png;base64," + response;
@@ -1363,7 +1598,8 @@ function addElementToDesignerPane(zIndex, elementId, sensorType, positionX, posi
             invoke('get_text_preview_image', {
                 imageWidth: parseInt(txtDisplayResolutionWidth.value),
                 imageHeight: parseInt(txtDisplayResolutionHeight.value),
-                textConfig: buildTextConfigFromAttributes(selectedListElement)
+                textConfig: buildTextConfigFromAttributes(selectedListElement),
+                autoScaleUnits: chkDeviceAutoScaleUnits.checked
             }).then(response => {
                 designerElement.src = "data:image/png;base64," + response;
             })
@@ -1382,7 +1618,8 @@ function addElementToDesignerPane(zIndex, elementId, sensorType, positionX, posi
             designerElement.style.height = elementGraphConfig.height + "px";
             invoke('get_graph_preview_image', {
                 networkDeviceId: currentNetworkDeviceId,
-                graphConfig: buildGraphConfigFromAttributes(selectedListElement)
+                graphConfig: buildGraphConfigFromAttributes(selectedListElement),
+                windowSeconds: getGraphWindowFromAttributes(selectedListElement)
             }).then(response => {
                 designerElement.src = "data:image/png;base64," + response;
             })
@@ -1619,6 +1856,7 @@ function createElementByInputs() {
         sensor_id: cmbGraphSensorIdSelection.value,
         min_sensor_value: txtGraphMinValue.value,
         max_sensor_value: txtGraphMaxValue.value,
+        window_seconds: txtGraphWindow.value,
         width: txtGraphWidth.value,
         height: txtGraphHeight.value,
         graph_type: cmbGraphType.value,
@@ -1773,6 +2011,7 @@ function showSelectedElementDetail() {
         cmbGraphSensorIdSelection.value = selectedListElement.getAttribute(ATTR_GRAPH_SENSOR_ID);
         txtGraphMinValue.value = selectedListElement.getAttribute(ATTR_GRAPH_MIN_VALUE);
         txtGraphMaxValue.value = selectedListElement.getAttribute(ATTR_GRAPH_MAX_VALUE);
+        txtGraphWindow.value = selectedListElement.getAttribute(ATTR_GRAPH_WINDOW);
         txtGraphWidth.value = selectedListElement.getAttribute(ATTR_GRAPH_WIDTH);
         txtGraphHeight.value = selectedListElement.getAttribute(ATTR_GRAPH_HEIGHT);
         cmbGraphType.value = selectedListElement.getAttribute(ATTR_GRAPH_TYPE);